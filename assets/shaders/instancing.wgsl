@@ -71,6 +71,7 @@ var<uniform> params: ComputeParams;
 
 @group(1) @binding(0) var<storage, read> all_tiles : TileStorage;
 @group(2) @binding(0) var<storage, read_write> visble_tiles_cp : TileStorage;
+@group(2) @binding(1) var<storage, read_write> visible_tile_indices_cp : array<u32>;
 
 @compute
 @workgroup_size(16, 16, 1)
@@ -173,7 +174,9 @@ fn calcvisibility(@builtin(global_invocation_id) global_id: vec3<u32>) {
         //calc index for visible tiles array end
 
 
-    	visble_tiles_cp.tiles[visible_index] = all_tiles.tiles[index.y * params.map_size.x + index.x];
+        let map_index = index.y * params.map_size.x + index.x;
+    	visble_tiles_cp.tiles[visible_index] = all_tiles.tiles[map_index];
+    	visible_tile_indices_cp[visible_index] = u32(map_index);
 }
 
 
@@ -190,15 +193,54 @@ struct VertexInput {
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) @interpolate(flat) visibility: u32,
 }
 
 struct CameraUniform {
-    view_proj: mat4x4<f32>
+    view_proj: mat4x4<f32>,
+    time: f32,
 };
 @group(1) @binding(0)
 var<uniform> camera: CameraUniform;
 
+struct TileAnimation {
+    first_frame: u32,
+    frame_count: u32,
+    frame_duration: f32,
+    mode: u32, //0 loop, 1 ping pong
+};
+
+@group(3) @binding(0) var<storage, read> animation_frames : array<u32>;
+@group(3) @binding(1) var<storage, read> animations : array<TileAnimation>;
+
+//returns the atlas cell offset of the current frame, animation id 0 means the tile is static
+fn current_animation_frame(animation_id: u32) -> vec2<u32> {
+    if (animation_id == 0u) {
+        return vec2<u32>(0u, 0u);
+    }
+    let animation = animations[animation_id - 1u];
+    var frame = u32(camera.time / animation.frame_duration);
+    if (animation.mode == 1u && animation.frame_count > 1u) {
+        let period = animation.frame_count * 2u - 2u;
+        frame = frame % period;
+        if (frame >= animation.frame_count) {
+            frame = period - frame;
+        }
+    } else {
+        frame = frame % animation.frame_count;
+    }
+    let packed = animation_frames[animation.first_frame + frame];
+    return vec2<u32>(packed & 0x000000ffu, (packed & 0x0000ff00u) >> 8u);
+}
+
 @group(2) @binding(0) var<storage, read> visble_tiles : TileStorage;
+@group(2) @binding(1) var<storage, read> visible_tile_indices : array<u32>;
+//one byte per tile, 0 unexplored, 1 explored, 2 visible
+@group(2) @binding(2) var<storage, read> fog_of_war : array<u32>;
+
+fn tile_visibility(map_index: u32) -> u32 {
+    return (fog_of_war[map_index / 4u] >> ((map_index % 4u) * 8u)) & 0xffu;
+}
 @vertex
 fn vs_main(
     input: VertexInput,
@@ -208,11 +250,12 @@ fn vs_main(
     let tile = visble_tiles.tiles[tileID];
     var output : VertexOutput;
 
-    let atlasCoordinate = vec3<u32>(
+    var atlasCoordinate = vec3<u32>(
         tile.AtlasCoord & 0x000000ffu,
         (tile.AtlasCoord & 0x0000ff00u) >> 8u,
-        tile.AtlasCoord >> 16u
+        (tile.AtlasCoord & 0x00ff0000u) >> 16u
     );
+    atlasCoordinate = atlasCoordinate + vec3<u32>(current_animation_frame(tile.AtlasCoord >> 24u), 0u);
 
     let imageSize = vec2<f32>(30.0, 64.0);
 
@@ -229,6 +272,7 @@ fn vs_main(
       (input.position.x / numberOfTextures.x) + (1.0 / numberOfTextures.x * f32(atlasCoordinate.x)),
       (input.position.y / numberOfTextures.y) + (1.0 / numberOfTextures.y * f32(atlasCoordinate.y))
     );
+    output.visibility = tile_visibility(visible_tile_indices[tileID]);
     //output.tex_coords = input.tex_coords.xy;
     return output;
 }
@@ -249,6 +293,12 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     if(color.a <= 0.0){
         discard;
     }
+    if(in.visibility == 0u){
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    if(in.visibility == 1u){
+        return vec4<f32>(color.rgb * 0.45, color.a);
+    }
     return color;
 }
 
@@ -0,0 +1,44 @@
+struct ScreenUniform {
+    size: vec2<f32>,
+};
+@group(0) @binding(0)
+var<uniform> screen: ScreenUniform;
+@group(0) @binding(1)
+var t_minimap: texture_2d<f32>;
+@group(0) @binding(2)
+var s_minimap: sampler;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+//==============================================================================
+// Vertex shader, positions are in window pixels
+//==============================================================================
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    let ndc = vec2<f32>(input.position.x / screen.size.x * 2.0 - 1.0, 1.0 - input.position.y / screen.size.y * 2.0);
+    output.position = vec4<f32>(ndc, 0.0, 1.0);
+    output.uv = input.uv;
+    return output;
+}
+
+//==============================================================================
+// Fragment shaders
+//==============================================================================
+@fragment
+fn fs_texture(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_minimap, s_minimap, in.uv);
+}
+
+@fragment
+fn fs_view(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
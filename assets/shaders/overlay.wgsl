@@ -0,0 +1,57 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    time: f32,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct InstanceInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+//corners of the tile diamond in world pixels, clockwise starting at the top
+fn diamond_corner(corner: u32) -> vec2<f32> {
+    switch (corner % 4u) {
+        case 0u: { return vec2<f32>(0.0, -8.0); }
+        case 1u: { return vec2<f32>(16.0, 0.0); }
+        case 2u: { return vec2<f32>(0.0, 8.0); }
+        default: { return vec2<f32>(-16.0, 0.0); }
+    }
+}
+
+fn to_clip(instance: InstanceInput, corner: u32) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = camera.view_proj * vec4<f32>(instance.position + diamond_corner(corner), 0.0, 1.0);
+    output.color = instance.color;
+    return output;
+}
+
+//==============================================================================
+// Vertex shaders
+//==============================================================================
+@vertex
+fn vs_fill(@builtin(vertex_index) vertex_index: u32, instance: InstanceInput) -> VertexOutput {
+    //two triangles: 0 1 2, 0 2 3
+    var corners = array<u32, 6>(0u, 1u, 2u, 0u, 2u, 3u);
+    return to_clip(instance, corners[vertex_index]);
+}
+
+@vertex
+fn vs_outline(@builtin(vertex_index) vertex_index: u32, instance: InstanceInput) -> VertexOutput {
+    //line list: 0-1 1-2 2-3 3-0
+    return to_clip(instance, (vertex_index + 1u) / 2u);
+}
+
+//==============================================================================
+// Fragment shader
+//==============================================================================
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
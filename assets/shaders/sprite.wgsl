@@ -0,0 +1,61 @@
+@group(0) @binding(0)
+var t_sprites: texture_2d_array<f32>;
+@group(0) @binding(1)
+var s_sprites: sampler;
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    time: f32,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct SpriteInput {
+    @location(0) position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) uv_min: vec2<f32>,
+    @location(3) uv_max: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+//corner of the sprite quad, two triangles: 0 1 2, 2 1 3
+fn quad_corner(vertex_index: u32) -> vec2<f32> {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    return corners[vertex_index];
+}
+
+//==============================================================================
+// Vertex shader, positions are in world pixels, the same space as the tiles
+//==============================================================================
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, sprite: SpriteInput) -> VertexOutput {
+    let corner = quad_corner(vertex_index);
+    let world = sprite.position + sprite.size * corner;
+    var output: VertexOutput;
+    output.position = camera.view_proj * vec4<f32>(world, 0.0, 1.0);
+    output.uv = mix(sprite.uv_min, sprite.uv_max, corner);
+    return output;
+}
+
+//==============================================================================
+// Fragment shader
+//==============================================================================
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_sprites, s_sprites, in.uv, 0);
+    if (color.a < 0.5) {
+        discard;
+    }
+    return color;
+}
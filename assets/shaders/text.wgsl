@@ -0,0 +1,80 @@
+struct ScreenUniform {
+    size: vec2<f32>,
+};
+@group(0) @binding(0)
+var<uniform> screen: ScreenUniform;
+@group(0) @binding(1)
+var t_font: texture_2d<f32>;
+@group(0) @binding(2)
+var s_font: sampler;
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    time: f32,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct GlyphInput {
+    @location(0) position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) uv_min: vec2<f32>,
+    @location(3) uv_max: vec2<f32>,
+    @location(4) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+//corner of the glyph quad, two triangles: 0 1 2, 2 1 3
+fn quad_corner(vertex_index: u32) -> vec2<f32> {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    return corners[vertex_index];
+}
+
+fn glyph_vertex(glyph: GlyphInput, corner: vec2<f32>, position: vec4<f32>) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = position;
+    output.uv = mix(glyph.uv_min, glyph.uv_max, corner);
+    output.color = glyph.color;
+    return output;
+}
+
+//==============================================================================
+// Vertex shaders
+//==============================================================================
+//positions are in window pixels
+@vertex
+fn vs_screen(@builtin(vertex_index) vertex_index: u32, glyph: GlyphInput) -> VertexOutput {
+    let corner = quad_corner(vertex_index);
+    let pixel = glyph.position + glyph.size * corner;
+    let ndc = vec2<f32>(pixel.x / screen.size.x * 2.0 - 1.0, 1.0 - pixel.y / screen.size.y * 2.0);
+    return glyph_vertex(glyph, corner, vec4<f32>(ndc, 0.0, 1.0));
+}
+
+//positions are in world pixels, the same space as the tiles
+@vertex
+fn vs_world(@builtin(vertex_index) vertex_index: u32, glyph: GlyphInput) -> VertexOutput {
+    let corner = quad_corner(vertex_index);
+    let world = glyph.position + glyph.size * corner;
+    return glyph_vertex(glyph, corner, camera.view_proj * vec4<f32>(world, 0.0, 1.0));
+}
+
+//==============================================================================
+// Fragment shader, the atlas only stores coverage
+//==============================================================================
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureSample(t_font, s_font, in.uv).r;
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
@@ -0,0 +1,74 @@
+//! Flat A* against the portal graph on generated maps, run with `cargo bench --bench pathfinding`.
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::time::{Duration, Instant};
+
+use castle_sim::components::cs_world::hierarchical_pathfinding::PortalGraph;
+use castle_sim::components::cs_world::pathfinding::{find_path, PathResult};
+use cgmath::Vector2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const QUERIES: usize = 20;
+
+fn per_query(duration: Duration) -> String {
+    format!("{:>9.3} ms", duration.as_secs_f64() * 1000.0 / QUERIES as f64)
+}
+
+fn main() {
+    for size in [256, 512, 1024] {
+        let mut grid = common::generated_grid(size, 1);
+        let mut rng = StdRng::seed_from_u64(2);
+        let queries: Vec<_> = (0..QUERIES)
+            .map(|_| (
+                Vector2::new(rng.gen_range(0..size), rng.gen_range(0..size)),
+                Vector2::new(rng.gen_range(0..size), rng.gen_range(0..size)),
+            ))
+            .collect();
+
+        let started = Instant::now();
+        let mut graph = PortalGraph::build(&grid);
+        let build = started.elapsed();
+
+        let started = Instant::now();
+        let flat: Vec<_> = queries.iter().map(|(start, goal)| find_path(&grid, *start, *goal)).collect();
+        let flat_time = started.elapsed();
+
+        let started = Instant::now();
+        let planned: Vec<_> = queries.iter().map(|(start, goal)| graph.find_path(&grid, *start, *goal)).collect();
+        let abstract_time = started.elapsed();
+
+        let started = Instant::now();
+        let refined = planned.iter().flatten().filter(|path| (*path).clone().refine_all(&grid).is_some()).count();
+        let refine_time = started.elapsed();
+
+        let mut ratio = 0.0;
+        let mut compared = 0;
+        for (flat, planned) in flat.iter().zip(planned.iter()) {
+            if let (PathResult::Found(flat), Some(planned)) = (flat, planned) {
+                if flat.cost > 0.0 {
+                    ratio += planned.cost() / flat.cost;
+                    compared += 1;
+                }
+            }
+        }
+
+        let started = Instant::now();
+        for y in 0..size / 2 {
+            let pos = Vector2::new(size / 2, y);
+            grid.set_cost(pos, None);
+            graph.mark_changed(pos);
+        }
+        graph.repair(&grid);
+        let repair = started.elapsed();
+
+        println!("{}x{} map, {} portals", size, size, graph.node_count());
+        println!("  build graph        {:>9.3} ms", build.as_secs_f64() * 1000.0);
+        println!("  flat A*            {} per query", per_query(flat_time));
+        println!("  portal search      {} per query", per_query(abstract_time));
+        println!("  full refinement    {} per query ({} paths)", per_query(refine_time), refined);
+        println!("  repair wall        {:>9.3} ms", repair.as_secs_f64() * 1000.0);
+        println!("  cost vs optimal    {:>9.3}", ratio / compared.max(1) as f32);
+    }
+}
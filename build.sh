@@ -1,3 +1,3 @@
 wasm-pack build --target web --out-name castle_sim --out-dir ../CastleSimWeb/src/assets/wasm --release
-cp -r ./assets ../CastleSimWeb/src/
+cargo run --release --bin pack_assets -- . ../CastleSimWeb/src/assets.pack
 rm ../CastleSimWeb/src/assets/wasm/.gitignore
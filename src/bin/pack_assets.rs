@@ -0,0 +1,48 @@
+//! Packs the `assets` directory into one asset pack for distribution.
+//!
+//! Usage: `cargo run --release --bin pack_assets -- [root] [output]`,
+//! `root` contains the `assets` directory and defaults to the current directory, `output` defaults to `assets.pack`.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use castle_sim::components::cs_io::ASSET_PACK;
+use castle_sim::components::cs_io::asset_pack::PackWriter;
+use castle_sim::components::cs_io::asset_path::AssetPath;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let root = PathBuf::from(args.next().unwrap_or_else(|| ".".to_string()));
+    let output = PathBuf::from(args.next().unwrap_or_else(|| ASSET_PACK.to_string()));
+
+    let mut files = Vec::new();
+    collect_files(&root.join("assets"), &mut files);
+    files.sort();
+
+    let mut writer = PackWriter::new();
+    for file in files.iter() {
+        let relative = file.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/");
+        let path = match AssetPath::new(&relative) {
+            Ok(path) => path,
+            Err(error) => {
+                eprintln!("skipping {}: {}", file.display(), error);
+                continue;
+            }
+        };
+        writer.add(path, fs::read(file).unwrap_or_else(|error| panic!("could not read {}: {}", file.display(), error)));
+    }
+    let bytes = writer.to_bytes();
+    fs::write(&output, &bytes).unwrap_or_else(|error| panic!("could not write {}: {}", output.display(), error));
+    println!("packed {} files into {} ({} bytes)", files.len(), output.display(), bytes.len());
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = fs::read_dir(dir).unwrap_or_else(|error| panic!("could not read {}: {}", dir.display(), error));
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::io;
+
+use thiserror::Error;
+
+use super::asset_path::{AssetPath, AssetPathError};
+use super::byte_reader::{ByteReader, UnexpectedEnd};
+
+const MAGIC: &[u8; 4] = b"CSPK";
+const VERSION: u16 = 1;
+/// Magic, version, entry count and index length.
+pub const HEADER_LEN: usize = 4 + 2 + 4 + 4;
+/// Index entry with a one byte path, a count larger than the index can hold is rejected before allocating.
+const MIN_ENTRY_LEN: usize = 2 + 1 + 8 + 4 + 4 + 4 + 1;
+
+#[derive(Error, Debug)]
+pub enum PackError {
+    #[error("not an asset pack")]
+    InvalidMagic,
+
+    #[error("unsupported asset pack version {0}")]
+    UnsupportedVersion(u16),
+
+    #[error("asset pack ends unexpectedly")]
+    UnexpectedEnd,
+
+    #[error("unknown compression {0} in asset pack")]
+    UnknownCompression(u8),
+
+    #[error("invalid path in asset pack: {0}")]
+    InvalidPath(#[from] AssetPathError),
+
+    #[error("{0} could not be decompressed")]
+    Decompress(AssetPath),
+
+    #[error("checksum of {0} does not match, the asset pack is damaged")]
+    ChecksumMismatch(AssetPath),
+
+    #[error("encountered an io error while reading the asset pack: {0}")]
+    Io(#[from] io::Error),
+}
+
+impl From<UnexpectedEnd> for PackError {
+    fn from(_: UnexpectedEnd) -> Self {
+        PackError::UnexpectedEnd
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Deflate,
+}
+
+/// Where an asset is stored in the pack, `offset` counts from the start of the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackEntry {
+    pub offset: u64,
+    pub stored_size: u32,
+    pub size: u32,
+    /// crc32 of the uncompressed data.
+    pub checksum: u32,
+    pub compression: Compression,
+}
+
+impl PackEntry {
+    /// Turns the stored bytes of this entry back into the asset data and verifies the checksum.
+    pub fn unpack(&self, path: &AssetPath, stored: &[u8]) -> Result<Vec<u8>, PackError> {
+        let bytes = match self.compression {
+            Compression::None => stored.to_vec(),
+            Compression::Deflate => miniz_oxide::inflate::decompress_to_vec_with_limit(stored, self.size as usize)
+                .map_err(|_| PackError::Decompress(path.clone()))?,
+        };
+        if bytes.len() != self.size as usize || crc32fast::hash(&bytes) != self.checksum {
+            return Err(PackError::ChecksumMismatch(path.clone()));
+        }
+        Ok(bytes)
+    }
+}
+
+/// Index table at the start of an asset pack.
+///
+/// File layout, little endian: `CSPK`, version u16, entry count u32, index length u32,
+/// then per entry `path len u16, utf8 path, offset u64, stored size u32, size u32, crc32 u32, compression u8`,
+/// followed by the data of all entries.
+#[derive(Debug, Default)]
+pub struct PackIndex {
+    entries: HashMap<AssetPath, PackEntry>,
+}
+
+impl PackIndex {
+    /// Length of the index that follows the header, so a reader knows how much to read before parsing.
+    pub fn index_len(header: &[u8]) -> Result<usize, PackError> {
+        let mut reader = ByteReader::new(header);
+        if reader.take(4)? != MAGIC {
+            return Err(PackError::InvalidMagic);
+        }
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(PackError::UnsupportedVersion(version));
+        }
+        reader.u32()?;
+        Ok(reader.u32()? as usize)
+    }
+
+    /// Parses the header and index, `bytes` may contain more of the pack than that.
+    pub fn parse(bytes: &[u8]) -> Result<Self, PackError> {
+        let index_len = Self::index_len(bytes)?;
+        let index = HEADER_LEN.checked_add(index_len)
+            .and_then(|end| bytes.get(..end))
+            .ok_or(PackError::UnexpectedEnd)?;
+        let mut reader = ByteReader::new(index);
+        // magic and version were checked by `index_len`
+        reader.take(6)?;
+        let count = reader.u32()? as usize;
+        reader.u32()?;
+        if count > index_len / MIN_ENTRY_LEN {
+            return Err(PackError::UnexpectedEnd);
+        }
+        let mut entries = HashMap::with_capacity(count);
+        for _ in 0..count {
+            let length = reader.u16()? as usize;
+            let path = AssetPath::new(&String::from_utf8_lossy(reader.take(length)?))?;
+            let offset = reader.u64()?;
+            let stored_size = reader.u32()?;
+            let size = reader.u32()?;
+            let checksum = reader.u32()?;
+            let compression = match reader.u8()? {
+                0 => Compression::None,
+                1 => Compression::Deflate,
+                other => return Err(PackError::UnknownCompression(other)),
+            };
+            entries.insert(path, PackEntry { offset, stored_size, size, checksum, compression });
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn get(&self, path: &AssetPath) -> Option<&PackEntry> {
+        self.entries.get(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &AssetPath> {
+        self.entries.keys()
+    }
+}
+
+/// Collects assets and writes them as one pack, see `PackIndex` for the layout.
+#[derive(Default)]
+pub struct PackWriter {
+    files: Vec<(AssetPath, Vec<u8>)>,
+}
+
+impl PackWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adding the same path twice replaces the earlier data.
+    pub fn add(&mut self, path: AssetPath, bytes: Vec<u8>) {
+        self.files.retain(|(existing, _)| *existing != path);
+        self.files.push((path, bytes));
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut files: Vec<&(AssetPath, Vec<u8>)> = self.files.iter().collect();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        // compressed data is only kept when it is smaller, images are usually compressed already
+        let stored: Vec<(Compression, Vec<u8>)> = files.iter()
+            .map(|(_, bytes)| {
+                let compressed = miniz_oxide::deflate::compress_to_vec(bytes, 6);
+                if compressed.len() < bytes.len() {
+                    (Compression::Deflate, compressed)
+                } else {
+                    (Compression::None, bytes.clone())
+                }
+            })
+            .collect();
+
+        let index_len: usize = files.iter().map(|(path, _)| 2 + path.as_str().len() + 8 + 4 + 4 + 4 + 1).sum();
+        let data_len: usize = stored.iter().map(|(_, bytes)| bytes.len()).sum();
+        let mut bytes = Vec::with_capacity(HEADER_LEN + index_len + data_len);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(files.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(index_len as u32).to_le_bytes());
+
+        let mut offset = (HEADER_LEN + index_len) as u64;
+        for ((path, data), (compression, stored_data)) in files.iter().zip(stored.iter()) {
+            bytes.extend_from_slice(&(path.as_str().len() as u16).to_le_bytes());
+            bytes.extend_from_slice(path.as_str().as_bytes());
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            bytes.extend_from_slice(&(stored_data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+            bytes.push(match compression {
+                Compression::None => 0,
+                Compression::Deflate => 1,
+            });
+            offset += stored_data.len() as u64;
+        }
+        for (_, stored_data) in stored.iter() {
+            bytes.extend_from_slice(stored_data);
+        }
+        bytes
+    }
+}
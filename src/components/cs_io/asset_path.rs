@@ -0,0 +1,116 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AssetPathError {
+    #[error("asset path is empty")]
+    Empty,
+
+    #[error("asset path {0:?} is absolute, asset paths are relative to the asset root")]
+    Absolute(String),
+
+    #[error("asset path {0:?} leaves the asset root")]
+    EscapesRoot(String),
+
+    #[error("asset path {path:?} contains the invalid character {character:?}")]
+    InvalidCharacter { path: String, character: char },
+}
+
+/// Characters that are not allowed in file names on one of the supported platforms or need escaping in URLs.
+const INVALID_CHARACTERS: [char; 8] = [':', '*', '?', '"', '<', '>', '|', '#'];
+
+/// Path of an asset relative to the asset root, the same on every platform.
+///
+/// Segments are separated by `/`, `\` is accepted while parsing. `.` segments and empty segments are removed
+/// and `..` is resolved, a path that would leave the root is rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetPath(String);
+
+impl AssetPath {
+    pub fn new(path: &str) -> Result<Self, AssetPathError> {
+        if path.starts_with('/') || path.starts_with('\\') || path.as_bytes().get(1) == Some(&b':') {
+            return Err(AssetPathError::Absolute(path.to_string()));
+        }
+        if let Some(character) = path.chars().find(|character| INVALID_CHARACTERS.contains(character) || character.is_control()) {
+            return Err(AssetPathError::InvalidCharacter { path: path.to_string(), character });
+        }
+
+        let mut segments: Vec<&str> = Vec::new();
+        for segment in path.split(['/', '\\']) {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    if segments.pop().is_none() {
+                        return Err(AssetPathError::EscapesRoot(path.to_string()));
+                    }
+                }
+                segment => segments.push(segment),
+            }
+        }
+        if segments.is_empty() {
+            return Err(AssetPathError::Empty);
+        }
+        Ok(Self(segments.join("/")))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn file_name(&self) -> &str {
+        self.0.rsplit('/').next().unwrap_or(&self.0)
+    }
+
+    pub fn extension(&self) -> Option<&str> {
+        let file_name = self.file_name();
+        file_name.rfind('.').filter(|index| *index > 0).map(|index| &file_name[index + 1..])
+    }
+
+    /// The directory of this path, `None` for files directly in the asset root.
+    pub fn parent(&self) -> Option<AssetPath> {
+        self.0.rfind('/').map(|index| Self(self.0[..index].to_string()))
+    }
+
+    /// Resolves `relative` against the directory of this path, e.g. the page image of a font file.
+    pub fn sibling(&self, relative: &str) -> Result<AssetPath, AssetPathError> {
+        match self.parent() {
+            Some(parent) => AssetPath::new(&format!("{}/{}", parent.0, relative)),
+            None => AssetPath::new(relative),
+        }
+    }
+
+    /// Resolves `relative` inside this path, e.g. a file in a save game directory.
+    pub fn join(&self, relative: &str) -> Result<AssetPath, AssetPathError> {
+        AssetPath::new(&format!("{}/{}", self.0, relative))
+    }
+
+    /// Native path for file based `AssetIo` implementations.
+    pub fn to_path_buf(&self) -> PathBuf {
+        self.0.split('/').collect()
+    }
+}
+
+impl fmt::Display for AssetPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for AssetPath {
+    type Err = AssetPathError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        AssetPath::new(path)
+    }
+}
+
+impl TryFrom<&str> for AssetPath {
+    type Error = AssetPathError;
+
+    fn try_from(path: &str) -> Result<Self, Self::Error> {
+        AssetPath::new(path)
+    }
+}
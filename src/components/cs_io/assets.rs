@@ -0,0 +1,275 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use bevy_ecs::system::Resource;
+use futures_util::StreamExt;
+use futures_util::stream::FuturesUnordered;
+use thiserror::Error;
+use wgpu::{Device, Queue};
+
+use super::{AssetIo, AssetIoError};
+use super::asset_path::AssetPath;
+
+/// Errors that occur while turning loaded bytes into an asset.
+#[derive(Error, Debug)]
+pub enum AssetError {
+    #[error(transparent)]
+    Io(#[from] AssetIoError),
+
+    #[error("could not decode {path}: {message}")]
+    Decode { path: AssetPath, message: String },
+}
+
+impl AssetError {
+    pub fn decode(path: &AssetPath, message: impl fmt::Display) -> Self {
+        AssetError::Decode { path: path.clone(), message: message.to_string() }
+    }
+}
+
+/// GPU access for assets that create device resources while loading.
+pub struct LoadContext<'a> {
+    pub device: &'a Device,
+    pub queue: &'a Queue,
+}
+
+pub trait Asset: Send + Sync + Sized + 'static {
+    fn from_bytes(bytes: Vec<u8>, path: &AssetPath, context: &LoadContext) -> Result<Self, AssetError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    Failed(String),
+}
+
+/// How far `load_pending_with_progress` got.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub loaded: usize,
+    pub total: usize,
+    /// The asset that finished last, loads run concurrently so there is no single asset in progress.
+    pub current: Option<AssetPath>,
+}
+
+impl LoadProgress {
+    /// Between 0 and 1, nothing to load counts as done.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        self.loaded as f32 / self.total as f32
+    }
+}
+
+struct AssetEntry<T> {
+    path: AssetPath,
+    state: Mutex<LoadState>,
+    value: RwLock<Option<Arc<T>>>,
+    /// Increased every time a new value is stored, dependents compare it to notice reloads.
+    version: AtomicU32,
+}
+
+/// Shared reference to an asset, the asset and its GPU resources are dropped with the last handle.
+pub struct Handle<T> {
+    entry: Arc<AssetEntry<T>>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self { entry: self.entry.clone() }
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle").field("path", &self.entry.path).field("state", &self.load_state()).finish()
+    }
+}
+
+impl<T> Handle<T> {
+    /// The current value of the asset once it is loaded, a reload replaces it for later calls.
+    pub fn get(&self) -> Option<Arc<T>> {
+        self.entry.value.read().unwrap().clone()
+    }
+
+    pub fn load_state(&self) -> LoadState {
+        self.entry.state.lock().unwrap().clone()
+    }
+
+    /// 0 until the first successful load.
+    pub fn version(&self) -> u32 {
+        self.entry.version.load(Ordering::Acquire)
+    }
+
+    pub fn path(&self) -> &AssetPath {
+        &self.entry.path
+    }
+
+    /// The asset or the reason it is not available, for startup code that cannot continue without it.
+    pub fn expect_loaded(&self) -> Arc<T> {
+        match self.get() {
+            Some(value) => value,
+            None => panic!("asset {} is not loaded: {:?}", self.entry.path, self.load_state()),
+        }
+    }
+}
+
+/// A load that was requested but not finished yet, type erased so all asset types share one queue.
+trait PendingLoad: Send + Sync {
+    fn path(&self) -> &AssetPath;
+    fn finish(&self, bytes: Result<Vec<u8>, AssetIoError>, context: &LoadContext);
+}
+
+impl<T: Asset> PendingLoad for AssetEntry<T> {
+    fn path(&self) -> &AssetPath {
+        &self.path
+    }
+
+    /// A failed reload keeps the previous value so the game can continue with it.
+    fn finish(&self, bytes: Result<Vec<u8>, AssetIoError>, context: &LoadContext) {
+        let result = bytes
+            .map_err(AssetError::from)
+            .and_then(|bytes| T::from_bytes(bytes, &self.path, context));
+        let state = match result {
+            Ok(value) => {
+                *self.value.write().unwrap() = Some(Arc::new(value));
+                self.version.fetch_add(1, Ordering::AcqRel);
+                LoadState::Loaded
+            }
+            Err(error) => {
+                log::error!("{}", error);
+                LoadState::Failed(error.to_string())
+            }
+        };
+        *self.state.lock().unwrap() = state;
+    }
+}
+
+struct EntryRef {
+    typed: Weak<dyn Any + Send + Sync>,
+    load: Weak<dyn PendingLoad>,
+}
+
+/// Loads assets through an `AssetIo`, one path is only loaded once per asset type while a handle to it is alive.
+#[derive(Resource)]
+pub struct Assets {
+    asset_io: Box<dyn AssetIo>,
+    entries: HashMap<(TypeId, AssetPath), EntryRef>,
+    /// Only the handles keep an entry alive, so a load nobody waits for any more is not read at all.
+    pending: Vec<Weak<dyn PendingLoad>>,
+}
+
+impl Assets {
+    pub fn new(asset_io: Box<dyn AssetIo>) -> Self {
+        Self {
+            asset_io,
+            entries: HashMap::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn asset_io(&self) -> &dyn AssetIo {
+        self.asset_io.as_ref()
+    }
+
+    /// Returns the handle of an asset, the file is read by the next `load_pending` unless it is already known.
+    /// A malformed path gives a handle in the failed state.
+    pub fn load<T: Asset>(&mut self, path: &str) -> Handle<T> {
+        match AssetPath::new(path) {
+            Ok(path) => self.load_asset_path(path),
+            Err(error) => {
+                log::error!("{}", error);
+                Handle {
+                    entry: Arc::new(AssetEntry {
+                        path: AssetPath::new("invalid").unwrap(),
+                        state: Mutex::new(LoadState::Failed(error.to_string())),
+                        value: RwLock::new(None),
+                        version: AtomicU32::new(0),
+                    }),
+                }
+            }
+        }
+    }
+
+    pub fn load_asset_path<T: Asset>(&mut self, path: AssetPath) -> Handle<T> {
+        let key = (TypeId::of::<T>(), path.clone());
+        if let Some(entry) = self.entries.get(&key).and_then(|entry| entry.typed.upgrade()) {
+            if let Ok(entry) = entry.downcast::<AssetEntry<T>>() {
+                return Handle { entry };
+            }
+        }
+
+        let entry = Arc::new(AssetEntry {
+            path,
+            state: Mutex::new(LoadState::Loading),
+            value: RwLock::new(None),
+            version: AtomicU32::new(0),
+        });
+        let typed: Arc<dyn Any + Send + Sync> = entry.clone();
+        let load: Arc<dyn PendingLoad> = entry.clone();
+        self.entries.insert(key, EntryRef { typed: Arc::downgrade(&typed), load: Arc::downgrade(&load) });
+        self.pending.push(Arc::downgrade(&load));
+        Handle { entry }
+    }
+
+    /// Requested loads whose handles are still alive.
+    pub fn pending_count(&self) -> usize {
+        self.pending.iter().filter(|load| load.strong_count() > 0).count()
+    }
+
+    /// Queues a reload of every live asset whose file changed according to the `AssetIo`.
+    /// Returns the number of queued reloads.
+    pub fn queue_changed(&mut self) -> usize {
+        let changed = self.asset_io.changed_paths();
+        if changed.is_empty() {
+            return 0;
+        }
+        let before = self.pending.len();
+        for ((_, path), entry) in self.entries.iter() {
+            if !changed.contains(path) {
+                continue;
+            }
+            if entry.load.strong_count() > 0 {
+                log::info!("reloading {}", path);
+                self.pending.push(entry.load.clone());
+            }
+        }
+        self.pending.len() - before
+    }
+
+    /// Reads and decodes all requested assets. Loads whose handles were dropped in the meantime are skipped.
+    pub async fn load_pending(&mut self, device: &Device, queue: &Queue) {
+        self.load_pending_with_progress(device, queue, |_| {}).await;
+    }
+
+    /// Like `load_pending`, `on_progress` is called once before the first read and after every finished asset.
+    /// All files are read at the same time and decoded in the order they arrive.
+    pub async fn load_pending_with_progress(&mut self, device: &Device, queue: &Queue, mut on_progress: impl FnMut(&LoadProgress)) {
+        let context = LoadContext { device, queue };
+        let pending: Vec<_> = std::mem::take(&mut self.pending).iter().filter_map(Weak::upgrade).collect();
+        let mut progress = LoadProgress { loaded: 0, total: pending.len(), current: None };
+        on_progress(&progress);
+
+        let asset_io = self.asset_io.as_ref();
+        let mut reads: FuturesUnordered<_> = pending.iter()
+            .map(|pending| async move { (pending, asset_io.load_path(pending.path()).await) })
+            .collect();
+        while let Some((pending, bytes)) = reads.next().await {
+            pending.finish(bytes, &context);
+            progress.loaded += 1;
+            progress.current = Some(pending.path().clone());
+            on_progress(&progress);
+        }
+        drop(reads);
+        self.remove_unused();
+    }
+
+    /// Forgets paths whose assets were freed, so a later `load` reads them again.
+    pub fn remove_unused(&mut self) {
+        self.entries.retain(|_, entry| entry.typed.strong_count() > 0);
+    }
+}
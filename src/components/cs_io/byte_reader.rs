@@ -0,0 +1,38 @@
+/// The data ended before a value could be read, formats turn this into their own error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnexpectedEnd;
+
+/// Reads little endian values from the start of a byte slice onwards.
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub fn take(&mut self, count: usize) -> Result<&'a [u8], UnexpectedEnd> {
+        let end = self.position.checked_add(count).ok_or(UnexpectedEnd)?;
+        let slice = self.bytes.get(self.position..end).ok_or(UnexpectedEnd)?;
+        self.position += count;
+        Ok(slice)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, UnexpectedEnd> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, UnexpectedEnd> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, UnexpectedEnd> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, UnexpectedEnd> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use bevy_utils::BoxedFuture;
+
+use super::{AssetIoError, AssetIo};
+use super::asset_path::AssetPath;
+
+// `EMBEDDED_ASSETS: &[(&str, &[u8])]`, generated by build.rs. Empty unless the `embedded_assets` feature is enabled.
+include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+
+/// Serves the files of the `assets` directory that were compiled into the binary.
+pub struct EmbeddedAssetIo {
+    files: HashMap<AssetPath, &'static [u8]>,
+}
+
+impl EmbeddedAssetIo {
+    pub fn new() -> Self {
+        Self::from_table(EMBEDDED_ASSETS)
+    }
+
+    pub fn from_table(table: &[(&str, &'static [u8])]) -> Self {
+        let files = table.iter()
+            .filter_map(|(path, bytes)| match AssetPath::new(path) {
+                Ok(path) => Some((path, *bytes)),
+                Err(error) => {
+                    log::warn!("skipping embedded asset: {}", error);
+                    None
+                }
+            })
+            .collect();
+        Self { files }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl Default for EmbeddedAssetIo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AssetIo for EmbeddedAssetIo {
+    fn load_path<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            match self.files.get(path) {
+                Some(bytes) => Ok(bytes.to_vec()),
+                None => Err(AssetIoError::NotFound(path.to_path_buf())),
+            }
+        })
+    }
+}
@@ -1,45 +1,106 @@
-
 use bevy_utils::BoxedFuture;
 use std::fs::File;
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Receiver};
 use std::{
     io::Read,
     path::{Path, PathBuf},
 };
 
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
 use super::{AssetIoError, AssetIo};
+use super::asset_path::AssetPath;
 
 pub struct FileAssetIo {
-    root_path: PathBuf
+    root_path: PathBuf,
+    watcher: Option<FileWatcher>,
+}
+
+/// Keeps the watcher alive and collects the paths it reports.
+struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    changes: Mutex<Receiver<AssetPath>>,
 }
 
 impl FileAssetIo {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
         FileAssetIo {
             root_path: PathBuf::new().join(path.as_ref()),
+            watcher: None,
         }
     }
+
+    /// Starts watching the asset directory, changed files are reported by `changed_paths`.
+    pub fn watch_for_changes(&mut self) -> notify::Result<()> {
+        let root = self.root_path.join(".").canonicalize()?;
+        let watched = root.join("assets");
+        let (sender, receiver) = channel();
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            let Ok(event) = result else {
+                return;
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                return;
+            }
+            for path in event.paths {
+                let asset_path = path.strip_prefix(&root).ok()
+                    .and_then(|relative| relative.to_str())
+                    .and_then(|relative| AssetPath::new(relative).ok());
+                if let Some(asset_path) = asset_path {
+                    let _ = sender.send(asset_path);
+                }
+            }
+        })?;
+        watcher.watch(&watched, RecursiveMode::Recursive)?;
+        self.watcher = Some(FileWatcher { _watcher: watcher, changes: Mutex::new(receiver) });
+        Ok(())
+    }
 }
 
 impl AssetIo for FileAssetIo {
-    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+    /// The file is read on its own thread, so the loads `Assets` starts together overlap like the fetches on the web.
+    fn load_path<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        let full_path = self.root_path.join(path.to_path_buf());
+        let (sender, receiver) = async_channel::bounded(1);
+        std::thread::spawn(move || {
+            let _ = sender.send_blocking(read_file(full_path));
+        });
         Box::pin(async move {
-            let mut bytes = Vec::new();
-            let full_path = self.root_path.join(path);
-            match File::open(&full_path) {
-                Ok(mut file) => {
-                    file.read_to_end(&mut bytes)?;
-                }
-                Err(e) => {
-                    return if e.kind() == std::io::ErrorKind::NotFound {
-                        Err(AssetIoError::NotFound(full_path))
-                    } else {
-                        Err(e.into())
-                    }
-                }
-            }
-            Ok(bytes)
+            receiver.recv().await
+                .unwrap_or_else(|_| Err(std::io::Error::other("the reading thread stopped").into()))
         })
     }
 
+    fn changed_paths(&self) -> Vec<AssetPath> {
+        let Some(watcher) = &self.watcher else {
+            return Vec::new();
+        };
+        let mut changed: Vec<AssetPath> = watcher.changes.lock().unwrap().try_iter().collect();
+        // editors often write a file in several steps
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+}
 
+fn read_file(full_path: PathBuf) -> Result<Vec<u8>, AssetIoError> {
+    let mut bytes = Vec::new();
+    // a directory is not an asset, the web server answers the same request with 404
+    if full_path.is_dir() {
+        return Err(AssetIoError::NotFound(full_path));
+    }
+    match File::open(&full_path) {
+        Ok(mut file) => {
+            file.read_to_end(&mut bytes)?;
+        }
+        Err(e) => {
+            return if e.kind() == std::io::ErrorKind::NotFound {
+                Err(AssetIoError::NotFound(full_path))
+            } else {
+                Err(e.into())
+            }
+        }
+    }
+    Ok(bytes)
 }
@@ -0,0 +1,41 @@
+use bevy_utils::BoxedFuture;
+
+use super::{AssetIoError, AssetIo};
+use super::asset_path::AssetPath;
+
+/// Tries its layers in order, the first layer that has a file wins.
+/// Only `NotFound` falls through to the next layer, other errors are returned as they are.
+pub struct LayeredAssetIo {
+    layers: Vec<Box<dyn AssetIo>>,
+}
+
+impl LayeredAssetIo {
+    pub fn new(layers: Vec<Box<dyn AssetIo>>) -> Self {
+        Self { layers }
+    }
+
+    pub fn layers(&self) -> &[Box<dyn AssetIo>] {
+        &self.layers
+    }
+}
+
+impl AssetIo for LayeredAssetIo {
+    fn load_path<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            for layer in self.layers.iter() {
+                match layer.load_path(path).await {
+                    Err(AssetIoError::NotFound(_)) => continue,
+                    result => return result,
+                }
+            }
+            Err(AssetIoError::NotFound(path.to_path_buf()))
+        })
+    }
+
+    fn changed_paths(&self) -> Vec<AssetPath> {
+        let mut changed: Vec<AssetPath> = self.layers.iter().flat_map(|layer| layer.changed_paths()).collect();
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+}
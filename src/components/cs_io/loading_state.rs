@@ -1,5 +1,7 @@
 #![allow(unused)]
 
+use super::assets::LoadProgress;
+
 cfg_if::cfg_if! {
     if #[cfg(target_arch = "wasm32")] {
         use wasm_bindgen::prelude::*;
@@ -24,14 +26,30 @@ pub fn set_loading_finish() {
 }
 
 
-pub fn set_loading(loading: Option<&str>) {
+/// Shows the progress on the web page, `loading_text` gets the current item and
+/// an optional `loading_progress` element (e.g. `<progress max="1">`) the fraction.
+pub fn set_loading(progress: Option<&LoadProgress>) {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             let win = web_sys::window().unwrap();
             let document = win.document().unwrap();
             let element = document.get_element_by_id("loading_text").unwrap();
             let element = element.dyn_into::<HtmlElement>().unwrap();
-            element.set_text_content(loading);
+            let text = progress.map(loading_text);
+            element.set_text_content(text.as_deref());
+            if let Some(bar) = document.get_element_by_id("loading_progress") {
+                let fraction = progress.map_or(0.0, |progress| progress.fraction());
+                bar.set_attribute("value", &fraction.to_string()).unwrap();
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+/// e.g. `Loading 60% assets/shaders/text.wgsl`
+pub fn loading_text(progress: &LoadProgress) -> String {
+    let percent = (progress.fraction() * 100.0).round();
+    match &progress.current {
+        Some(current) => format!("Loading {}% {}", percent, current),
+        None => format!("Loading {}%", percent),
+    }
+}
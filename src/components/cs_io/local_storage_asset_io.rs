@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bevy_utils::BoxedFuture;
+use web_sys::Storage;
+
+use super::{AssetIoError, AssetIo, WritableAssetIo};
+use super::asset_path::AssetPath;
+
+/// Files of the current user in the browser's local storage, stored base64 encoded under `prefix/path`.
+pub struct LocalStorageAssetIo {
+    prefix: String,
+}
+
+impl LocalStorageAssetIo {
+    pub fn new(prefix: &str) -> Self {
+        LocalStorageAssetIo {
+            prefix: prefix.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn key(&self, path: &AssetPath) -> String {
+        format!("{}/{}", self.prefix, path)
+    }
+}
+
+/// `Storage` is not `Send`, so it is looked up for every call instead of being kept.
+fn storage() -> Result<Storage, AssetIoError> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| AssetIoError::Network("local storage is not available".to_string()))
+}
+
+fn storage_error(error: wasm_bindgen::JsValue) -> AssetIoError {
+    AssetIoError::Network(error.as_string().unwrap_or_else(|| format!("{:?}", error)))
+}
+
+impl AssetIo for LocalStorageAssetIo {
+    fn load_path<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            let key = self.key(path);
+            let value = storage()?.get_item(&key).map_err(storage_error)?
+                .ok_or_else(|| AssetIoError::NotFound(PathBuf::from(&key)))?;
+            STANDARD.decode(value).map_err(|error| AssetIoError::Decode(error.to_string()))
+        })
+    }
+}
+
+impl WritableAssetIo for LocalStorageAssetIo {
+    fn save<'a>(&'a self, path: &'a AssetPath, bytes: &'a [u8]) -> BoxedFuture<'a, Result<(), AssetIoError>> {
+        Box::pin(async move {
+            // fails when the storage quota of the page is used up
+            storage()?.set_item(&self.key(path), &STANDARD.encode(bytes)).map_err(storage_error)
+        })
+    }
+
+    fn list<'a>(&'a self, directory: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<AssetPath>, AssetIoError>> {
+        Box::pin(async move {
+            let storage = storage()?;
+            let prefix = format!("{}/", self.key(directory));
+            let mut files = Vec::new();
+            for index in 0..storage.length().map_err(storage_error)? {
+                let Some(key) = storage.key(index).map_err(storage_error)? else {
+                    continue;
+                };
+                let Some(name) = key.strip_prefix(&prefix) else {
+                    continue;
+                };
+                if name.contains('/') {
+                    continue;
+                }
+                if let Ok(path) = directory.join(name) {
+                    files.push(path);
+                }
+            }
+            files.sort();
+            Ok(files)
+        })
+    }
+
+    fn delete<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<(), AssetIoError>> {
+        Box::pin(async move {
+            let storage = storage()?;
+            let key = self.key(path);
+            if storage.get_item(&key).map_err(storage_error)?.is_none() {
+                return Err(AssetIoError::NotFound(PathBuf::from(key)));
+            }
+            storage.remove_item(&key).map_err(storage_error)
+        })
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use bevy_utils::BoxedFuture;
+
+use super::{AssetIoError, AssetIo, WritableAssetIo};
+use super::asset_path::AssetPath;
+
+/// Serves files kept in memory, for tests and content generated at runtime.
+/// Inserting a file reports it through `changed_paths` so loaded handles pick up the new bytes.
+/// As a `WritableAssetIo` it stands in for the user's storage in tests.
+#[derive(Default)]
+pub struct InMemoryAssetIo {
+    files: RwLock<HashMap<AssetPath, Arc<[u8]>>>,
+    changed: Mutex<Vec<AssetPath>>,
+}
+
+impl InMemoryAssetIo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(self, path: AssetPath, bytes: impl Into<Arc<[u8]>>) -> Self {
+        self.insert(path, bytes);
+        self
+    }
+
+    pub fn insert(&self, path: AssetPath, bytes: impl Into<Arc<[u8]>>) {
+        self.files.write().unwrap().insert(path.clone(), bytes.into());
+        self.changed.lock().unwrap().push(path);
+    }
+
+    pub fn remove(&self, path: &AssetPath) -> bool {
+        self.files.write().unwrap().remove(path).is_some()
+    }
+
+    pub fn contains(&self, path: &AssetPath) -> bool {
+        self.files.read().unwrap().contains_key(path)
+    }
+}
+
+impl AssetIo for InMemoryAssetIo {
+    fn load_path<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            match self.files.read().unwrap().get(path) {
+                Some(bytes) => Ok(bytes.to_vec()),
+                None => Err(AssetIoError::NotFound(path.to_path_buf())),
+            }
+        })
+    }
+
+    fn changed_paths(&self) -> Vec<AssetPath> {
+        std::mem::take(&mut *self.changed.lock().unwrap())
+    }
+}
+
+impl WritableAssetIo for InMemoryAssetIo {
+    fn save<'a>(&'a self, path: &'a AssetPath, bytes: &'a [u8]) -> BoxedFuture<'a, Result<(), AssetIoError>> {
+        Box::pin(async move {
+            self.insert(path.clone(), bytes);
+            Ok(())
+        })
+    }
+
+    fn list<'a>(&'a self, directory: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<AssetPath>, AssetIoError>> {
+        Box::pin(async move {
+            let mut files: Vec<AssetPath> = self.files.read().unwrap().keys()
+                .filter(|path| path.parent().as_ref() == Some(directory))
+                .cloned()
+                .collect();
+            files.sort();
+            Ok(files)
+        })
+    }
+
+    fn delete<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<(), AssetIoError>> {
+        Box::pin(async move {
+            match self.remove(path) {
+                true => Ok(()),
+                false => Err(AssetIoError::NotFound(path.to_path_buf())),
+            }
+        })
+    }
+}
@@ -2,7 +2,19 @@
 pub mod file_asset_io;
 #[cfg(target_arch = "wasm32")]
 pub mod wasm_asset_io;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod user_data_asset_io;
+#[cfg(target_arch = "wasm32")]
+pub mod local_storage_asset_io;
 pub mod loading_state;
+pub mod assets;
+pub mod asset_path;
+pub mod memory_asset_io;
+pub mod embedded_asset_io;
+pub mod layered_asset_io;
+pub mod asset_pack;
+pub mod byte_reader;
+pub mod pack_asset_io;
 
 
 use downcast_rs::{impl_downcast, Downcast};
@@ -10,23 +22,42 @@ use downcast_rs::{impl_downcast, Downcast};
 pub use file_asset_io::*;
 #[cfg(target_arch = "wasm32")]
 pub use wasm_asset_io::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use user_data_asset_io::UserDataAssetIo;
+#[cfg(target_arch = "wasm32")]
+pub use local_storage_asset_io::LocalStorageAssetIo;
+pub use memory_asset_io::InMemoryAssetIo;
+pub use embedded_asset_io::EmbeddedAssetIo;
+pub use layered_asset_io::LayeredAssetIo;
+pub use pack_asset_io::PackAssetIo;
 
 
 
 use std::{
     io,
-    path::{Path, PathBuf},
+    path::PathBuf,
 };
+use bevy_ecs::system::Resource;
 use bevy_utils::BoxedFuture;
 use thiserror::Error;
 
+use asset_path::AssetPath;
+
 /// Errors that occur while loading assets.
 #[derive(Error, Debug)]
 pub enum AssetIoError {
-    /// Path not found.
+    /// Path not found, also used for directories and for any non-OK HTTP response.
     #[error("path not found: {0}")]
     NotFound(PathBuf),
 
+    /// The request could not be made or failed without a response, e.g. a network or JavaScript error.
+    #[error("network error while loading asset: {0}")]
+    Network(String),
+
+    /// A response arrived but its body could not be read.
+    #[error("could not read asset data: {0}")]
+    Decode(String),
+
     /// Encountered an I/O error while loading an asset.
     #[error("encountered an io error while loading asset: {0}")]
     Io(#[from] io::Error),
@@ -35,18 +66,91 @@ pub enum AssetIoError {
 
 pub trait AssetIo: Downcast + Send + Sync + 'static {
     /// Returns a future to load the full file data at the provided path.
-    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>>;
+    fn load_path<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>>;
+
+    /// Paths that changed since the last call, empty unless the implementation watches for changes.
+    fn changed_paths(&self) -> Vec<AssetPath> {
+        Vec::new()
+    }
 }
 
 impl_downcast!(AssetIo);
 
-pub fn get_asset_store() -> Box<dyn AssetIo>{
+/// Companion of `AssetIo` for data the game writes itself, like save games, settings and replays.
+pub trait WritableAssetIo: AssetIo {
+    /// Writes the file, replacing an existing one and creating missing directories.
+    fn save<'a>(&'a self, path: &'a AssetPath, bytes: &'a [u8]) -> BoxedFuture<'a, Result<(), AssetIoError>>;
+
+    /// Files directly inside `directory`, sorted. A missing directory is empty.
+    fn list<'a>(&'a self, directory: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<AssetPath>, AssetIoError>>;
+
+    /// Removes the file, `NotFound` if it does not exist.
+    fn delete<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<(), AssetIoError>>;
+}
+
+impl_downcast!(WritableAssetIo);
+
+/// Asset pack next to the executable or the web page, produced by the `pack_assets` tool.
+pub const ASSET_PACK: &str = "assets.pack";
+
+pub async fn get_asset_store() -> Box<dyn AssetIo>{
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
-            let asset_io: Box<dyn AssetIo> = Box::new(WasmAssetIo::new(""));
+            let wasm_asset_io = WasmAssetIo::new("");
+            // the pack is tried first, loose files only exist in development
+            let asset_io: Box<dyn AssetIo> = match PackAssetIo::fetch(&wasm_asset_io, &AssetPath::new(ASSET_PACK).unwrap()).await {
+                Ok(pack) => Box::new(LayeredAssetIo::new(vec![Box::new(pack), Box::new(wasm_asset_io)])),
+                Err(AssetIoError::NotFound(_)) => Box::new(wasm_asset_io),
+                Err(error) => {
+                    log::warn!("could not load {}: {}", ASSET_PACK, error);
+                    Box::new(wasm_asset_io)
+                }
+            };
         }else{
-            let asset_io: Box<dyn AssetIo> = Box::new(FileAssetIo::new(""));
+            let mut file_asset_io = FileAssetIo::new("");
+            // hot reloading is a development feature
+            if cfg!(debug_assertions) {
+                if let Err(error) = file_asset_io.watch_for_changes() {
+                    log::warn!("asset hot reloading disabled: {}", error);
+                }
+            }
+            // loose files override the pack so single assets can be patched
+            let asset_io: Box<dyn AssetIo> = match std::path::Path::new(ASSET_PACK).exists() {
+                true => match PackAssetIo::open(ASSET_PACK) {
+                    Ok(pack) => Box::new(LayeredAssetIo::new(vec![Box::new(file_asset_io), Box::new(pack)])),
+                    Err(error) => {
+                        log::warn!("could not open {}: {}", ASSET_PACK, error);
+                        Box::new(file_asset_io)
+                    }
+                },
+                false => Box::new(file_asset_io),
+            };
         }
     }
+    // files next to the executable or on the server still override the embedded ones
+    if cfg!(feature = "embedded_assets") {
+        return Box::new(LayeredAssetIo::new(vec![asset_io, Box::new(EmbeddedAssetIo::new())]));
+    }
     asset_io
+}
+
+/// The user's storage as a resource, for systems that save and load.
+#[derive(Resource)]
+pub struct UserStore(pub Box<dyn WritableAssetIo>);
+
+/// Storage for data of the current user, see `WritableAssetIo`.
+pub fn get_user_store() -> Box<dyn WritableAssetIo> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            Box::new(LocalStorageAssetIo::new("castle_sim"))
+        }else{
+            match UserDataAssetIo::for_current_user("castle_sim") {
+                Some(user_data) => Box::new(user_data),
+                None => {
+                    log::warn!("no user data directory, saving next to the game instead");
+                    Box::new(UserDataAssetIo::new("user_data"))
+                }
+            }
+        }
+    }
 }
\ No newline at end of file
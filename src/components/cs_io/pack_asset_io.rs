@@ -0,0 +1,112 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{Read, Seek, SeekFrom};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+
+use bevy_utils::BoxedFuture;
+
+use super::{AssetIoError, AssetIo};
+use super::asset_path::AssetPath;
+#[cfg(not(target_arch = "wasm32"))]
+use super::asset_pack::HEADER_LEN;
+use super::asset_pack::{PackEntry, PackError, PackIndex};
+
+/// Serves the assets of one pack file, only the index is read up front.
+pub struct PackAssetIo {
+    index: PackIndex,
+    source: PackSource,
+}
+
+enum PackSource {
+    /// Entries are read with a seek on every load, the length bounds the entries of a damaged index.
+    #[cfg(not(target_arch = "wasm32"))]
+    File { file: Mutex<File>, len: u64 },
+    /// The whole pack, the browser has no random access to files on the server.
+    Memory(Vec<u8>),
+}
+
+impl PackAssetIo {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, PackError> {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut bytes = vec![0; HEADER_LEN];
+        file.read_exact(&mut bytes).map_err(unexpected_end)?;
+        let index_len = PackIndex::index_len(&bytes)?;
+        if (HEADER_LEN + index_len) as u64 > len {
+            return Err(PackError::UnexpectedEnd);
+        }
+        bytes.resize(HEADER_LEN + index_len, 0);
+        file.read_exact(&mut bytes[HEADER_LEN..]).map_err(unexpected_end)?;
+        Ok(Self {
+            index: PackIndex::parse(&bytes)?,
+            source: PackSource::File { file: Mutex::new(file), len },
+        })
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, PackError> {
+        Ok(Self {
+            index: PackIndex::parse(&bytes)?,
+            source: PackSource::Memory(bytes),
+        })
+    }
+
+    /// Loads a whole pack through another `AssetIo`, used on the web where the pack is fetched once.
+    pub async fn fetch(asset_io: &dyn AssetIo, path: &AssetPath) -> Result<Self, AssetIoError> {
+        let bytes = asset_io.load_path(path).await?;
+        Self::from_bytes(bytes).map_err(|error| AssetIoError::Decode(error.to_string()))
+    }
+
+    pub fn index(&self) -> &PackIndex {
+        &self.index
+    }
+
+    fn read(&self, path: &AssetPath, entry: &PackEntry) -> Result<Vec<u8>, PackError> {
+        match &self.source {
+            #[cfg(not(target_arch = "wasm32"))]
+            PackSource::File { file, len } => {
+                let end = entry.offset.checked_add(entry.stored_size as u64).ok_or(PackError::UnexpectedEnd)?;
+                if end > *len {
+                    return Err(PackError::UnexpectedEnd);
+                }
+                let mut stored = vec![0; entry.stored_size as usize];
+                let mut file = file.lock().unwrap();
+                file.seek(SeekFrom::Start(entry.offset))?;
+                file.read_exact(&mut stored).map_err(unexpected_end)?;
+                entry.unpack(path, &stored)
+            }
+            PackSource::Memory(bytes) => {
+                let stored = usize::try_from(entry.offset).ok()
+                    .and_then(|start| Some(start..start.checked_add(entry.stored_size as usize)?))
+                    .and_then(|range| bytes.get(range))
+                    .ok_or(PackError::UnexpectedEnd)?;
+                entry.unpack(path, stored)
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unexpected_end(error: std::io::Error) -> PackError {
+    if error.kind() == std::io::ErrorKind::UnexpectedEof {
+        PackError::UnexpectedEnd
+    } else {
+        error.into()
+    }
+}
+
+impl AssetIo for PackAssetIo {
+    fn load_path<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            let entry = self.index.get(path).ok_or_else(|| AssetIoError::NotFound(path.to_path_buf()))?;
+            self.read(path, entry).map_err(|error| match error {
+                PackError::Io(error) => AssetIoError::Io(error),
+                error => AssetIoError::Decode(error.to_string()),
+            })
+        })
+    }
+}
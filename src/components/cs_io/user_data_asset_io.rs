@@ -0,0 +1,100 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy_utils::BoxedFuture;
+
+use super::{AssetIoError, AssetIo, WritableAssetIo};
+use super::asset_path::AssetPath;
+
+/// Files of the current user on desktop, e.g. `~/.local/share/castle_sim` or `%APPDATA%\castle_sim`.
+pub struct UserDataAssetIo {
+    root_path: PathBuf,
+}
+
+impl UserDataAssetIo {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        UserDataAssetIo {
+            root_path: path.as_ref().to_owned(),
+        }
+    }
+
+    /// `None` if the platform has no data directory for the user.
+    pub fn for_current_user(application: &str) -> Option<Self> {
+        dirs::data_dir().map(|data_dir| Self::new(data_dir.join(application)))
+    }
+
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
+    fn full_path(&self, path: &AssetPath) -> PathBuf {
+        self.root_path.join(path.to_path_buf())
+    }
+}
+
+fn not_found(error: io::Error, path: PathBuf) -> AssetIoError {
+    if error.kind() == io::ErrorKind::NotFound {
+        AssetIoError::NotFound(path)
+    } else {
+        error.into()
+    }
+}
+
+impl AssetIo for UserDataAssetIo {
+    fn load_path<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            let full_path = self.full_path(path);
+            if full_path.is_dir() {
+                return Err(AssetIoError::NotFound(full_path));
+            }
+            fs::read(&full_path).map_err(|error| not_found(error, full_path))
+        })
+    }
+}
+
+impl WritableAssetIo for UserDataAssetIo {
+    fn save<'a>(&'a self, path: &'a AssetPath, bytes: &'a [u8]) -> BoxedFuture<'a, Result<(), AssetIoError>> {
+        Box::pin(async move {
+            let full_path = self.full_path(path);
+            if let Some(directory) = full_path.parent() {
+                fs::create_dir_all(directory)?;
+            }
+            // a crash while writing must not destroy the previous save
+            let temporary = full_path.with_file_name(format!("{}.tmp", path.file_name()));
+            fs::write(&temporary, bytes)?;
+            fs::rename(&temporary, &full_path)?;
+            Ok(())
+        })
+    }
+
+    fn list<'a>(&'a self, directory: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<AssetPath>, AssetIoError>> {
+        Box::pin(async move {
+            let entries = match fs::read_dir(self.full_path(directory)) {
+                Ok(entries) => entries,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(error) => return Err(error.into()),
+            };
+            let mut files = Vec::new();
+            for entry in entries {
+                let entry = entry?;
+                if !entry.file_type()?.is_file() {
+                    continue;
+                }
+                // files with names that are not valid asset paths were not written by the game
+                if let Some(path) = entry.file_name().to_str().and_then(|name| directory.join(name).ok()) {
+                    files.push(path);
+                }
+            }
+            files.sort();
+            Ok(files)
+        })
+    }
+
+    fn delete<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<(), AssetIoError>> {
+        Box::pin(async move {
+            let full_path = self.full_path(path);
+            fs::remove_file(&full_path).map_err(|error| not_found(error, full_path))
+        })
+    }
+}
@@ -1,12 +1,9 @@
 use super::{AssetIoError, AssetIo};
-use anyhow::Result;
+use super::asset_path::AssetPath;
 use bevy_utils::BoxedFuture;
 use js_sys::Uint8Array;
-use std::{
-    convert::TryFrom,
-    path::{Path, PathBuf},
-};
-use wasm_bindgen::JsCast;
+use std::path::{Path, PathBuf};
+use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
 use web_sys::Response;
 
@@ -21,22 +18,49 @@ impl WasmAssetIo {
             root_path: path.as_ref().to_owned(),
         }
     }
+
+    /// Asset paths already use `/`, only the root has to be put in front.
+    fn url(&self, path: &AssetPath) -> String {
+        match self.root_path.to_str() {
+            Some("") | None => path.to_string(),
+            Some(root) => format!("{}/{}", root.trim_end_matches('/'), path),
+        }
+    }
 }
 
 impl AssetIo for WasmAssetIo {
-    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+    fn load_path<'a>(&'a self, path: &'a AssetPath) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
         Box::pin(async move {
-            let path = self.root_path.join(path);
-            let window = web_sys::window().unwrap();
-            let resp_value = JsFuture::from(window.fetch_with_str(path.to_str().unwrap()))
+            let url = self.url(path);
+            let window = web_sys::window().ok_or_else(|| AssetIoError::Network("no window available".to_string()))?;
+            let response_value = JsFuture::from(window.fetch_with_str(&url))
                 .await
-                .unwrap();
-            let resp: Response = resp_value.dyn_into().unwrap();
-            let data = JsFuture::from(resp.array_buffer().unwrap()).await.unwrap();
+                .map_err(|error| AssetIoError::Network(js_error_message(error)))?;
+            let response: Response = response_value
+                .dyn_into()
+                .map_err(|error| AssetIoError::Network(js_error_message(error)))?;
+            if !response.ok() {
+                return Err(AssetIoError::NotFound(PathBuf::from(url)));
+            }
+            let array_buffer = response.array_buffer().map_err(|error| AssetIoError::Decode(js_error_message(error)))?;
+            let data = JsFuture::from(array_buffer)
+                .await
+                .map_err(|error| AssetIoError::Decode(js_error_message(error)))?;
             let bytes = Uint8Array::new(&data).to_vec();
             Ok(bytes)
         })
     }
 
 
-}
\ No newline at end of file
+}
+
+/// Message of a rejected promise, which is usually a JavaScript `Error` but can be any value.
+fn js_error_message(error: JsValue) -> String {
+    if let Some(message) = error.as_string() {
+        return message;
+    }
+    match error.dyn_ref::<js_sys::Error>() {
+        Some(error) => String::from(error.message()),
+        None => format!("{:?}", error),
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use bevy_ecs::system::Resource;
+use cgmath::Vector2;
+use thiserror::Error;
+
+use crate::components::cs_io::{AssetIo, AssetIoError};
+use crate::components::cs_io::assets::{Asset, AssetError, LoadContext};
+use crate::components::cs_io::asset_path::{AssetPath, AssetPathError};
+
+/// Characters rasterised when a font atlas is built from a TTF file.
+pub const DEFAULT_CHARACTERS: &str = " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~äöüÄÖÜß°€";
+/// Pixel size of fonts loaded through `Assets`.
+pub const DEFAULT_PIXEL_SIZE: f32 = 32.0;
+const ATLAS_WIDTH: u32 = 512;
+const GLYPH_PADDING: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum FontError {
+    #[error(transparent)]
+    Asset(#[from] AssetIoError),
+
+    #[error(transparent)]
+    Path(#[from] AssetPathError),
+
+    #[error("invalid font file: {0}")]
+    InvalidFont(String),
+
+    #[error("invalid font page image: {0}")]
+    Image(#[from] image::ImageError),
+
+    #[error("bmfont line {line}: {message}")]
+    Parse { line: usize, message: String },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph {
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    /// Size of the glyph quad in pixels.
+    pub size: [f32; 2],
+    /// Offset of the quad from the pen position at the top of the line.
+    pub offset: [f32; 2],
+    pub advance: f32,
+}
+
+/// A glyph quad produced by `FontAtlas::layout`, relative to the top left corner of the text.
+#[derive(Debug, Clone, Copy)]
+pub struct PositionedGlyph {
+    pub position: Vector2<f32>,
+    pub size: Vector2<f32>,
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+/// Single channel coverage atlas with the metrics needed to lay out text.
+#[derive(Debug, Clone, Resource)]
+pub struct FontAtlas {
+    pub size: Vector2<u32>,
+    pub pixels: Vec<u8>,
+    pub glyphs: HashMap<char, Glyph>,
+    pub kerning: HashMap<(char, char), f32>,
+    pub line_height: f32,
+}
+
+impl FontAtlas {
+    /// Loads a `.fnt` BMFont description with its page image, any other file is read as TTF/OTF.
+    pub async fn load(asset_io: &dyn AssetIo, path: &AssetPath, pixel_size: f32) -> Result<Self, FontError> {
+        let bytes = asset_io.load_path(path).await?;
+        if path.extension() != Some("fnt") {
+            return Self::from_ttf(bytes, pixel_size, DEFAULT_CHARACTERS);
+        }
+        let description = String::from_utf8_lossy(&bytes);
+        let page = parse_bmfont_page(&description)?;
+        let page_bytes = asset_io.load_path(&path.sibling(&page)?).await?;
+        Self::from_bmfont(&description, &page_bytes)
+    }
+
+    pub fn from_ttf(bytes: Vec<u8>, pixel_size: f32, characters: &str) -> Result<Self, FontError> {
+        let font = FontVec::try_from_vec(bytes).map_err(|error| FontError::InvalidFont(error.to_string()))?;
+        let scale = PxScale::from(pixel_size);
+        let scaled_font = font.as_scaled(scale);
+        let ascent = scaled_font.ascent();
+
+        let mut packer = ShelfPacker::new(ATLAS_WIDTH);
+        let mut bitmaps = Vec::new();
+        let mut metrics = Vec::new();
+        for character in characters.chars() {
+            let id = font.glyph_id(character);
+            let advance = scaled_font.h_advance(id);
+            let glyph = id.with_scale_and_position(scale, ab_glyph::point(0.0, ascent));
+            match font.outline_glyph(glyph) {
+                Some(outlined) => {
+                    let bounds = outlined.px_bounds();
+                    let width = bounds.width() as u32;
+                    let height = bounds.height() as u32;
+                    let mut coverage = vec![0; (width * height) as usize];
+                    outlined.draw(|x, y, value| {
+                        if x < width && y < height {
+                            coverage[(y * width + x) as usize] = (value * 255.0) as u8;
+                        }
+                    });
+                    let position = packer.place(width, height);
+                    bitmaps.push((position, width, height, coverage));
+                    metrics.push((character, position, [width, height], [bounds.min.x, bounds.min.y], advance));
+                }
+                None => metrics.push((character, [0, 0], [0, 0], [0.0, 0.0], advance)),
+            }
+        }
+
+        let size = Vector2::new(ATLAS_WIDTH, packer.height().next_power_of_two());
+        let mut pixels = vec![0; (size.x * size.y) as usize];
+        for (position, width, height, coverage) in bitmaps {
+            for row in 0..height {
+                let start = ((position[1] + row) * size.x + position[0]) as usize;
+                pixels[start..start + width as usize].copy_from_slice(&coverage[(row * width) as usize..((row + 1) * width) as usize]);
+            }
+        }
+
+        let glyphs = metrics.iter()
+            .map(|(character, position, glyph_size, offset, advance)| {
+                (*character, Glyph::new(size, *position, *glyph_size, *offset, *advance))
+            })
+            .collect();
+        let mut kerning = HashMap::new();
+        for first in characters.chars() {
+            for second in characters.chars() {
+                let amount = scaled_font.kern(font.glyph_id(first), font.glyph_id(second));
+                if amount != 0.0 {
+                    kerning.insert((first, second), amount);
+                }
+            }
+        }
+
+        Ok(Self {
+            size,
+            pixels,
+            glyphs,
+            kerning,
+            line_height: scaled_font.height() + scaled_font.line_gap(),
+        })
+    }
+
+    /// Reads the text variant of the BMFont format, only single page fonts are supported.
+    pub fn from_bmfont(description: &str, page_image: &[u8]) -> Result<Self, FontError> {
+        let image = image::load_from_memory(page_image)?;
+        let has_alpha = image.color().has_alpha();
+        let rgba = image.to_rgba8();
+        let size = Vector2::new(rgba.width(), rgba.height());
+        let pixels = rgba.pixels()
+            .map(|pixel| if has_alpha { pixel[3] } else { pixel[0].max(pixel[1]).max(pixel[2]) })
+            .collect();
+
+        let mut glyphs = HashMap::new();
+        let mut kerning = HashMap::new();
+        let mut line_height = 0.0;
+        for (line_index, line) in description.lines().enumerate() {
+            let (tag, values) = parse_bmfont_line(line);
+            let value = |key: &str| -> Result<i32, FontError> {
+                values.get(key)
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| FontError::Parse { line: line_index + 1, message: format!("missing or invalid {}", key) })
+            };
+            match tag {
+                "common" => {
+                    if value("pages")? > 1 {
+                        return Err(FontError::Parse { line: line_index + 1, message: "only single page fonts are supported".to_string() });
+                    }
+                    line_height = value("lineHeight")? as f32;
+                }
+                "char" => {
+                    let Some(character) = char::from_u32(value("id")? as u32) else {
+                        continue;
+                    };
+                    let glyph = Glyph::new(
+                        size,
+                        [value("x")? as u32, value("y")? as u32],
+                        [value("width")? as u32, value("height")? as u32],
+                        [value("xoffset")? as f32, value("yoffset")? as f32],
+                        value("xadvance")? as f32,
+                    );
+                    glyphs.insert(character, glyph);
+                }
+                "kerning" => {
+                    let first = char::from_u32(value("first")? as u32);
+                    let second = char::from_u32(value("second")? as u32);
+                    if let (Some(first), Some(second)) = (first, second) {
+                        kerning.insert((first, second), value("amount")? as f32);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self { size, pixels, glyphs, kerning, line_height })
+    }
+
+    fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs.get(&character).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    /// Places the glyphs of `text` line by line, `\n` starts a new line.
+    pub fn layout(&self, text: &str, scale: f32) -> Vec<PositionedGlyph> {
+        let mut glyphs = Vec::with_capacity(text.len());
+        let mut pen = Vector2::new(0.0, 0.0);
+        let mut previous = None;
+        for character in text.chars() {
+            if character == '\n' {
+                pen = Vector2::new(0.0, pen.y + self.line_height * scale);
+                previous = None;
+                continue;
+            }
+            let Some(glyph) = self.glyph(character) else {
+                continue;
+            };
+            if let Some(previous) = previous {
+                pen.x += self.kerning.get(&(previous, character)).copied().unwrap_or(0.0) * scale;
+            }
+            if glyph.size[0] > 0.0 && glyph.size[1] > 0.0 {
+                glyphs.push(PositionedGlyph {
+                    position: pen + Vector2::new(glyph.offset[0], glyph.offset[1]) * scale,
+                    size: Vector2::new(glyph.size[0], glyph.size[1]) * scale,
+                    uv_min: glyph.uv_min,
+                    uv_max: glyph.uv_max,
+                });
+            }
+            pen.x += glyph.advance * scale;
+            previous = Some(character);
+        }
+        glyphs
+    }
+
+    /// Width of the longest line and height of all lines.
+    pub fn measure(&self, text: &str, scale: f32) -> Vector2<f32> {
+        let mut width: f32 = 0.0;
+        let mut lines = 0;
+        for line in text.split('\n') {
+            let mut line_width = 0.0;
+            let mut previous = None;
+            for character in line.chars() {
+                let Some(glyph) = self.glyph(character) else {
+                    continue;
+                };
+                if let Some(previous) = previous {
+                    line_width += self.kerning.get(&(previous, character)).copied().unwrap_or(0.0);
+                }
+                line_width += glyph.advance;
+                previous = Some(character);
+            }
+            width = width.max(line_width);
+            lines += 1;
+        }
+        Vector2::new(width, self.line_height * lines as f32) * scale
+    }
+}
+
+/// TTF/OTF files rasterised at `DEFAULT_PIXEL_SIZE`. BMFont pages are separate files, those fonts go through `FontAtlas::load`.
+impl Asset for FontAtlas {
+    fn from_bytes(bytes: Vec<u8>, path: &AssetPath, _context: &LoadContext) -> Result<Self, AssetError> {
+        if path.extension() == Some("fnt") {
+            return Err(AssetError::decode(path, "bmfont files need their page image, load them with FontAtlas::load"));
+        }
+        Self::from_ttf(bytes, DEFAULT_PIXEL_SIZE, DEFAULT_CHARACTERS).map_err(|error| AssetError::decode(path, error))
+    }
+}
+
+impl Glyph {
+    fn new(atlas_size: Vector2<u32>, position: [u32; 2], size: [u32; 2], offset: [f32; 2], advance: f32) -> Self {
+        let atlas_size = Vector2::new(atlas_size.x as f32, atlas_size.y as f32);
+        Self {
+            uv_min: [position[0] as f32 / atlas_size.x, position[1] as f32 / atlas_size.y],
+            uv_max: [(position[0] + size[0]) as f32 / atlas_size.x, (position[1] + size[1]) as f32 / atlas_size.y],
+            size: [size[0] as f32, size[1] as f32],
+            offset,
+            advance,
+        }
+    }
+}
+
+/// Fills the atlas row by row, a new shelf starts when a glyph does not fit into the current one.
+struct ShelfPacker {
+    width: u32,
+    cursor: [u32; 2],
+    shelf_height: u32,
+}
+
+impl ShelfPacker {
+    fn new(width: u32) -> Self {
+        Self { width, cursor: [GLYPH_PADDING, GLYPH_PADDING], shelf_height: 0 }
+    }
+
+    fn place(&mut self, width: u32, height: u32) -> [u32; 2] {
+        if self.cursor[0] + width + GLYPH_PADDING > self.width {
+            self.cursor = [GLYPH_PADDING, self.cursor[1] + self.shelf_height + GLYPH_PADDING];
+            self.shelf_height = 0;
+        }
+        let position = self.cursor;
+        self.cursor[0] += width + GLYPH_PADDING;
+        self.shelf_height = self.shelf_height.max(height);
+        position
+    }
+
+    fn height(&self) -> u32 {
+        self.cursor[1] + self.shelf_height + GLYPH_PADDING
+    }
+}
+
+/// Splits `tag key=value key="quoted value"` into the tag and its values.
+fn parse_bmfont_line(line: &str) -> (&str, HashMap<&str, &str>) {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_once(' ').unwrap_or((line, ""));
+    let mut values = HashMap::new();
+    while let Some((key, after_key)) = rest.trim_start().split_once('=') {
+        let (value, after_value) = match after_key.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => after_key.split_once(' ').unwrap_or((after_key, "")),
+        };
+        values.insert(key.trim(), value);
+        rest = after_value;
+    }
+    (tag, values)
+}
+
+fn parse_bmfont_page(description: &str) -> Result<String, FontError> {
+    description.lines()
+        .map(parse_bmfont_line)
+        .find(|(tag, values)| *tag == "page" && values.get("id") == Some(&"0"))
+        .and_then(|(_, values)| values.get("file").map(|file| file.to_string()))
+        .ok_or_else(|| FontError::Parse { line: 0, message: "no page with id 0".to_string() })
+}
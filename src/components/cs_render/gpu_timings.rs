@@ -0,0 +1,151 @@
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::{Mut, World};
+use wgpu::{Buffer, CommandEncoder, Device, QuerySet, Queue};
+
+use crate::main_loop::Render;
+
+/// Passes measured in `render_instances`, timestamp `n` is written before pass `n` and `n + 1` after it.
+pub const TIMED_PASSES: [&str; 5] = ["Visibility compute", "Tiles", "Overlay and text", "Minimap", "Ui"];
+const TIMESTAMP_COUNT: u32 = TIMED_PASSES.len() as u32 + 1;
+/// Frames that can wait for their readback at the same time, further frames are not measured.
+const READBACK_SLOTS: usize = 3;
+
+const SLOT_FREE: u8 = 0;
+const SLOT_MAPPING: u8 = 1;
+const SLOT_MAPPED: u8 = 2;
+
+#[derive(Debug, Clone)]
+pub struct PassTiming {
+    pub name: &'static str,
+    pub milliseconds: f32,
+}
+
+/// GPU time of each pass of the last measured frame, empty when the adapter has no `TIMESTAMP_QUERY` support.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct GpuTimings {
+    pub supported: bool,
+    pub passes: Vec<PassTiming>,
+}
+
+struct ReadbackSlot {
+    buffer: Buffer,
+    state: Arc<AtomicU8>,
+}
+
+/// Owns the query set, only inserted into the world when timestamp queries are supported.
+#[derive(Resource)]
+pub struct GpuProfiler {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    slots: Vec<ReadbackSlot>,
+    /// Nanoseconds per timestamp tick.
+    timestamp_period: f32,
+}
+
+impl GpuProfiler {
+    pub fn register(device: &Device, queue: &Queue, world: &mut World) {
+        let supported = device.features().contains(wgpu::Features::TIMESTAMP_QUERY);
+        world.insert_resource(GpuTimings { supported, passes: Vec::new() });
+        if !supported {
+            return;
+        }
+
+        let size = (TIMESTAMP_COUNT as usize * mem::size_of::<u64>()) as wgpu::BufferAddress;
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("pass_timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: TIMESTAMP_COUNT,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("timestamp_resolve_buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let slots = (0..READBACK_SLOTS)
+            .map(|_| ReadbackSlot {
+                buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("timestamp_readback_buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                state: Arc::new(AtomicU8::new(SLOT_FREE)),
+            })
+            .collect();
+
+        world.insert_resource(GpuProfiler {
+            query_set,
+            resolve_buffer,
+            slots,
+            timestamp_period: queue.get_timestamp_period(),
+        });
+    }
+
+    pub fn write_timestamp(&self, encoder: &mut CommandEncoder, index: u32) {
+        encoder.write_timestamp(&self.query_set, index);
+    }
+
+    /// Copies the timestamps of the submitted frame into a free readback buffer and starts mapping it.
+    fn resolve(&mut self, device: &Device, queue: &Queue) {
+        let Some(slot) = self.slots.iter().find(|slot| slot.state.load(Ordering::Acquire) == SLOT_FREE) else {
+            return;
+        };
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Timestamp Resolve Encoder"),
+        });
+        encoder.resolve_query_set(&self.query_set, 0..TIMESTAMP_COUNT, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &slot.buffer, 0, slot.buffer.size());
+        queue.submit(std::iter::once(encoder.finish()));
+
+        slot.state.store(SLOT_MAPPING, Ordering::Release);
+        let state = slot.state.clone();
+        slot.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            state.store(if result.is_ok() { SLOT_MAPPED } else { SLOT_FREE }, Ordering::Release);
+        });
+    }
+
+    /// Reads every readback buffer that finished mapping and returns the timings of the newest one.
+    fn collect(&mut self, device: &Device) -> Option<Vec<PassTiming>> {
+        device.poll(wgpu::Maintain::Poll);
+        let mut timings = None;
+        for slot in self.slots.iter() {
+            if slot.state.load(Ordering::Acquire) != SLOT_MAPPED {
+                continue;
+            }
+            {
+                let data = slot.buffer.slice(..).get_mapped_range();
+                let timestamps: &[u64] = bytemuck::cast_slice(&data);
+                timings = Some(TIMED_PASSES.iter()
+                    .enumerate()
+                    .map(|(index, name)| PassTiming {
+                        name,
+                        milliseconds: timestamps[index + 1].saturating_sub(timestamps[index]) as f32 * self.timestamp_period / 1_000_000.0,
+                    })
+                    .collect());
+            }
+            slot.buffer.unmap();
+            slot.state.store(SLOT_FREE, Ordering::Release);
+        }
+        timings
+    }
+}
+
+/// Called after a frame was submitted, does nothing when timestamp queries are unsupported.
+pub fn resolve_gpu_timings(world: &mut World) {
+    if !world.contains_resource::<GpuProfiler>() {
+        return;
+    }
+    let timings = world.resource_scope(|world, mut profiler: Mut<GpuProfiler>| {
+        let render = world.get_resource::<Render>().unwrap();
+        profiler.resolve(&render.device, &render.queue);
+        profiler.collect(&render.device)
+    });
+    if let Some(passes) = timings {
+        world.get_resource_mut::<GpuTimings>().unwrap().passes = passes;
+    }
+}
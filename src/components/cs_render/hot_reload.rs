@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::{Mut, World};
+use wgpu::{BindGroupLayout, Device, ShaderModule};
+
+use crate::components::cs_io::assets::{Assets, Handle};
+use crate::components::cs_render::minimap::Minimap;
+use crate::components::cs_render::overlay::OverlayRenderer;
+use crate::components::cs_render::shader::texture_sampler_binding;
+use crate::components::cs_render::shader::tile_animation_binding::TileAnimationBinding;
+use crate::components::cs_render::shader_types::texture::Texture;
+use crate::components::cs_render::sprite::SpriteRenderer;
+use crate::components::cs_render::text::TextRenderer;
+use crate::components::cs_render::world_render_pipline;
+use crate::components::cs_world::editor;
+use crate::components::cs_world::map::Map;
+use crate::components::cs_world::map_data::MapData;
+use crate::components::cs_world::tile_registry::TileRegistry;
+use crate::main_loop::{DummyTest, Render};
+
+/// Everything needed to rebuild the tile pipelines when the shader or the atlas changes on disk.
+#[derive(Resource)]
+pub struct WorldPipelineSources {
+    pub shader: Handle<ShaderModule>,
+    pub diffuse_texture: Handle<Texture>,
+    /// Texture, camera, visible tiles and tile animation layouts in bind group order.
+    pub render_bind_group_layouts: [BindGroupLayout; 4],
+    /// Compute params, all tiles and visible tiles layouts in bind group order.
+    pub compute_bind_group_layouts: [BindGroupLayout; 3],
+    shader_version: u32,
+    texture_version: u32,
+}
+
+impl WorldPipelineSources {
+    pub fn new(shader: Handle<ShaderModule>, diffuse_texture: Handle<Texture>, render_bind_group_layouts: [BindGroupLayout; 4], compute_bind_group_layouts: [BindGroupLayout; 3]) -> Self {
+        Self {
+            shader_version: shader.version(),
+            texture_version: diffuse_texture.version(),
+            shader,
+            diffuse_texture,
+            render_bind_group_layouts,
+            compute_bind_group_layouts,
+        }
+    }
+}
+
+/// A shader handle and the version the current pipelines were built from.
+pub struct ShaderSource {
+    shader: Handle<ShaderModule>,
+    version: u32,
+}
+
+impl ShaderSource {
+    pub fn new(shader: Handle<ShaderModule>) -> Self {
+        Self { version: shader.version(), shader }
+    }
+
+    /// The shader if it was reloaded since the last call.
+    fn take_reloaded(&mut self) -> Option<Arc<ShaderModule>> {
+        if self.shader.version() == self.version {
+            return None;
+        }
+        self.version = self.shader.version();
+        self.shader.get()
+    }
+}
+
+/// Shaders of the renderers drawn on top of the tiles, kept alive so they are reloaded with their files.
+#[derive(Resource)]
+pub struct RendererShaders {
+    pub overlay: ShaderSource,
+    pub text: ShaderSource,
+    pub minimap: ShaderSource,
+    pub sprite: ShaderSource,
+}
+
+/// Data files the world was built from, a reload replaces the tile definitions or the map in place.
+#[derive(Resource)]
+pub struct WorldDataSources {
+    pub tile_definitions: Handle<TileRegistry>,
+    /// The map the game started from, generated instead while the file does not load.
+    pub map: Handle<MapData>,
+    tile_definitions_version: u32,
+    map_version: u32,
+}
+
+impl WorldDataSources {
+    pub fn new(tile_definitions: Handle<TileRegistry>, map: Handle<MapData>) -> Self {
+        Self {
+            tile_definitions_version: tile_definitions.version(),
+            map_version: map.version(),
+            tile_definitions,
+            map,
+        }
+    }
+}
+
+/// Reloads assets whose files changed and rebuilds the pipelines, bind groups and world data that use them.
+pub fn reload_changed_assets(world: &mut World) {
+    if world.get_resource_mut::<Assets>().unwrap().queue_changed() == 0 {
+        return;
+    }
+    world.resource_scope(|world, mut assets: Mut<Assets>| {
+        let render = world.get_resource::<Render>().unwrap();
+        pollster::block_on(assets.load_pending(&render.device, &render.queue));
+    });
+    world.resource_scope(rebuild_world_pipelines);
+    if world.contains_resource::<RendererShaders>() {
+        world.resource_scope(rebuild_renderer_pipelines);
+    }
+    if world.contains_resource::<WorldDataSources>() {
+        world.resource_scope(rebuild_world_data);
+    }
+}
+
+fn rebuild_renderer_pipelines(world: &mut World, mut shaders: Mut<RendererShaders>) {
+    world.resource_scope(|world, render: Mut<Render>| {
+        rebuild_renderer(world, &render.device, &mut shaders.overlay, OverlayRenderer::rebuild_pipelines);
+        rebuild_renderer(world, &render.device, &mut shaders.text, TextRenderer::rebuild_pipelines);
+        rebuild_renderer(world, &render.device, &mut shaders.minimap, Minimap::rebuild_pipelines);
+        rebuild_renderer(world, &render.device, &mut shaders.sprite, SpriteRenderer::rebuild_pipelines);
+    });
+}
+
+fn rebuild_renderer<R: Resource>(world: &mut World, device: &Device, source: &mut ShaderSource, rebuild: fn(&mut R, &Device, &ShaderModule) -> Result<(), wgpu::Error>) {
+    let (Some(shader), Some(mut renderer)) = (source.take_reloaded(), world.get_resource_mut::<R>()) else {
+        return;
+    };
+    match rebuild(&mut renderer, device, &shader) {
+        Ok(()) => log::info!("rebuilt the pipelines of {}", source.shader.path()),
+        Err(error) => log::error!("keeping the previous pipelines, {} does not fit them: {}", source.shader.path(), error),
+    }
+}
+
+/// New tile definitions must keep every existing kind at its id, the map stores kinds by id.
+/// A changed map file replaces the map as one undoable edit.
+fn rebuild_world_data(world: &mut World, mut sources: Mut<WorldDataSources>) {
+    if sources.tile_definitions.version() != sources.tile_definitions_version {
+        sources.tile_definitions_version = sources.tile_definitions.version();
+        let registry = TileRegistry::clone(&sources.tile_definitions.expect_loaded());
+        let moved = world.resource::<TileRegistry>().iter()
+            .find(|(id, definition)| registry.id(&definition.name) != Some(*id))
+            .map(|(_, definition)| definition.name.clone());
+        match moved {
+            Some(name) => {
+                log::error!("keeping the previous tile definitions, {} moves or removes {}", sources.tile_definitions.path(), name);
+            }
+            None => {
+                let layout = &world.resource::<WorldPipelineSources>().render_bind_group_layouts[3];
+                let tile_animation_binding = TileAnimationBinding::create(&world.resource::<Render>().device, &registry, layout);
+                world.insert_resource(tile_animation_binding);
+                world.resource_mut::<Map>().refresh_tiles(&registry);
+                world.insert_resource(registry);
+                log::info!("reloaded tile definitions");
+            }
+        }
+    }
+
+    if sources.map.version() != sources.map_version {
+        sources.map_version = sources.map.version();
+        let data = sources.map.expect_loaded();
+        match editor::replace_map(world, &data, format!("reload {}", sources.map.path())) {
+            Ok(()) => log::info!("reloaded {}", sources.map.path()),
+            Err(error) => log::error!("keeping the current map, {} can not replace it: {}", sources.map.path(), error),
+        }
+    }
+}
+
+fn rebuild_world_pipelines(world: &mut World, mut sources: Mut<WorldPipelineSources>) {
+    let render = world.get_resource::<Render>().unwrap();
+    let mut pipelines = None;
+    let mut diffuse_bind_group = None;
+
+    if sources.shader.version() != sources.shader_version {
+        sources.shader_version = sources.shader.version();
+        let shader = sources.shader.expect_loaded();
+        let render_layouts: Vec<&BindGroupLayout> = sources.render_bind_group_layouts.iter().collect();
+        let compute_layouts: Vec<&BindGroupLayout> = sources.compute_bind_group_layouts.iter().collect();
+        let created = validated(&render.device, || (
+            world_render_pipline::create_render_pipline(&render.device, &render.config, &shader, &render_layouts),
+            world_render_pipline::create_compute_pipline(&render.device, &shader, &compute_layouts),
+        ));
+        match created {
+            Ok(created) => pipelines = Some(created),
+            Err(error) => log::error!("keeping the previous tile pipelines, {} does not fit them: {}", sources.shader.path(), error),
+        }
+    }
+
+    if sources.diffuse_texture.version() != sources.texture_version {
+        sources.texture_version = sources.diffuse_texture.version();
+        diffuse_bind_group = Some(texture_sampler_binding::create_diffuse_bind_group(
+            &render.device,
+            &sources.diffuse_texture.expect_loaded(),
+            &sources.render_bind_group_layouts[0],
+        ));
+    }
+
+    let mut dummy_test = world.get_resource_mut::<DummyTest>().unwrap();
+    if let Some((render_pipeline, compute_pipeline)) = pipelines {
+        dummy_test.render_pipeline = render_pipeline;
+        dummy_test.compute_pipeline = compute_pipeline;
+        log::info!("rebuilt tile pipelines");
+    }
+    if let Some(diffuse_bind_group) = diffuse_bind_group {
+        dummy_test.diffuse_bind_group = diffuse_bind_group;
+        log::info!("re-uploaded tile atlas");
+    }
+}
+
+/// Runs `create` inside a validation error scope and returns the first error instead of panicking.
+pub fn validated<T>(device: &Device, create: impl FnOnce() -> T) -> Result<T, wgpu::Error> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = create();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(error) => Err(error),
+        None => Ok(value),
+    }
+}
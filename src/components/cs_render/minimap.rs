@@ -0,0 +1,415 @@
+use std::mem;
+
+use bevy_ecs::event::EventReader;
+use bevy_ecs::schedule::{IntoSystemConfig, Schedule};
+use bevy_ecs::system::{Res, ResMut, Resource};
+use bevy_ecs::world::World;
+use cgmath::Vector2;
+use wgpu::{BindGroup, Buffer, Device, Queue, RenderPipeline, ShaderModule, TextureFormat};
+use wgpu::util::DeviceExt;
+use winit::event::MouseButton;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::components::cs_render::hot_reload;
+use crate::components::cs_render::shader_types::texture::Texture;
+use crate::components::cs_util::camera::CustomCamera;
+use crate::components::cs_util::input::{Cursor, Input};
+use crate::components::cs_ui::ui_layer::UiFocus;
+use crate::components::cs_world::map::{self, Map, TilesChanged};
+use crate::components::cs_world::tile_registry::TileRegistry;
+use crate::main_loop::Render;
+
+const MINIMAP_SIZE: Vector2<f32> = Vector2::new(256.0, 128.0);
+const MINIMAP_MARGIN: f32 = 10.0;
+
+/// One RGBA pixel per tile, coloured by the tile registry. Needs no GPU so it can also be used to export map overviews.
+#[derive(Debug, Clone)]
+pub struct MinimapImage {
+    pub size: Vector2<i32>,
+    pub pixels: Vec<u8>,
+}
+
+impl MinimapImage {
+    pub fn from_map(map: &Map, registry: &TileRegistry) -> Self {
+        let mut image = Self {
+            size: map.size,
+            pixels: vec![0; (map.size.x * map.size.y * 4) as usize],
+        };
+        for index in 0..map.kinds.len() {
+            image.update_tile(map, registry, index);
+        }
+        image
+    }
+
+    pub fn update_tile(&mut self, map: &Map, registry: &TileRegistry, index: usize) {
+        let color = registry.get(map.kinds[index]).minimap_color;
+        self.pixels[index * 4..index * 4 + 4].copy_from_slice(&color);
+    }
+
+    pub fn to_image(&self) -> image::RgbaImage {
+        image::RgbaImage::from_raw(self.size.x as u32, self.size.y as u32, self.pixels.clone()).unwrap()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_png<P: AsRef<std::path::Path>>(&self, path: P) -> image::ImageResult<()> {
+        self.to_image().save_with_format(path, image::ImageFormat::Png)
+    }
+}
+
+/// Placement of the minimap diamond on screen, the map's (0, 0) corner is at the top.
+#[derive(Debug, Clone, Copy)]
+pub struct MinimapLayout {
+    pub center: Vector2<f32>,
+    pub half_extent: Vector2<f32>,
+    pub map_size: Vector2<f32>,
+}
+
+impl MinimapLayout {
+    pub fn bottom_right(screen_size: Vector2<f32>, map_size: Vector2<i32>) -> Self {
+        let half_extent = MINIMAP_SIZE / 2.0;
+        Self {
+            center: screen_size - half_extent - Vector2::new(MINIMAP_MARGIN, MINIMAP_MARGIN),
+            half_extent,
+            map_size: Vector2::new(map_size.x as f32, map_size.y as f32),
+        }
+    }
+
+    /// Normalized texture coordinate of a position in map tile space, tiles span from -0.5 to +0.5 around their position.
+    fn map_to_uv(&self, map_pos: Vector2<f32>) -> Vector2<f32> {
+        Vector2::new((map_pos.x + 0.5) / self.map_size.x, (map_pos.y + 0.5) / self.map_size.y)
+    }
+
+    fn uv_to_screen(&self, uv: Vector2<f32>) -> Vector2<f32> {
+        Vector2::new(
+            self.center.x + (uv.x - uv.y) * self.half_extent.x,
+            self.center.y + (uv.x + uv.y - 1.0) * self.half_extent.y,
+        )
+    }
+
+    pub fn map_to_screen(&self, map_pos: Vector2<f32>) -> Vector2<f32> {
+        self.uv_to_screen(self.map_to_uv(map_pos))
+    }
+
+    /// Returns the map position under a screen position or `None` if it is outside of the minimap.
+    pub fn screen_to_map(&self, screen_pos: Vector2<f32>) -> Option<Vector2<f32>> {
+        let a = (screen_pos.x - self.center.x) / self.half_extent.x;
+        let b = (screen_pos.y - self.center.y) / self.half_extent.y + 1.0;
+        let uv = Vector2::new((a + b) / 2.0, (b - a) / 2.0);
+        if !(0.0..=1.0).contains(&uv.x) || !(0.0..=1.0).contains(&uv.y) {
+            return None;
+        }
+        Some(Vector2::new(uv.x * self.map_size.x - 0.5, uv.y * self.map_size.y - 0.5))
+    }
+
+    fn vertices(&self) -> [MinimapVertex; 6] {
+        let corner = |u: f32, v: f32| MinimapVertex {
+            position: self.uv_to_screen(Vector2::new(u, v)).into(),
+            uv: [u, v],
+        };
+        [
+            corner(0.0, 0.0), corner(1.0, 0.0), corner(1.0, 1.0),
+            corner(0.0, 0.0), corner(1.0, 1.0), corner(0.0, 1.0),
+        ]
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct MinimapVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+}
+
+impl MinimapVertex {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<MinimapVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Minimap {
+    pub image: MinimapImage,
+    pub layout: MinimapLayout,
+    texture: wgpu::Texture,
+    bind_group: BindGroup,
+    screen_buffer: Buffer,
+    map_vertex_buffer: Buffer,
+    view_vertex_buffer: Buffer,
+    /// Kept to rebuild the pipelines when the shader is reloaded.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pipeline_layout: wgpu::PipelineLayout,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    format: TextureFormat,
+    texture_pipeline: RenderPipeline,
+    view_pipeline: RenderPipeline,
+}
+
+impl Minimap {
+    /// Creates the GPU side of the minimap without touching the ECS, e.g. for the headless renderer.
+    pub fn create(device: &Device, queue: &Queue, format: TextureFormat, shader: &ShaderModule, image: MinimapImage, screen_size: Vector2<f32>) -> Self {
+        let size = wgpu::Extent3d {
+            width: image.size.x as u32,
+            height: image.size.y as u32,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("minimap_texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        write_pixels(queue, &texture, &image, Vector2::new(0, 0), size);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = Texture::create_sampler(device);
+
+        let layout = MinimapLayout::bottom_right(screen_size, image.size);
+        let screen_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("minimap_screen_buffer"),
+            contents: bytemuck::cast_slice(&[screen_size.x, screen_size.y, 0.0, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let map_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("minimap_vertex_buffer"),
+            contents: bytemuck::cast_slice(&layout.vertices()),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        let view_vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("minimap_view_vertex_buffer"),
+            size: (mem::size_of::<MinimapVertex>() * 5) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("minimap_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: screen_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("minimap_bind_group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Minimap Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let texture_pipeline = create_minimap_pipeline(device, &pipeline_layout, shader, format, "fs_texture", wgpu::PrimitiveTopology::TriangleList);
+        let view_pipeline = create_minimap_pipeline(device, &pipeline_layout, shader, format, "fs_view", wgpu::PrimitiveTopology::LineStrip);
+
+        Self {
+            image,
+            layout,
+            texture,
+            bind_group,
+            screen_buffer,
+            map_vertex_buffer,
+            view_vertex_buffer,
+            pipeline_layout,
+            format,
+            texture_pipeline,
+            view_pipeline,
+        }
+    }
+
+    /// Recreates the pipelines from a reloaded shader, the previous ones stay if it does not fit.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn rebuild_pipelines(&mut self, device: &Device, shader: &ShaderModule) -> Result<(), wgpu::Error> {
+        let (texture_pipeline, view_pipeline) = hot_reload::validated(device, || (
+            create_minimap_pipeline(device, &self.pipeline_layout, shader, self.format, "fs_texture", wgpu::PrimitiveTopology::TriangleList),
+            create_minimap_pipeline(device, &self.pipeline_layout, shader, self.format, "fs_view", wgpu::PrimitiveTopology::LineStrip),
+        ))?;
+        self.texture_pipeline = texture_pipeline;
+        self.view_pipeline = view_pipeline;
+        Ok(())
+    }
+
+    pub fn register(minimap: Minimap, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(minimap);
+        schedule.add_system(update_minimap.after(map::publish_tile_changes));
+        schedule.add_system(minimap_click);
+    }
+
+    /// Uploads changed tiles, whole rows are written once many tiles changed at the same time.
+    pub fn update_tiles(&mut self, queue: &Queue, map: &Map, registry: &TileRegistry, indices: &[usize]) {
+        for index in indices {
+            self.image.update_tile(map, registry, *index);
+        }
+        if indices.len() > self.image.size.x as usize {
+            let size = wgpu::Extent3d {
+                width: self.image.size.x as u32,
+                height: self.image.size.y as u32,
+                depth_or_array_layers: 1,
+            };
+            write_pixels(queue, &self.texture, &self.image, Vector2::new(0, 0), size);
+            return;
+        }
+        for index in indices {
+            let pos = Vector2::new(*index as i32 % self.image.size.x, *index as i32 / self.image.size.x);
+            write_pixels(queue, &self.texture, &self.image, pos, wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 });
+        }
+    }
+
+    pub fn update_view(&mut self, queue: &Queue, screen_size: Vector2<f32>, camera: &CustomCamera) {
+        self.layout = MinimapLayout::bottom_right(screen_size, self.image.size);
+        let corners = camera.view_corners();
+        let view_vertices: Vec<MinimapVertex> = [corners[0], corners[1], corners[2], corners[3], corners[0]].iter()
+            .map(|corner| MinimapVertex {
+                position: self.layout.map_to_screen(*corner).into(),
+                uv: [0.0, 0.0],
+            })
+            .collect();
+        queue.write_buffer(&self.screen_buffer, 0, bytemuck::cast_slice(&[screen_size.x, screen_size.y, 0.0, 0.0]));
+        queue.write_buffer(&self.map_vertex_buffer, 0, bytemuck::cast_slice(&self.layout.vertices()));
+        queue.write_buffer(&self.view_vertex_buffer, 0, bytemuck::cast_slice(&view_vertices));
+    }
+
+    /// Returns the number of draw calls.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) -> u32 {
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_pipeline(&self.texture_pipeline);
+        render_pass.set_vertex_buffer(0, self.map_vertex_buffer.slice(..));
+        render_pass.draw(0..6, 0..1);
+        render_pass.set_pipeline(&self.view_pipeline);
+        render_pass.set_vertex_buffer(0, self.view_vertex_buffer.slice(..));
+        render_pass.draw(0..5, 0..1);
+        2
+    }
+}
+
+fn write_pixels(queue: &Queue, texture: &wgpu::Texture, image: &MinimapImage, origin: Vector2<i32>, size: wgpu::Extent3d) {
+    let offset = ((origin.y * image.size.x + origin.x) * 4) as wgpu::BufferAddress;
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d { x: origin.x as u32, y: origin.y as u32, z: 0 },
+        },
+        &image.pixels,
+        wgpu::ImageDataLayout {
+            offset,
+            bytes_per_row: Some(4 * image.size.x as u32),
+            rows_per_image: Some(image.size.y as u32),
+        },
+        size,
+    );
+}
+
+fn create_minimap_pipeline(device: &Device, layout: &wgpu::PipelineLayout, shader: &ShaderModule, format: TextureFormat, fragment_entry_point: &str, topology: wgpu::PrimitiveTopology) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Minimap Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[MinimapVertex::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fragment_entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+pub fn update_minimap(
+    render: Res<Render>,
+    mut minimap: ResMut<Minimap>,
+    map: Res<Map>,
+    registry: Res<TileRegistry>,
+    camera: Res<CustomCamera>,
+    mut tiles_changed: EventReader<TilesChanged>,
+) {
+    for event in tiles_changed.iter() {
+        minimap.update_tiles(&render.queue, &map, &registry, &event.indices);
+    }
+    let screen_size = Vector2::new(render.config.width as f32, render.config.height as f32);
+    minimap.update_view(&render.queue, screen_size, &camera);
+}
+
+pub fn minimap_click(
+    mouse_input: Res<Input<MouseButton>>,
+    cursor: Res<Cursor>,
+    minimap: Res<Minimap>,
+    ui_focus: Res<UiFocus>,
+    mut camera: ResMut<CustomCamera>,
+) {
+    if !mouse_input.pressed(MouseButton::Left) || ui_focus.pointer {
+        return;
+    }
+    if let Some(map_pos) = minimap.layout.screen_to_map(cursor.position) {
+        camera.look_at(map_pos);
+    }
+}
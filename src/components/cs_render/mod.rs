@@ -2,3 +2,11 @@ pub mod shader_types;
 pub mod shader;
 pub mod world_render_pipline;
 pub mod render_loop;
+pub mod minimap;
+pub mod overlay;
+pub mod font;
+pub mod text;
+pub mod sprite;
+pub mod gpu_timings;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod hot_reload;
@@ -0,0 +1,297 @@
+use std::mem;
+
+use bevy_ecs::schedule::Schedule;
+use bevy_ecs::system::{Res, ResMut, Resource};
+use bevy_ecs::world::World;
+use cgmath::Vector2;
+use wgpu::{BindGroup, BindGroupLayout, Buffer, Device, RenderPipeline, ShaderModule, TextureFormat};
+use winit::event::VirtualKeyCode;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::components::cs_render::hot_reload;
+use crate::components::cs_ui::ui_layer::{UiContext, UiFocus};
+use crate::components::cs_util::camera::CustomCamera;
+use crate::components::cs_util::input::{Cursor, Input};
+use crate::components::cs_world::map;
+use crate::components::cs_world::map::Map;
+use crate::main_loop::Render;
+
+const GRID_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.25];
+const HOVER_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.35];
+pub const INVALID_COLOR: [f32; 4] = [0.9, 0.1, 0.1, 0.45];
+
+/// A set of tiles drawn with one colour, e.g. a building footprint or a selected area.
+#[derive(Debug, Clone)]
+pub struct OverlayLayer {
+    pub name: &'static str,
+    pub tiles: Vec<Vector2<i32>>,
+    pub color: [f32; 4],
+}
+
+/// Everything the overlay pass draws on top of the world, written by the game tools each update.
+#[derive(Debug, Clone, Resource)]
+pub struct TileOverlay {
+    pub show_grid: bool,
+    /// Tile under the cursor, updated by `update_hovered_tile`.
+    pub hovered_tile: Option<Vector2<i32>>,
+    pub show_hover: bool,
+    layers: Vec<OverlayLayer>,
+}
+
+impl Default for TileOverlay {
+    fn default() -> Self {
+        Self {
+            show_grid: false,
+            hovered_tile: None,
+            show_hover: true,
+            layers: Vec::new(),
+        }
+    }
+}
+
+impl TileOverlay {
+    /// Replaces the tiles of the layer with the given name or adds it on top of the others.
+    pub fn set_layer(&mut self, name: &'static str, tiles: Vec<Vector2<i32>>, color: [f32; 4]) {
+        match self.layers.iter_mut().find(|layer| layer.name == name) {
+            Some(layer) => {
+                layer.tiles = tiles;
+                layer.color = color;
+            }
+            None => self.layers.push(OverlayLayer { name, tiles, color }),
+        }
+    }
+
+    pub fn remove_layer(&mut self, name: &'static str) {
+        self.layers.retain(|layer| layer.name != name);
+    }
+
+    pub fn layers(&self) -> &[OverlayLayer] {
+        &self.layers
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OverlayInstance {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl OverlayInstance {
+    /// Diamond on top of the tile, raised with it.
+    fn new(tile: Vector2<i32>, map: &Map, color: [f32; 4]) -> Self {
+        let ground = map::map_to_screen_pos_centered(Vector2::new(tile.x as f32, tile.y as f32));
+        let height = map.height(tile).unwrap_or(0) as f32 * map::HEIGHT_STEP;
+        Self {
+            position: [ground.x, ground.y - height],
+            color,
+        }
+    }
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<OverlayInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Builds the filled and outlined diamonds for the overlay, the fills come first in the returned list.
+pub fn build_overlay_instances(overlay: &TileOverlay, map: &Map, view_corners: [Vector2<f32>; 4]) -> (Vec<OverlayInstance>, usize) {
+    let map_size = map.size;
+    let in_map = |tile: &Vector2<i32>| map.in_bounds(*tile);
+    let mut instances = Vec::new();
+    for layer in overlay.layers.iter() {
+        instances.extend(layer.tiles.iter().filter(|tile| in_map(tile)).map(|tile| OverlayInstance::new(*tile, map, layer.color)));
+    }
+    let hovered = overlay.hovered_tile.filter(|tile| overlay.show_hover && in_map(tile));
+    if let Some(tile) = hovered {
+        instances.push(OverlayInstance::new(tile, map, HOVER_COLOR));
+    }
+    let fill_count = instances.len();
+
+    if overlay.show_grid {
+        let min_x = view_corners.iter().map(|corner| corner.x).fold(f32::MAX, f32::min).floor() as i32;
+        let max_x = view_corners.iter().map(|corner| corner.x).fold(f32::MIN, f32::max).ceil() as i32;
+        let min_y = view_corners.iter().map(|corner| corner.y).fold(f32::MAX, f32::min).floor() as i32;
+        let max_y = view_corners.iter().map(|corner| corner.y).fold(f32::MIN, f32::max).ceil() as i32;
+        // raised tiles below the bottom of the view reach up into it
+        let raised = (map::MAX_HEIGHT as f32 * map::HEIGHT_STEP / map::TILE_SIZE_HALF.y).ceil() as i32;
+        for y in min_y.max(0)..=(max_y + raised).min(map_size.y - 1) {
+            for x in min_x.max(0)..=(max_x + raised).min(map_size.x - 1) {
+                instances.push(OverlayInstance::new(Vector2::new(x, y), map, GRID_COLOR));
+            }
+        }
+    }
+    if let Some(tile) = hovered {
+        instances.push(OverlayInstance::new(tile, map, [1.0, 1.0, 1.0, 0.9]));
+    }
+    (instances, fill_count)
+}
+
+#[derive(Resource)]
+pub struct OverlayRenderer {
+    instance_buffer: Buffer,
+    capacity: usize,
+    fill_count: u32,
+    instance_count: u32,
+    /// Kept to rebuild the pipelines when the shader is reloaded.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pipeline_layout: wgpu::PipelineLayout,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    format: TextureFormat,
+    fill_pipeline: RenderPipeline,
+    outline_pipeline: RenderPipeline,
+}
+
+impl OverlayRenderer {
+    pub fn new(device: &Device, format: TextureFormat, shader: &ShaderModule, camera_bind_group_layout: &BindGroupLayout) -> Self {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let capacity = 1024;
+        Self {
+            instance_buffer: create_instance_buffer(device, capacity),
+            capacity,
+            fill_count: 0,
+            instance_count: 0,
+            fill_pipeline: create_overlay_pipeline(device, &pipeline_layout, shader, format, "vs_fill", wgpu::PrimitiveTopology::TriangleList),
+            outline_pipeline: create_overlay_pipeline(device, &pipeline_layout, shader, format, "vs_outline", wgpu::PrimitiveTopology::LineList),
+            pipeline_layout,
+            format,
+        }
+    }
+
+    /// Recreates the pipelines from a reloaded shader, the previous ones stay if it does not fit.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn rebuild_pipelines(&mut self, device: &Device, shader: &ShaderModule) -> Result<(), wgpu::Error> {
+        let (fill_pipeline, outline_pipeline) = hot_reload::validated(device, || (
+            create_overlay_pipeline(device, &self.pipeline_layout, shader, self.format, "vs_fill", wgpu::PrimitiveTopology::TriangleList),
+            create_overlay_pipeline(device, &self.pipeline_layout, shader, self.format, "vs_outline", wgpu::PrimitiveTopology::LineList),
+        ))?;
+        self.fill_pipeline = fill_pipeline;
+        self.outline_pipeline = outline_pipeline;
+        Ok(())
+    }
+
+    pub fn register(overlay_renderer: OverlayRenderer, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(overlay_renderer);
+        world.insert_resource(TileOverlay::default());
+        schedule.add_system(update_hovered_tile);
+        schedule.add_system(update_overlay_instances);
+        schedule.add_system(toggle_grid);
+    }
+
+    pub fn upload(&mut self, device: &Device, queue: &wgpu::Queue, instances: &[OverlayInstance], fill_count: usize) {
+        if instances.len() > self.capacity {
+            self.capacity = instances.len().next_power_of_two();
+            self.instance_buffer = create_instance_buffer(device, self.capacity);
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(instances));
+        self.fill_count = fill_count as u32;
+        self.instance_count = instances.len() as u32;
+    }
+
+    /// Returns the number of draw calls.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera_bind_group: &'a BindGroup) -> u32 {
+        if self.instance_count == 0 {
+            return 0;
+        }
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.set_pipeline(&self.fill_pipeline);
+        render_pass.draw(0..6, 0..self.fill_count);
+        render_pass.set_pipeline(&self.outline_pipeline);
+        render_pass.draw(0..8, self.fill_count..self.instance_count);
+        2
+    }
+}
+
+fn create_instance_buffer(device: &Device, capacity: usize) -> Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("overlay_instance_buffer"),
+        size: (capacity * mem::size_of::<OverlayInstance>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_overlay_pipeline(device: &Device, layout: &wgpu::PipelineLayout, shader: &ShaderModule, format: TextureFormat, vertex_entry_point: &str, topology: wgpu::PrimitiveTopology) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Overlay Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: vertex_entry_point,
+            buffers: &[OverlayInstance::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+pub fn update_hovered_tile(cursor: Res<Cursor>, camera: Res<CustomCamera>, ui_focus: Res<UiFocus>, map: Res<Map>, mut overlay: ResMut<TileOverlay>) {
+    let tile = match ui_focus.pointer {
+        true => None,
+        false => Some(map.pick_tile(camera.screen_to_world(cursor.position))),
+    };
+    if overlay.hovered_tile != tile {
+        overlay.hovered_tile = tile;
+    }
+}
+
+pub fn update_overlay_instances(
+    render: Res<Render>,
+    camera: Res<CustomCamera>,
+    overlay: Res<TileOverlay>,
+    map: Res<Map>,
+    mut overlay_renderer: ResMut<OverlayRenderer>,
+) {
+    let (instances, fill_count) = build_overlay_instances(&overlay, &map, camera.view_corners());
+    overlay_renderer.upload(&render.device, &render.queue, &instances, fill_count);
+}
+
+pub fn toggle_grid(keyboard_input: Res<Input<VirtualKeyCode>>, mut overlay: ResMut<TileOverlay>) {
+    if keyboard_input.just_pressed(VirtualKeyCode::G) {
+        overlay.show_grid = !overlay.show_grid;
+    }
+}
+
+/// Ui schedule system with the overlay toggles.
+pub fn overlay_settings_window(ui_context: Res<UiContext>, mut overlay: ResMut<TileOverlay>) {
+    egui::Window::new("Overlay")
+        .default_pos([10.0, 10.0])
+        .resizable(false)
+        .show(&ui_context.context, |ui| {
+            ui.checkbox(&mut overlay.show_grid, "Grid (G)");
+            ui.checkbox(&mut overlay.show_hover, "Hover highlight");
+        });
+}
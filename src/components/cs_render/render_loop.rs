@@ -2,41 +2,72 @@ use std::iter;
 
 use bevy_ecs::world::World;
 use cgmath::Vector2;
+use instant::Instant;
 use log::warn;
 use winit::event_loop::ControlFlow;
 
+use crate::components::cs_render::gpu_timings;
+use crate::components::cs_render::gpu_timings::GpuProfiler;
+use crate::components::cs_render::minimap::Minimap;
+use crate::components::cs_render::overlay::OverlayRenderer;
 use crate::components::cs_render::shader::camera_binding::CameraBinding;
 use crate::components::cs_render::shader::compute_shader_binding::ComputeParamsBinding;
+use crate::components::cs_render::shader::tile_animation_binding::TileAnimationBinding;
 use crate::components::cs_render::shader_types::compute_params_uniform::{COMPUTEGROUPSIZE, ComputeParamsUniform};
 use crate::components::cs_render::shader_types::geometry::VERTICES;
+use crate::components::cs_render::sprite::SpriteRenderer;
+use crate::components::cs_render::text::TextRenderer;
+use crate::components::cs_ui::ui_layer::UiLayer;
 use crate::components::cs_util::cs_window::State;
+use crate::components::cs_util::performance::PerformanceStats;
 use crate::main_loop::{DummyTest, Render};
 
+/// Counters of one rendered frame.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderStats {
+    pub visible_tiles: u32,
+    pub draw_calls: u32,
+}
+
 pub fn render_game_world(world: &mut World, state: &mut State, control_flow: &mut ControlFlow) {
-    match render_instances(
-        world.get_resource::<Render>().unwrap(),
-        world.get_resource::<CameraBinding>().unwrap(),
-        world.get_resource::<DummyTest>().unwrap(),
-        world.get_resource::<ComputeParamsBinding>().unwrap(),
-        world.get_resource::<ComputeParamsUniform>().unwrap(),
-    ) {
-        Ok(_) => {}
+    let render_start = Instant::now();
+    match render_instances(world) {
+        Ok(render_stats) => {
+            let mut performance_stats = world.get_resource_mut::<PerformanceStats>().unwrap();
+            performance_stats.record_render(render_start.elapsed(), render_stats.visible_tiles, render_stats.draw_calls);
+            gpu_timings::resolve_gpu_timings(world);
+        }
         Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => state.resize(world),
         Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
         Err(wgpu::SurfaceError::Timeout) => warn!("Surface timeout"),
     }
 }
 
-pub fn render_instances(
-    render: &Render,
-    camera_binding: &CameraBinding,
-    dummy_test: &DummyTest,
-    compute_params_binding: &ComputeParamsBinding,
-    compute_params_uniform: &ComputeParamsUniform,
-) -> Result<(), wgpu::SurfaceError> {
+pub fn render_instances(world: &World) -> Result<RenderStats, wgpu::SurfaceError> {
+    let render = world.get_resource::<Render>().unwrap();
+    let camera_binding = world.get_resource::<CameraBinding>().unwrap();
+    let dummy_test = world.get_resource::<DummyTest>().unwrap();
+    let compute_params_binding = world.get_resource::<ComputeParamsBinding>().unwrap();
+    let compute_params_uniform = world.get_resource::<ComputeParamsUniform>().unwrap();
+    let tile_animation_binding = world.get_resource::<TileAnimationBinding>().unwrap();
+    let overlay_renderer = world.get_resource::<OverlayRenderer>().unwrap();
+    let sprite_renderer = world.get_resource::<SpriteRenderer>().unwrap();
+    let text_renderer = world.get_resource::<TextRenderer>().unwrap();
+    let minimap = world.get_resource::<Minimap>().unwrap();
+    let ui_layer = world.get_resource::<UiLayer>().unwrap();
+    let gpu_profiler = world.get_resource::<GpuProfiler>();
+    let timestamp = |encoder: &mut wgpu::CommandEncoder, index: u32| {
+        if let Some(gpu_profiler) = gpu_profiler {
+            gpu_profiler.write_timestamp(encoder, index);
+        }
+    };
+
+    // the tile pass is one draw call
+    let mut render_stats = RenderStats { visible_tiles: 0, draw_calls: 1 };
     let mut encoder = render.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
         label: Some("Render Encoder"),
     });
+    timestamp(&mut encoder, 0);
     {
         let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
             label: Some("Life grid step"),
@@ -47,6 +78,7 @@ pub fn render_instances(
         cpass.set_bind_group(2, &dummy_test.compute_visible_buffer_bind_group, &[]);
         cpass.dispatch_workgroups((compute_params_uniform.columns / COMPUTEGROUPSIZE) as u32, ((compute_params_uniform.rows * 2) / COMPUTEGROUPSIZE) as u32, 1);
     };
+    timestamp(&mut encoder, 1);
 
 
     let output = render.surface.get_current_texture().unwrap();
@@ -83,14 +115,70 @@ pub fn render_instances(
         render_pass.set_bind_group(0, &dummy_test.diffuse_bind_group, &[]);
         render_pass.set_bind_group(1, &camera_binding.camera_bind_group, &[]);
         render_pass.set_bind_group(2, &dummy_test.instance_buffer_bind_group, &[]);
+        render_pass.set_bind_group(3, &tile_animation_binding.tile_animation_bind_group, &[]);
         render_pass.set_vertex_buffer(0, dummy_test.geometry_buffer.slice(..));
         render_pass.draw(0..VERTICES.len() as u32, 0..(instances) as u32);
         //error!("Wuff {:?}", instances);
+        render_stats.visible_tiles = instances as u32;
+    }
+    timestamp(&mut encoder, 2);
+
+    {
+        let mut overlay_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overlay Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_stats.draw_calls += overlay_renderer.draw(&mut overlay_pass, &camera_binding.camera_bind_group);
+        render_stats.draw_calls += sprite_renderer.draw(&mut overlay_pass, &camera_binding.camera_bind_group);
+        render_stats.draw_calls += text_renderer.draw(&mut overlay_pass, &camera_binding.camera_bind_group);
+    }
+    timestamp(&mut encoder, 3);
+
+    {
+        let mut minimap_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Minimap Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_stats.draw_calls += minimap.draw(&mut minimap_pass);
+    }
+    timestamp(&mut encoder, 4);
+
+    {
+        let mut ui_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Ui Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_stats.draw_calls += ui_layer.draw(&mut ui_pass);
     }
+    timestamp(&mut encoder, 5);
 
     render.queue.submit(iter::once(encoder.finish()));
     output.present();
-    Ok(())
+    Ok(render_stats)
 }
 
 
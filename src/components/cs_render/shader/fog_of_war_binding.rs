@@ -0,0 +1,38 @@
+use bevy_ecs::schedule::Schedule;
+use bevy_ecs::system::{Res, ResMut, Resource};
+use bevy_ecs::world::World;
+use wgpu::{Buffer, Device};
+
+use crate::components::cs_world::fog_of_war::FogOfWar;
+use crate::main_loop::Render;
+
+#[derive(Resource)]
+pub struct FogOfWarBinding {
+    pub fog_of_war_buffer: Buffer,
+}
+
+impl FogOfWarBinding {
+    /// The buffer is bound together with the visible tiles, see `create_visible_buffer`.
+    pub fn register(fog_of_war_buffer: Buffer, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(Self { fog_of_war_buffer });
+        schedule.add_system(update_fog_of_war_buffer);
+    }
+}
+
+pub fn update_fog_of_war_buffer(render: Res<Render>, fog_of_war_binding: Res<FogOfWarBinding>, mut fog_of_war: ResMut<FogOfWar>) {
+    if let Some((offset, tiles)) = fog_of_war.take_changed() {
+        // one byte per tile, the shader reads four tiles per u32
+        let mut padded = tiles.to_vec();
+        padded.resize(padded.len().next_multiple_of(4), 0);
+        render.queue.write_buffer(&fog_of_war_binding.fog_of_war_buffer, offset as wgpu::BufferAddress, &padded);
+    }
+}
+
+pub fn create_fog_of_war_buffer(device: &Device, tile_count: usize) -> Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("fog_of_war_buffer"),
+        size: tile_count.next_multiple_of(4) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
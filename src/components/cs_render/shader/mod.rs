@@ -1,3 +1,8 @@
+#[allow(clippy::new_ret_no_self, clippy::needless_borrow)]
 pub mod camera_binding;
+#[allow(clippy::needless_borrow)]
 pub mod texture_sampler_binding;
+#[allow(clippy::new_ret_no_self, clippy::needless_borrow)]
 pub mod compute_shader_binding;
+pub mod tile_animation_binding;
+pub mod fog_of_war_binding;
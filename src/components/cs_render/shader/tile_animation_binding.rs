@@ -0,0 +1,88 @@
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::World;
+use wgpu::{BindGroup, BindGroupLayout, Buffer, Device};
+use wgpu::util::DeviceExt;
+
+use crate::components::cs_render::shader_types::tile_animation::TileAnimationData;
+use crate::components::cs_world::tile_registry::TileRegistry;
+
+#[derive(Resource)]
+pub struct TileAnimationBinding {
+    pub frames_buffer: Buffer,
+    pub animations_buffer: Buffer,
+    pub tile_animation_bind_group: BindGroup,
+}
+
+impl TileAnimationBinding {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(device: &Device, registry: &TileRegistry, world: &mut World) -> BindGroupLayout {
+        let tile_animation_bind_group_layout = create_tile_animation_bind_group_layout(device);
+        world.insert_resource(Self::create(device, registry, &tile_animation_bind_group_layout));
+        tile_animation_bind_group_layout
+    }
+
+    /// Buffers and bind group for the animations of `registry`, also used when the tile definitions are reloaded.
+    pub fn create(device: &Device, registry: &TileRegistry, layout: &BindGroupLayout) -> Self {
+        let (mut frames, mut animations) = registry.animation_table();
+        // storage buffers can not be empty
+        if frames.is_empty() {
+            frames.push(0);
+        }
+        if animations.is_empty() {
+            animations.push(TileAnimationData::default());
+        }
+
+        let frames_buffer = create_storage_buffer(device, "tile_animation_frames_buffer", bytemuck::cast_slice(&frames));
+        let animations_buffer = create_storage_buffer(device, "tile_animations_buffer", bytemuck::cast_slice(&animations));
+        let tile_animation_bind_group = create_tile_animation_bind_group(device, &frames_buffer, &animations_buffer, layout);
+        Self {
+            frames_buffer,
+            animations_buffer,
+            tile_animation_bind_group,
+        }
+    }
+}
+
+fn create_storage_buffer(device: &Device, label: &str, contents: &[u8]) -> Buffer {
+    device.create_buffer_init(
+        &wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage: wgpu::BufferUsages::STORAGE,
+        }
+    )
+}
+
+pub fn create_tile_animation_bind_group_layout(device: &Device) -> BindGroupLayout {
+    let storage_entry = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[storage_entry(0), storage_entry(1)],
+        label: Some("tile_animation_bind_group_layout"),
+    })
+}
+
+pub fn create_tile_animation_bind_group(device: &Device, frames_buffer: &Buffer, animations_buffer: &Buffer, layout: &BindGroupLayout) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: frames_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: animations_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("tile_animation_bind_group"),
+    })
+}
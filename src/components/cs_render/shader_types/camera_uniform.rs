@@ -1,18 +1,24 @@
 use bevy_ecs::prelude::{Schedule, World};
 use bevy_ecs::system::{Res, ResMut, Resource};
 use crate::components::cs_util::camera::CustomCamera;
+use crate::components::cs_util::time::GameTime;
 
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Resource)]
 pub struct CameraUniform {
     pub view_proj: [[f32; 4]; 4],
+    /// Seconds since the game started, drives the tile animations in `vs_main`.
+    pub time: f32,
+    _padding: [f32; 3],
 }
 
 impl CameraUniform {
     pub(crate) fn new(camera: &CustomCamera, world: &mut World, schedule: &mut Schedule) -> Self {
         let camera_uniform = Self {
-            view_proj: (camera.projection * camera.view).into()
+            view_proj: (camera.projection * camera.view).into(),
+            time: 0.0,
+            _padding: [0.0; 3],
         };
 
         world.insert_resource(camera_uniform);
@@ -23,6 +29,7 @@ impl CameraUniform {
 
 
 
-pub fn update_view_proj(mut camera_uniform: ResMut<CameraUniform>, camera: Res<CustomCamera>) {
+pub fn update_view_proj(mut camera_uniform: ResMut<CameraUniform>, camera: Res<CustomCamera>, time: Res<GameTime>) {
     camera_uniform.view_proj = (camera.projection * camera.view).into();
+    camera_uniform.time = time.elapsed as f32;
 }
\ No newline at end of file
@@ -1,9 +1,9 @@
 use bevy_ecs::schedule::Schedule;
 use bevy_ecs::system::{Res, ResMut, Resource};
 use bevy_ecs::world::World;
+use cgmath::Vector2;
 
 use crate::components::cs_util::camera::CustomCamera;
-use crate::components::cs_world::map::SIZE;
 
 pub const COMPUTEGROUPSIZE: i32 = 16;
 
@@ -17,9 +17,9 @@ pub struct ComputeParamsUniform {
 }
 
 impl ComputeParamsUniform {
-    pub(crate) fn new(camera: &CustomCamera, world: &mut World, schedule: &mut Schedule) -> Self {
+    pub(crate) fn new(camera: &CustomCamera, map_size: Vector2<i32>, world: &mut World, schedule: &mut Schedule) -> Self {
         let compute_params_uniform = Self {
-            map_size: [SIZE, SIZE],
+            map_size: map_size.into(),
             columns: camera.visible_area.w + (COMPUTEGROUPSIZE - camera.visible_area.w % COMPUTEGROUPSIZE),
             start_pos: [camera.visible_area.x, camera.visible_area.y],
             rows: camera.visible_area.z + (COMPUTEGROUPSIZE - camera.visible_area.z % COMPUTEGROUPSIZE),
@@ -32,7 +32,6 @@ impl ComputeParamsUniform {
 }
 
 pub fn update_compute_params(mut compute_camera_uniform: ResMut<ComputeParamsUniform>, camera: Res<CustomCamera>) {
-    compute_camera_uniform.map_size = [SIZE, SIZE];
     compute_camera_uniform.columns = camera.visible_area.w + (COMPUTEGROUPSIZE - camera.visible_area.w % COMPUTEGROUPSIZE);
     compute_camera_uniform.rows = (camera.visible_area.z + (COMPUTEGROUPSIZE - camera.visible_area.z % COMPUTEGROUPSIZE))  / 2;
     compute_camera_uniform.start_pos = [camera.visible_area.x, camera.visible_area.y];
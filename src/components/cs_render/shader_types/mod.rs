@@ -1,5 +1,7 @@
 pub mod geometry;
 pub mod tile_instance;
+#[allow(clippy::field_reassign_with_default)]
 pub mod texture;
 pub mod camera_uniform;
-pub mod compute_params_uniform;
\ No newline at end of file
+pub mod compute_params_uniform;
+pub mod tile_animation;
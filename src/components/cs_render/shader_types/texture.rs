@@ -2,6 +2,9 @@ use anyhow::*;
 use image::GenericImageView;
 use wgpu::{Device, Sampler, TextureViewDimension};
 
+use crate::components::cs_io::asset_path::AssetPath;
+use crate::components::cs_io::assets::{Asset, AssetError, LoadContext};
+
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
@@ -98,7 +101,7 @@ impl Texture {
         descriptor.dimension = Some(TextureViewDimension::D2Array);
 
         let view = texture.create_view(&descriptor);
-        let sampler = Self::create_sampler(&device);
+        let sampler = Self::create_sampler(device);
 
         Ok(Self {
             texture,
@@ -121,4 +124,11 @@ impl Texture {
             ..Default::default()
         })
     }
-}
\ No newline at end of file
+}
+
+impl Asset for Texture {
+    fn from_bytes(bytes: Vec<u8>, path: &AssetPath, context: &LoadContext) -> std::result::Result<Self, AssetError> {
+        Texture::from_bytes(context.device, context.queue, &bytes, path.as_str())
+            .map_err(|error| AssetError::decode(path, error))
+    }
+}
@@ -0,0 +1,13 @@
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TileAnimationData {
+    pub first_frame: u32,
+    pub frame_count: u32,
+    pub frame_duration: f32,
+    pub mode: u32,
+}
+
+/// Packs an atlas cell offset the same way as the x/y part of `AtlasCoordinate`.
+pub fn pack_frame(offset: [u8; 2]) -> u32 {
+    offset[0] as u32 | (offset[1] as u32) << 8
+}
@@ -11,6 +11,8 @@ pub struct TileInstance {
 pub struct AtlasCoordinate
 {
     pub coordinate: [u8; 2],
-    pub index: u16,
+    pub index: u8,
+    /// 0 for static tiles, otherwise the animation id from the `TileRegistry`.
+    pub animation: u8,
 }
 
@@ -0,0 +1,225 @@
+use std::mem;
+
+use bevy_ecs::schedule::Schedule;
+use bevy_ecs::system::{Query, Res, ResMut, Resource};
+use bevy_ecs::world::World;
+use cgmath::Vector2;
+use wgpu::{BindGroup, BindGroupLayout, Buffer, Device, Queue, RenderPipeline, ShaderModule, TextureFormat};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::components::cs_render::hot_reload;
+use crate::components::cs_render::shader::texture_sampler_binding;
+use crate::components::cs_render::shader_types::texture::Texture;
+use crate::components::cs_world::fog_of_war::{FogOfWar, TileVisibility};
+use crate::components::cs_world::building::{Building, BuildingDefinition, BuildingRegistry, SpriteRect};
+use crate::components::cs_world::map;
+use crate::components::cs_world::map::TILE_SIZE_HALF;
+use crate::components::cs_world::position::TilePosition;
+use crate::components::cs_world::unit::{AnimationPlayer, Facing, Unit, UnitDefinition, UnitRegistry, SPRITE_FOOT_OFFSET, SPRITE_SIZE};
+use crate::main_loop::Render;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpriteInstance {
+    /// Top left corner in world pixels.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+impl SpriteInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32x2,
+        3 => Float32x2,
+    ];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<SpriteInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Frame of a unit standing at `position`, with its feet on the position.
+pub fn unit_sprite(definition: &UnitDefinition, position: Vector2<f32>, facing: Facing, animation: &AnimationPlayer, sheet_size: Vector2<u32>) -> SpriteInstance {
+    let cell = definition.sprite_cell(facing, animation);
+    let feet = map::position_to_screen(position);
+    let rect = SpriteRect { origin: Vector2::new(cell.x * SPRITE_SIZE.x, cell.y * SPRITE_SIZE.y), size: SPRITE_SIZE };
+    let top_left = Vector2::new(feet.x - SPRITE_SIZE.x as f32 / 2.0, feet.y - SPRITE_SIZE.y as f32 + SPRITE_FOOT_OFFSET);
+    sheet_sprite(rect, top_left, false, sheet_size)
+}
+
+/// A building with the bottom of its sprite on the bottom corner of its footprint, mirrored when turned sideways.
+pub fn building_sprite(definition: &BuildingDefinition, building: &Building, sheet_size: Vector2<u32>) -> SpriteInstance {
+    let footprint = definition.footprint(building.rotation);
+    let top_corner = map::position_to_screen(Vector2::new(building.origin.x as f32, building.origin.y as f32));
+    let top_left = Vector2::new(
+        top_corner.x - TILE_SIZE_HALF.x * footprint.y as f32,
+        top_corner.y + TILE_SIZE_HALF.y * (footprint.x + footprint.y) as f32 - definition.sprite.size.y as f32,
+    );
+    sheet_sprite(definition.sprite, top_left, building.rotation.is_sideways(), sheet_size)
+}
+
+fn sheet_sprite(rect: SpriteRect, top_left: Vector2<f32>, mirrored: bool, sheet_size: Vector2<u32>) -> SpriteInstance {
+    let uv = |pixel: Vector2<u32>| Vector2::new(pixel.x as f32 / sheet_size.x as f32, pixel.y as f32 / sheet_size.y as f32);
+    let mut uv_min = uv(rect.origin);
+    let mut uv_max = uv(rect.origin + rect.size);
+    if mirrored {
+        std::mem::swap(&mut uv_min.x, &mut uv_max.x);
+    }
+    SpriteInstance {
+        position: top_left.into(),
+        size: [rect.size.x as f32, rect.size.y as f32],
+        uv_min: uv_min.into(),
+        uv_max: uv_max.into(),
+    }
+}
+
+/// Draws the units and buildings from one sprite sheet, sorted back to front.
+#[derive(Resource)]
+pub struct SpriteRenderer {
+    bind_group: BindGroup,
+    sheet_size: Vector2<u32>,
+    instance_buffer: Buffer,
+    capacity: usize,
+    instance_count: u32,
+    /// Kept to rebuild the pipelines when the shader is reloaded.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pipeline_layout: wgpu::PipelineLayout,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    format: TextureFormat,
+    pipeline: RenderPipeline,
+}
+
+impl SpriteRenderer {
+    pub fn create(device: &Device, format: TextureFormat, shader: &ShaderModule, camera_bind_group_layout: &BindGroupLayout, sheet: &Texture) -> Self {
+        let texture_bind_group_layout = texture_sampler_binding::create_texture_group_layout(device);
+        let bind_group = texture_sampler_binding::create_diffuse_bind_group(device, sheet, &texture_bind_group_layout);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sprite Pipeline Layout"),
+            bind_group_layouts: &[&texture_bind_group_layout, camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = create_sprite_pipeline(device, &pipeline_layout, shader, format);
+        let size = sheet.texture.size();
+        let capacity = 256;
+
+        Self {
+            bind_group,
+            sheet_size: Vector2::new(size.width, size.height),
+            instance_buffer: create_instance_buffer(device, capacity),
+            capacity,
+            instance_count: 0,
+            pipeline_layout,
+            format,
+            pipeline,
+        }
+    }
+
+    /// Recreates the pipeline from a reloaded shader, the previous one stays if it does not fit.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn rebuild_pipelines(&mut self, device: &Device, shader: &ShaderModule) -> Result<(), wgpu::Error> {
+        self.pipeline = hot_reload::validated(device, || create_sprite_pipeline(device, &self.pipeline_layout, shader, self.format))?;
+        Ok(())
+    }
+
+    pub fn register(sprite_renderer: SpriteRenderer, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(sprite_renderer);
+        schedule.add_system(update_sprite_instances);
+    }
+
+    pub fn sheet_size(&self) -> Vector2<u32> {
+        self.sheet_size
+    }
+
+    pub fn upload(&mut self, device: &Device, queue: &Queue, instances: &[SpriteInstance]) {
+        if instances.len() > self.capacity {
+            self.capacity = instances.len().next_power_of_two();
+            self.instance_buffer = create_instance_buffer(device, self.capacity);
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(instances));
+        self.instance_count = instances.len() as u32;
+    }
+
+    /// Returns the number of draw calls.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera_bind_group: &'a BindGroup) -> u32 {
+        if self.instance_count == 0 {
+            return 0;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.instance_count);
+        1
+    }
+}
+
+fn create_instance_buffer(device: &Device, capacity: usize) -> Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("sprite_instance_buffer"),
+        size: (capacity * mem::size_of::<SpriteInstance>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Units under the fog of the active player are left out, buildings stay once explored.
+pub fn update_sprite_instances(
+    render: Res<Render>,
+    unit_registry: Res<UnitRegistry>,
+    building_registry: Option<Res<BuildingRegistry>>,
+    fog_of_war: Option<Res<FogOfWar>>,
+    units: Query<(&TilePosition, &Unit, &Facing, &AnimationPlayer)>,
+    buildings: Query<(&TilePosition, &Building)>,
+    mut sprite_renderer: ResMut<SpriteRenderer>,
+) {
+    let visibility = |position: &TilePosition| match &fog_of_war {
+        Some(fog_of_war) => fog_of_war.visibility(fog_of_war.active_player, position.tile()).unwrap_or(TileVisibility::Unexplored),
+        None => TileVisibility::Visible,
+    };
+    let sheet_size = sprite_renderer.sheet_size;
+    let mut visible: Vec<(Vector2<f32>, SpriteInstance)> = units.iter()
+        .filter(|(position, ..)| visibility(position) == TileVisibility::Visible)
+        .map(|(position, unit, facing, animation)| (position.0, unit_sprite(unit_registry.get(unit.kind), position.0, *facing, animation, sheet_size)))
+        .collect();
+    if let Some(building_registry) = &building_registry {
+        visible.extend(buildings.iter()
+            .filter(|(position, _)| visibility(position) != TileVisibility::Unexplored)
+            .map(|(position, building)| (position.0, building_sprite(building_registry.get(building.kind), building, sheet_size))));
+    }
+    // sprites further down the screen are in front
+    visible.sort_by(|(a, _), (b, _)| (a.x + a.y).total_cmp(&(b.x + b.y)));
+    let instances: Vec<_> = visible.into_iter().map(|(_, instance)| instance).collect();
+    sprite_renderer.upload(&render.device, &render.queue, &instances);
+}
+
+fn create_sprite_pipeline(device: &Device, layout: &wgpu::PipelineLayout, shader: &ShaderModule, format: TextureFormat) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Sprite Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[SpriteInstance::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
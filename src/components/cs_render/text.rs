@@ -0,0 +1,349 @@
+use std::mem;
+
+use bevy_ecs::prelude::Component;
+use bevy_ecs::schedule::Schedule;
+use bevy_ecs::system::{Query, Res, ResMut, Resource};
+use bevy_ecs::world::World;
+use cgmath::Vector2;
+use wgpu::{BindGroup, BindGroupLayout, Buffer, Device, Queue, RenderPipeline, ShaderModule, TextureFormat};
+use wgpu::util::DeviceExt;
+
+use crate::components::cs_render::font::FontAtlas;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::components::cs_render::hot_reload;
+use crate::components::cs_world::map;
+use crate::components::cs_world::position::TilePosition;
+use crate::main_loop::Render;
+
+/// Text drawn above an entity, centred over its tile position.
+#[derive(Component, Debug, Clone)]
+pub struct WorldLabel {
+    pub text: String,
+    pub color: [f32; 4],
+    pub scale: f32,
+    /// World pixels between the tile centre and the bottom of the text.
+    pub height: f32,
+}
+
+impl WorldLabel {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            color: [1.0, 1.0, 1.0, 1.0],
+            scale: 0.5,
+            height: 24.0,
+        }
+    }
+}
+
+/// Text at a fixed position in window pixels.
+#[derive(Debug, Clone)]
+pub struct ScreenText {
+    pub name: &'static str,
+    pub text: String,
+    pub position: Vector2<f32>,
+    pub color: [f32; 4],
+    pub scale: f32,
+}
+
+/// Screen space texts written by debug overlays and tools, kept until they are replaced or removed.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ScreenTexts {
+    texts: Vec<ScreenText>,
+}
+
+impl ScreenTexts {
+    /// Replaces the text with the given name or adds it.
+    pub fn set(&mut self, name: &'static str, text: impl Into<String>, position: Vector2<f32>, color: [f32; 4], scale: f32) {
+        let text = ScreenText { name, text: text.into(), position, color, scale };
+        match self.texts.iter_mut().find(|existing| existing.name == name) {
+            Some(existing) => *existing = text,
+            None => self.texts.push(text),
+        }
+    }
+
+    pub fn remove(&mut self, name: &'static str) {
+        self.texts.retain(|text| text.name != name);
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlyphInstance {
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl GlyphInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32x2,
+        3 => Float32x2,
+        4 => Float32x4,
+    ];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<GlyphInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Lays out `text` with its top left corner at `origin`.
+pub fn build_glyph_instances(font: &FontAtlas, text: &str, origin: Vector2<f32>, scale: f32, color: [f32; 4], instances: &mut Vec<GlyphInstance>) {
+    instances.extend(font.layout(text, scale).iter().map(|glyph| GlyphInstance {
+        position: (origin + glyph.position).into(),
+        size: glyph.size.into(),
+        uv_min: glyph.uv_min,
+        uv_max: glyph.uv_max,
+        color,
+    }));
+}
+
+#[derive(Resource)]
+pub struct TextRenderer {
+    font: FontAtlas,
+    bind_group: BindGroup,
+    screen_buffer: Buffer,
+    instance_buffer: Buffer,
+    capacity: usize,
+    world_count: u32,
+    instance_count: u32,
+    /// Kept to rebuild the pipelines when the shader is reloaded.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pipeline_layout: wgpu::PipelineLayout,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    format: TextureFormat,
+    world_pipeline: RenderPipeline,
+    screen_pipeline: RenderPipeline,
+}
+
+impl TextRenderer {
+    pub fn create(device: &Device, queue: &Queue, format: TextureFormat, shader: &ShaderModule, camera_bind_group_layout: &BindGroupLayout, font: FontAtlas, screen_size: Vector2<f32>) -> Self {
+        let size = wgpu::Extent3d {
+            width: font.size.x,
+            height: font.size.y,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("font_atlas_texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &font.pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(font.size.x),
+                rows_per_image: Some(font.size.y),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // glyphs are scaled with the camera zoom, so they are filtered unlike the tiles
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("font_atlas_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let screen_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("text_screen_buffer"),
+            contents: bytemuck::cast_slice(&[screen_size.x, screen_size.y, 0.0, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("text_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: screen_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("text_bind_group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Text Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout, camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let capacity = 1024;
+
+        Self {
+            font,
+            bind_group,
+            screen_buffer,
+            instance_buffer: create_instance_buffer(device, capacity),
+            capacity,
+            world_count: 0,
+            instance_count: 0,
+            world_pipeline: create_text_pipeline(device, &pipeline_layout, shader, format, "vs_world"),
+            screen_pipeline: create_text_pipeline(device, &pipeline_layout, shader, format, "vs_screen"),
+            pipeline_layout,
+            format,
+        }
+    }
+
+    /// Recreates the pipelines from a reloaded shader, the previous ones stay if it does not fit.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn rebuild_pipelines(&mut self, device: &Device, shader: &ShaderModule) -> Result<(), wgpu::Error> {
+        let (world_pipeline, screen_pipeline) = hot_reload::validated(device, || (
+            create_text_pipeline(device, &self.pipeline_layout, shader, self.format, "vs_world"),
+            create_text_pipeline(device, &self.pipeline_layout, shader, self.format, "vs_screen"),
+        ))?;
+        self.world_pipeline = world_pipeline;
+        self.screen_pipeline = screen_pipeline;
+        Ok(())
+    }
+
+    pub fn register(text_renderer: TextRenderer, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(text_renderer);
+        world.insert_resource(ScreenTexts::default());
+        schedule.add_system(update_text_instances);
+    }
+
+    pub fn font(&self) -> &FontAtlas {
+        &self.font
+    }
+
+    /// World space glyphs come first in `instances`, followed by `instances.len() - world_count` screen space glyphs.
+    pub fn upload(&mut self, device: &Device, queue: &Queue, instances: &[GlyphInstance], world_count: usize, screen_size: Vector2<f32>) {
+        if instances.len() > self.capacity {
+            self.capacity = instances.len().next_power_of_two();
+            self.instance_buffer = create_instance_buffer(device, self.capacity);
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(instances));
+        queue.write_buffer(&self.screen_buffer, 0, bytemuck::cast_slice(&[screen_size.x, screen_size.y, 0.0, 0.0]));
+        self.world_count = world_count as u32;
+        self.instance_count = instances.len() as u32;
+    }
+
+    /// Returns the number of draw calls.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera_bind_group: &'a BindGroup) -> u32 {
+        if self.instance_count == 0 {
+            return 0;
+        }
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.set_pipeline(&self.world_pipeline);
+        render_pass.draw(0..6, 0..self.world_count);
+        render_pass.set_pipeline(&self.screen_pipeline);
+        render_pass.draw(0..6, self.world_count..self.instance_count);
+        2
+    }
+}
+
+fn create_instance_buffer(device: &Device, capacity: usize) -> Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("text_instance_buffer"),
+        size: (capacity * mem::size_of::<GlyphInstance>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_text_pipeline(device: &Device, layout: &wgpu::PipelineLayout, shader: &ShaderModule, format: TextureFormat, vertex_entry_point: &str) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Text Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: vertex_entry_point,
+            buffers: &[GlyphInstance::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+pub fn update_text_instances(
+    render: Res<Render>,
+    screen_texts: Res<ScreenTexts>,
+    labels: Query<(&TilePosition, &WorldLabel)>,
+    mut text_renderer: ResMut<TextRenderer>,
+) {
+    let mut instances = Vec::new();
+    for (position, label) in labels.iter() {
+        let size = text_renderer.font.measure(&label.text, label.scale);
+        let anchor = map::map_to_screen_pos_centered(position.0);
+        let origin = Vector2::new(anchor.x - size.x / 2.0, anchor.y - label.height - size.y);
+        build_glyph_instances(&text_renderer.font, &label.text, origin, label.scale, label.color, &mut instances);
+    }
+    let world_count = instances.len();
+    for text in screen_texts.texts.iter() {
+        build_glyph_instances(&text_renderer.font, &text.text, text.position, text.scale, text.color, &mut instances);
+    }
+    let screen_size = Vector2::new(render.config.width as f32, render.config.height as f32);
+    text_renderer.upload(&render.device, &render.queue, &instances, world_count, screen_size);
+}
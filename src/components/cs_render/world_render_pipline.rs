@@ -1,13 +1,22 @@
 use std::borrow::Cow;
-use std::path::Path;
+use std::mem;
+
+use bevy_ecs::event::EventReader;
+use bevy_ecs::system::{Local, Res};
 
 use wgpu::{BindGroup, BindGroupLayout, Buffer, ComputePipeline, Device, RenderPipeline, ShaderModule, SurfaceConfiguration, util};
 use wgpu::util::DeviceExt;
 
-use crate::components::cs_io::AssetIo;
+use crate::components::cs_io::asset_path::AssetPath;
+use crate::components::cs_io::assets::{Asset, AssetError, LoadContext};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::components::cs_render::hot_reload;
 use crate::components::cs_render::shader_types::geometry::{GeometryData, VERTICES};
 use crate::components::cs_render::shader_types::texture::Texture;
 use crate::components::cs_render::shader_types::tile_instance::TileInstance;
+use crate::components::cs_world::map::{Map, TilePreview, TilesChanged};
+use crate::components::cs_world::tile_registry::TileRegistry;
+use crate::main_loop::{DummyTest, Render};
 
 pub fn create_render_pipline(device: &Device, config: &SurfaceConfiguration, shader: &ShaderModule, bind_group_layout: &[&BindGroupLayout]) -> RenderPipeline {
     let render_pipeline_layout = device.create_pipeline_layout(
@@ -22,14 +31,14 @@ pub fn create_render_pipline(device: &Device, config: &SurfaceConfiguration, sha
         label: Some("Render Pipeline"),
         layout: Some(&render_pipeline_layout),
         vertex: wgpu::VertexState {
-            module: &shader,
+            module: shader,
             entry_point: "vs_main",
             buffers: &[
                 GeometryData::desc(),
             ],
         },
         fragment: Some(wgpu::FragmentState {
-            module: &shader,
+            module: shader,
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
                 format: config.format,
@@ -62,7 +71,7 @@ pub fn create_render_pipline(device: &Device, config: &SurfaceConfiguration, sha
     })
 }
 
-pub fn create_visible_buffer(device: &Device, instances: &mut Vec<TileInstance>) -> (BindGroupLayout, BindGroup, Buffer) {
+pub fn create_visible_buffer(device: &Device, instances: &[TileInstance], fog_of_war_buffer: &Buffer) -> (BindGroupLayout, BindGroup, Buffer, Buffer) {
 
     // let visible_tiles_buffer = device.create_buffer(&BufferDescriptor {
     //     label: Some("visible_tiles_buffer"),
@@ -74,22 +83,23 @@ pub fn create_visible_buffer(device: &Device, instances: &mut Vec<TileInstance>)
     let visible_tiles_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("visible_tiles_buffer"),
         usage: wgpu::BufferUsages::STORAGE,
-        contents: bytemuck::cast_slice(&instances),
+        contents: bytemuck::cast_slice(instances),
+    });
+
+    // map index of every visible tile, written by the compute shader next to the tile itself
+    let visible_tile_indices_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("visible_tile_indices_buffer"),
+        usage: wgpu::BufferUsages::STORAGE,
+        size: (instances.len() * mem::size_of::<u32>()) as wgpu::BufferAddress,
+        mapped_at_creation: false,
     });
 
 
     let instance_buffer_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
         entries: &[
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: true },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }
+            storage_layout_entry(0, wgpu::ShaderStages::VERTEX, true),
+            storage_layout_entry(1, wgpu::ShaderStages::VERTEX, true),
+            storage_layout_entry(2, wgpu::ShaderStages::VERTEX, true),
         ],
         label: Some("visible_tiles_bind_group_layout"),
     });
@@ -100,26 +110,26 @@ pub fn create_visible_buffer(device: &Device, instances: &mut Vec<TileInstance>)
             wgpu::BindGroupEntry {
                 binding: 0,
                 resource: visible_tiles_buffer.as_entire_binding(),
-            }
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: visible_tile_indices_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: fog_of_war_buffer.as_entire_binding(),
+            },
         ],
         label: Some("visible_tiles_bind_group"),
     });
-    (instance_buffer_bind_group_layout, instance_buffer_bind_group, visible_tiles_buffer)
+    (instance_buffer_bind_group_layout, instance_buffer_bind_group, visible_tiles_buffer, visible_tile_indices_buffer)
 }
 
-pub fn create_compute_visible_tiles_buffer(device: &Device, visible_tiles_buffer: Buffer) -> (BindGroupLayout, BindGroup) {
+pub fn create_compute_visible_tiles_buffer(device: &Device, visible_tiles_buffer: Buffer, visible_tile_indices_buffer: Buffer) -> (BindGroupLayout, BindGroup) {
     let instance_buffer_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
         entries: &[
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
+            storage_layout_entry(0, wgpu::ShaderStages::COMPUTE, false),
+            storage_layout_entry(1, wgpu::ShaderStages::COMPUTE, false),
         ],
         label: Some("visible_tiles_bind_group_layout"),
     });
@@ -130,18 +140,35 @@ pub fn create_compute_visible_tiles_buffer(device: &Device, visible_tiles_buffer
             wgpu::BindGroupEntry {
                 binding: 0,
                 resource: visible_tiles_buffer.as_entire_binding(),
-            }
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: visible_tile_indices_buffer.as_entire_binding(),
+            },
         ],
         label: Some("visible_tiles_bind_group"),
     });
     (instance_buffer_bind_group_layout, instance_buffer_bind_group)
 }
 
-pub fn create_compute_all_tiles_buffer(device: &Device, instances: &mut Vec<TileInstance>) -> (BindGroupLayout, BindGroup) {
+fn storage_layout_entry(binding: u32, visibility: wgpu::ShaderStages, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub fn create_compute_all_tiles_buffer(device: &Device, instances: &[TileInstance]) -> (BindGroupLayout, BindGroup, Buffer) {
     let all_tiles_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
         label: Some("all_tiles_buffer"),
-        contents: bytemuck::cast_slice(&instances),
-        usage: wgpu::BufferUsages::STORAGE,
+        contents: bytemuck::cast_slice(instances),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
     }
     );
 
@@ -171,7 +198,59 @@ pub fn create_compute_all_tiles_buffer(device: &Device, instances: &mut Vec<Tile
         ],
         label: Some("all_tiles_bind_group"),
     });
-    (instance_buffer_bind_group_layout, instance_buffer_bind_group)
+    (instance_buffer_bind_group_layout, instance_buffer_bind_group, all_tiles_buffer)
+}
+
+/// Writes tiles changed through `Map::set_tile` into the all tiles buffer, neighbouring tiles are uploaded in one write.
+pub fn upload_changed_tiles(render: Res<Render>, dummy_test: Res<DummyTest>, map: Res<Map>, mut tiles_changed: EventReader<TilesChanged>) {
+    for changed in tiles_changed.iter().map(|event| &event.indices) {
+        let mut start = 0;
+        while start < changed.len() {
+            let mut end = start + 1;
+            while end < changed.len() && changed[end] == changed[end - 1] + 1 {
+                end += 1;
+            }
+            let first = changed[start];
+            let last = changed[end - 1];
+            render.queue.write_buffer(
+                &dummy_test.all_tiles_buffer,
+                (first * mem::size_of::<TileInstance>()) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&map.tiles[first..=last]),
+            );
+            start = end;
+        }
+    }
+}
+
+/// Draws the tiles of the `TilePreview` over the map, tiles left by the previous preview get their map instance back.
+pub fn upload_tile_preview(
+    render: Res<Render>,
+    dummy_test: Res<DummyTest>,
+    map: Res<Map>,
+    registry: Res<TileRegistry>,
+    preview: Res<TilePreview>,
+    mut previewed: Local<Vec<usize>>,
+) {
+    if preview.tiles.is_empty() && previewed.is_empty() {
+        return;
+    }
+    let instances = map.preview_tiles(&preview.tiles, &registry);
+    for index in previewed.iter().filter(|index| instances.binary_search_by_key(*index, |(index, _)| *index).is_err()) {
+        write_tile(&render, &dummy_test.all_tiles_buffer, *index, &map.tiles[*index]);
+    }
+    // rewritten every update, tile changes under the preview would otherwise replace it
+    for (index, instance) in instances.iter() {
+        write_tile(&render, &dummy_test.all_tiles_buffer, *index, instance);
+    }
+    *previewed = instances.into_iter().map(|(index, _)| index).collect();
+}
+
+fn write_tile(render: &Render, all_tiles_buffer: &Buffer, index: usize, instance: &TileInstance) {
+    render.queue.write_buffer(
+        all_tiles_buffer,
+        (index * mem::size_of::<TileInstance>()) as wgpu::BufferAddress,
+        bytemuck::bytes_of(instance),
+    );
 }
 
 pub fn create_compute_pipline(device: &Device, shader: &ShaderModule, bind_group_layout: &[&BindGroupLayout]) -> ComputePipeline {
@@ -186,21 +265,24 @@ pub fn create_compute_pipline(device: &Device, shader: &ShaderModule, bind_group
     device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
         label: Some("cs compute pipeline"),
         layout: Some(&compute_pipeline_layout),
-        module: &shader,
+        module: shader,
         entry_point: "calcvisibility",
     })
 }
 
-pub(crate) async fn load_shader(device: &Device, asset_io: &Box<dyn AssetIo>, path: &str) -> ShaderModule {
-    let shader_bytes = asset_io.load_path(Path::new(path)).await;
-    let value = shader_bytes.unwrap();
-    let shader_string = std::str::from_utf8(&value).unwrap();
-
-    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: Some("Shader"),
-        source: wgpu::ShaderSource::Wgsl(Cow::from(shader_string)),
-    });
-    shader
+impl Asset for ShaderModule {
+    fn from_bytes(bytes: Vec<u8>, path: &AssetPath, context: &LoadContext) -> Result<Self, AssetError> {
+        let shader_string = String::from_utf8(bytes).map_err(|error| AssetError::decode(path, error))?;
+        let create = || context.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(path.as_str()),
+            source: wgpu::ShaderSource::Wgsl(Cow::from(shader_string)),
+        });
+        // compile errors are returned so a broken shader edit does not end the game
+        #[cfg(not(target_arch = "wasm32"))]
+        return hot_reload::validated(context.device, create).map_err(|error| AssetError::decode(path, error));
+        #[cfg(target_arch = "wasm32")]
+        return Ok(create());
+    }
 }
 
 pub(crate) fn create_geometry_buffer(device: &Device) -> Buffer {
@@ -0,0 +1,85 @@
+use bevy_ecs::system::{Res, ResMut};
+use egui::Color32;
+
+use crate::components::cs_ui::ui_layer::UiContext;
+use crate::components::cs_world::editor::{Brush, EditorLayer, EditorRequest, HeightMode, MapEditor, MAX_BRUSH_RADIUS};
+use crate::components::cs_world::map::MAX_HEIGHT;
+use crate::components::cs_world::tile_registry::TileRegistry;
+
+/// Ui schedule system with the brushes, the tile palette and saving of the map editor, only shown while it is open.
+pub fn editor_window(ui_context: Res<UiContext>, registry: Res<TileRegistry>, mut editor: ResMut<MapEditor>) {
+    if !editor.active {
+        return;
+    }
+    egui::Window::new("Map editor")
+        .anchor(egui::Align2::LEFT_TOP, [10.0, 10.0])
+        .resizable(false)
+        .show(&ui_context.context, |ui| {
+            ui.horizontal(|ui| {
+                for (brush, name) in [(Brush::Single, "single"), (Brush::Square, "square"), (Brush::Circle, "circle"), (Brush::Fill, "fill")] {
+                    ui.selectable_value(&mut editor.brush, brush, name);
+                }
+            });
+            if matches!(editor.brush, Brush::Square | Brush::Circle) {
+                ui.add(egui::Slider::new(&mut editor.radius, 0..=MAX_BRUSH_RADIUS).text("Radius"));
+            }
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut editor.layer, EditorLayer::Terrain, "terrain");
+                ui.selectable_value(&mut editor.layer, EditorLayer::Height, "height");
+            });
+            ui.separator();
+            match editor.layer {
+                EditorLayer::Terrain => {
+                    egui::Grid::new("palette_grid").num_columns(2).show(ui, |ui| {
+                        for (kind, definition) in registry.iter() {
+                            let [r, g, b, a] = definition.minimap_color;
+                            let (swatch, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                            ui.painter().rect_filled(swatch, 2.0, Color32::from_rgba_unmultiplied(r, g, b, a));
+                            ui.selectable_value(&mut editor.tile, kind, &definition.name);
+                            ui.end_row();
+                        }
+                    });
+                }
+                EditorLayer::Height => {
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut editor.height_mode, HeightMode::Raise, "raise");
+                        ui.selectable_value(&mut editor.height_mode, HeightMode::Lower, "lower");
+                        ui.selectable_value(&mut editor.height_mode, HeightMode::Level, "level");
+                    });
+                    if editor.height_mode == HeightMode::Level {
+                        ui.add(egui::Slider::new(&mut editor.height, 0..=MAX_HEIGHT).text("Height"));
+                    }
+                }
+            }
+            ui.label("Right click takes the stroke back, F2 closes");
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut editor.map_name);
+                if ui.button("Save").clicked() {
+                    editor.request = Some(EditorRequest::Save);
+                }
+            });
+            let mut load = None;
+            for name in editor.saved_maps.iter() {
+                ui.horizontal(|ui| {
+                    ui.label(name);
+                    if ui.small_button("Load").clicked() {
+                        load = Some(name.clone());
+                    }
+                });
+            }
+            if let Some(name) = load {
+                editor.map_name = name.clone();
+                editor.request = Some(EditorRequest::Load(name));
+            }
+            match &editor.status {
+                Some(Ok(message)) => {
+                    ui.label(message);
+                }
+                Some(Err(error)) => {
+                    ui.colored_label(Color32::LIGHT_RED, error.to_string());
+                }
+                None => {}
+            }
+        });
+}
@@ -0,0 +1,82 @@
+use egui::{Align, Layout, ProgressBar, RawInput};
+use egui_wgpu::renderer::ScreenDescriptor;
+use winit::window::Window;
+
+use crate::components::cs_io::assets::LoadProgress;
+use crate::components::cs_io::loading_state;
+use crate::main_loop::Render;
+
+/// Progress bar shown while the assets load on desktop, before the world and the `UiLayer` exist.
+pub struct LoadingScreen {
+    context: egui::Context,
+    renderer: egui_wgpu::Renderer,
+}
+
+impl LoadingScreen {
+    pub fn new(render: &Render) -> Self {
+        Self {
+            context: egui::Context::default(),
+            renderer: egui_wgpu::Renderer::new(&render.device, render.config.format, None, 1),
+        }
+    }
+
+    /// Draws one frame directly to the surface.
+    pub fn draw(&mut self, render: &Render, window: &Window, progress: &LoadProgress) {
+        let pixels_per_point = window.scale_factor() as f32;
+        let screen_size = egui::vec2(render.config.width as f32, render.config.height as f32) / pixels_per_point;
+        let raw_input = RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, screen_size)),
+            pixels_per_point: Some(pixels_per_point),
+            ..Default::default()
+        };
+        let output = self.context.run(raw_input, |context| {
+            egui::CentralPanel::default().show(context, |ui| {
+                ui.with_layout(Layout::top_down(Align::Center), |ui| {
+                    ui.add_space(ui.available_height() / 2.0 - 30.0);
+                    ui.heading("Castle Sim");
+                    ui.add(ProgressBar::new(progress.fraction()).desired_width(screen_size.x / 3.0).show_percentage());
+                    ui.label(loading_state::loading_text(progress));
+                });
+            });
+        });
+
+        // the window may be minimized, the next progress update tries again
+        let Ok(frame) = render.surface.get_current_texture() else {
+            return;
+        };
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        for (id, image_delta) in output.textures_delta.set.iter() {
+            self.renderer.update_texture(&render.device, &render.queue, *id, image_delta);
+        }
+        let paint_jobs = self.context.tessellate(output.shapes);
+        let screen_descriptor = ScreenDescriptor {
+            size_in_pixels: [render.config.width, render.config.height],
+            pixels_per_point,
+        };
+
+        let mut encoder = render.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Loading Screen Encoder"),
+        });
+        let command_buffers = self.renderer.update_buffers(&render.device, &render.queue, &mut encoder, &paint_jobs, &screen_descriptor);
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Loading Screen Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.renderer.render(&mut render_pass, &paint_jobs, &screen_descriptor);
+        }
+        render.queue.submit(command_buffers.into_iter().chain(std::iter::once(encoder.finish())));
+        frame.present();
+        for id in output.textures_delta.free.iter() {
+            self.renderer.free_texture(id);
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod ui_layer;
+pub mod performance_overlay;
+pub mod loading_screen;
+pub mod placement_window;
+pub mod editor_window;
\ No newline at end of file
@@ -0,0 +1,81 @@
+use bevy_ecs::system::Res;
+use egui::{Color32, Pos2, Sense, Stroke, Vec2};
+
+use crate::components::cs_render::gpu_timings::GpuTimings;
+use crate::components::cs_ui::ui_layer::UiContext;
+use crate::components::cs_util::performance::{PerformanceOverlay, PerformanceStats, FRAME_HISTORY};
+
+const GRAPH_SIZE: Vec2 = Vec2::new(FRAME_HISTORY as f32, 60.0);
+/// Frame time shown at the top of the graph, longer frames are clipped.
+const GRAPH_MAX_FRAME_TIME: f32 = 33.3;
+const TARGET_FRAME_TIME: f32 = 16.7;
+
+/// Ui schedule system showing `PerformanceStats` in the top right corner.
+pub fn performance_window(ui_context: Res<UiContext>, overlay: Res<PerformanceOverlay>, stats: Res<PerformanceStats>, gpu_timings: Res<GpuTimings>) {
+    if !overlay.visible {
+        return;
+    }
+    egui::Window::new("Performance")
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .resizable(false)
+        .collapsible(false)
+        .show(&ui_context.context, |ui| {
+            egui::Grid::new("performance_grid").num_columns(2).show(ui, |ui| {
+                let rows = [
+                    ("FPS", format!("{}", stats.frames_per_second)),
+                    ("Updates/s", format!("{}", stats.updates_per_second)),
+                    ("Frame time", format!("{:.2} ms", stats.frame_time)),
+                    ("Tick time", format!("{:.3} ms", stats.tick_time)),
+                    ("Ticks/frame", format!("{}", stats.ticks_per_frame)),
+                    ("Render time", format!("{:.2} ms", stats.render_time)),
+                    ("Visible tiles", format!("{}", stats.visible_tiles)),
+                    ("Draw calls", format!("{}", stats.draw_calls)),
+                ];
+                for (name, value) in rows {
+                    ui.label(name);
+                    ui.monospace(value);
+                    ui.end_row();
+                }
+            });
+            frame_time_graph(ui, &stats);
+            gpu_timings_grid(ui, &gpu_timings);
+        });
+}
+
+fn gpu_timings_grid(ui: &mut egui::Ui, gpu_timings: &GpuTimings) {
+    ui.separator();
+    if !gpu_timings.supported {
+        ui.label("GPU timings need TIMESTAMP_QUERY support");
+        return;
+    }
+    egui::Grid::new("gpu_timings_grid").num_columns(2).show(ui, |ui| {
+        for pass in gpu_timings.passes.iter() {
+            ui.label(pass.name);
+            ui.monospace(format!("{:.3} ms", pass.milliseconds));
+            ui.end_row();
+        }
+    });
+}
+
+fn frame_time_graph(ui: &mut egui::Ui, stats: &PerformanceStats) {
+    let (rect, _) = ui.allocate_exact_size(GRAPH_SIZE, Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, Color32::from_black_alpha(120));
+
+    let to_y = |frame_time: f32| rect.bottom() - (frame_time / GRAPH_MAX_FRAME_TIME).min(1.0) * rect.height();
+    let target_y = to_y(TARGET_FRAME_TIME);
+    painter.line_segment([Pos2::new(rect.left(), target_y), Pos2::new(rect.right(), target_y)], Stroke::new(1.0, Color32::DARK_GREEN));
+
+    let points: Vec<Pos2> = stats.frame_times()
+        .enumerate()
+        .map(|(index, frame_time)| Pos2::new(rect.left() + index as f32, to_y(frame_time)))
+        .collect();
+    painter.add(egui::Shape::line(points, Stroke::new(1.0, Color32::LIGHT_GRAY)));
+    painter.text(
+        rect.min + Vec2::new(2.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        format!("{:.0} ms", GRAPH_MAX_FRAME_TIME),
+        egui::FontId::monospace(10.0),
+        Color32::GRAY,
+    );
+}
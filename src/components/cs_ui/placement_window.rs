@@ -0,0 +1,94 @@
+use bevy_ecs::system::{Res, ResMut};
+use egui::Color32;
+
+use crate::components::cs_ui::ui_layer::UiContext;
+use crate::components::cs_world::building::BuildingRegistry;
+use crate::components::cs_world::editor::MapEditor;
+use crate::components::cs_world::history::History;
+use crate::components::cs_world::placement::{PlacementTool, WallMode, WallTool};
+use crate::components::cs_world::wall::{WallPiece, TOWER_SPACING};
+
+/// Ui schedule system listing the buildings and wall pieces, picking one turns on its tool.
+/// Hidden while the map editor is open.
+pub fn placement_window(
+    ui_context: Res<UiContext>,
+    registry: Res<BuildingRegistry>,
+    history: Res<History>,
+    editor: Res<MapEditor>,
+    mut tool: ResMut<PlacementTool>,
+    mut wall_tool: ResMut<WallTool>,
+) {
+    if editor.active {
+        return;
+    }
+    egui::Window::new("Build")
+        .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+        .resizable(false)
+        .show(&ui_context.context, |ui| {
+            egui::Grid::new("building_grid").num_columns(2).show(ui, |ui| {
+                for (kind, definition) in registry.iter() {
+                    let selected = tool.building == Some(kind);
+                    if ui.selectable_label(selected, definition.name).clicked() {
+                        tool.select(if selected { None } else { Some(kind) });
+                        wall_tool.select(None);
+                    }
+                    ui.label(definition.cost.to_string());
+                    ui.end_row();
+                }
+                let wall_modes = [
+                    (WallMode::Walls, "walls", format!("{} per segment", WallPiece::Wall.cost())),
+                    (WallMode::Gatehouse, "gatehouse", WallPiece::Gatehouse.cost().to_string()),
+                ];
+                for (mode, name, cost) in wall_modes {
+                    let selected = wall_tool.mode == Some(mode);
+                    if ui.selectable_label(selected, name).clicked() {
+                        wall_tool.select(if selected { None } else { Some(mode) });
+                        tool.select(None);
+                    }
+                    ui.label(cost);
+                    ui.end_row();
+                }
+                if ui.selectable_label(tool.demolish, "demolish").clicked() {
+                    let demolish = !tool.demolish;
+                    tool.select_demolish(demolish);
+                    wall_tool.select(None);
+                }
+                ui.end_row();
+            });
+            if let Some(mode) = wall_tool.mode {
+                ui.separator();
+                match mode {
+                    WallMode::Walls => {
+                        ui.checkbox(&mut wall_tool.towers, format!("Towers every {} segments", TOWER_SPACING));
+                        ui.label("Drag to draw, right click cancels");
+                    }
+                    WallMode::Gatehouse => {
+                        ui.label("Click a straight wall");
+                    }
+                }
+                if let Some(Ok(plan)) = &wall_tool.preview {
+                    ui.label(format!("Cost: {}", plan.cost));
+                }
+                if let Some(reason) = wall_tool.invalid_reason() {
+                    ui.colored_label(Color32::LIGHT_RED, reason);
+                }
+            }
+            if history.undo_label().is_some() || history.redo_label().is_some() {
+                ui.separator();
+                if let Some(label) = history.undo_label() {
+                    ui.label(format!("Ctrl+Z: undo {}", label));
+                }
+                if let Some(label) = history.redo_label() {
+                    ui.label(format!("Ctrl+Y: redo {}", label));
+                }
+            }
+            if tool.building.is_none() {
+                return;
+            }
+            ui.separator();
+            ui.label("R rotates, Esc cancels");
+            if let Some(reason) = tool.invalid_reason() {
+                ui.colored_label(Color32::LIGHT_RED, reason);
+            }
+        });
+}
@@ -0,0 +1,126 @@
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::World;
+use egui::{ClippedPrimitive, TexturesDelta};
+use egui_wgpu::renderer::ScreenDescriptor;
+use wgpu::{Device, TextureFormat};
+use winit::event::{ElementState, WindowEvent};
+use winit::window::Window;
+
+use crate::main_loop::Render;
+
+/// Shared egui context, systems in the ui schedule use it to build their panels and buttons.
+#[derive(Resource, Clone, Default)]
+pub struct UiContext {
+    pub context: egui::Context,
+}
+
+/// What the ui used during the last frame, world interactions check this so clicks don't go through panels.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct UiFocus {
+    /// The cursor is over a panel or the ui is dragging something.
+    pub pointer: bool,
+    /// A text field has keyboard focus.
+    pub keyboard: bool,
+}
+
+/// Collects window input for egui and paints the output of the ui schedule after the world pass.
+#[derive(Resource)]
+pub struct UiLayer {
+    winit_state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    paint_jobs: Vec<ClippedPrimitive>,
+    textures_delta: TexturesDelta,
+    screen_descriptor: ScreenDescriptor,
+}
+
+impl UiLayer {
+    pub fn register(device: &Device, format: TextureFormat, window: &Window, world: &mut World) {
+        let mut winit_state = egui_winit::State::new(window);
+        winit_state.set_pixels_per_point(window.scale_factor() as f32);
+        let size = window.inner_size();
+        world.insert_resource(UiLayer {
+            winit_state,
+            renderer: egui_wgpu::Renderer::new(device, format, None, 1),
+            paint_jobs: Vec::new(),
+            textures_delta: TexturesDelta::default(),
+            screen_descriptor: ScreenDescriptor {
+                size_in_pixels: [size.width, size.height],
+                pixels_per_point: window.scale_factor() as f32,
+            },
+        });
+        world.insert_resource(UiContext::default());
+        world.insert_resource(UiFocus::default());
+    }
+
+    /// Passes a window event to egui, returns true if the game should not see it.
+    /// Releases are never consumed, otherwise a button pressed in the world stays down when released over a panel.
+    pub fn on_window_event(world: &mut World, event: &WindowEvent) -> bool {
+        let context = world.resource::<UiContext>().context.clone();
+        let mut ui_layer = world.resource_mut::<UiLayer>();
+        let response = ui_layer.winit_state.on_event(&context, event);
+        let released = match event {
+            WindowEvent::MouseInput { state, .. } => *state == ElementState::Released,
+            WindowEvent::KeyboardInput { input, .. } => input.state == ElementState::Released,
+            _ => false,
+        };
+        response.consumed && !released
+    }
+
+    /// Starts a ui frame, the systems of the ui schedule can use `UiContext` until `end_frame`.
+    pub fn begin_frame(world: &mut World, window: &Window) {
+        let context = world.resource::<UiContext>().context.clone();
+        let mut ui_layer = world.resource_mut::<UiLayer>();
+        let raw_input = ui_layer.winit_state.take_egui_input(window);
+        context.begin_frame(raw_input);
+    }
+
+    /// Tessellates the frame and uploads textures and buffers so `draw` only needs shared access.
+    pub fn end_frame(world: &mut World, window: &Window) {
+        let context = world.resource::<UiContext>().context.clone();
+        let output = context.end_frame();
+        {
+            let mut focus = world.resource_mut::<UiFocus>();
+            focus.pointer = context.wants_pointer_input() || context.is_pointer_over_area();
+            focus.keyboard = context.wants_keyboard_input();
+        }
+
+        world.resource_scope(|world, mut ui_layer: bevy_ecs::world::Mut<UiLayer>| {
+            let render = world.resource::<Render>();
+            let ui_layer = &mut *ui_layer;
+            ui_layer.winit_state.handle_platform_output(window, &context, output.platform_output);
+
+            // textures freed last frame are no longer referenced by any submitted pass
+            for id in std::mem::take(&mut ui_layer.textures_delta.free) {
+                ui_layer.renderer.free_texture(&id);
+            }
+            for (id, image_delta) in output.textures_delta.set.iter() {
+                ui_layer.renderer.update_texture(&render.device, &render.queue, *id, image_delta);
+            }
+            ui_layer.textures_delta = output.textures_delta;
+
+            ui_layer.paint_jobs = context.tessellate(output.shapes);
+            ui_layer.screen_descriptor = ScreenDescriptor {
+                size_in_pixels: [render.config.width, render.config.height],
+                pixels_per_point: context.pixels_per_point(),
+            };
+
+            let mut encoder = render.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Ui Upload Encoder"),
+            });
+            let command_buffers = ui_layer.renderer.update_buffers(
+                &render.device,
+                &render.queue,
+                &mut encoder,
+                &ui_layer.paint_jobs,
+                &ui_layer.screen_descriptor,
+            );
+            render.queue.submit(command_buffers.into_iter().chain(std::iter::once(encoder.finish())));
+        });
+    }
+
+    /// Returns the number of draw calls, egui issues one per clipped mesh.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) -> u32 {
+        self.renderer.render(render_pass, &self.paint_jobs, &self.screen_descriptor);
+        self.paint_jobs.len() as u32
+    }
+}
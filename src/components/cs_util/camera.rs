@@ -55,6 +55,27 @@ impl CustomCamera {
         update_visible_ara(self);
     }
 
+    /// Centers the camera on a position in map tile space.
+    pub fn look_at(&mut self, map_pos: Vector2<f32>) {
+        self.position = map::map_to_screen_pos_centered(map_pos);
+        update_matrix(self);
+    }
+
+    /// Converts a position in window pixels into world (screen space before the camera) coordinates.
+    pub fn screen_to_world(&self, screen_pos: Vector2<f32>) -> Vector2<f32> {
+        transform(screen_pos, self.view.invert().unwrap())
+    }
+
+    /// Map tile space positions of the window corners, clockwise starting top left.
+    pub fn view_corners(&self) -> [Vector2<f32>; 4] {
+        [
+            Vector2::new(0.0, 0.0),
+            Vector2::new(self.size.x, 0.0),
+            Vector2::new(self.size.x, self.size.y),
+            Vector2::new(0.0, self.size.y),
+        ].map(|corner| map::screen_to_map_pos_f32(self.screen_to_world(corner)))
+    }
+
     fn calculate_proj_matrix(screen_size: Vector2<f32>) -> Matrix4<f32> {
         create_orthographic_off_center(0.0, screen_size.x as f32, screen_size.y as f32, 0.0, 0.0, -1.0)
     }
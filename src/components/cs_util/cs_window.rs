@@ -14,8 +14,9 @@ use winit::{
 use winit::dpi::{LogicalSize, PhysicalSize};
 
 use crate::components::cs_render::shader_types::texture::Texture;
+use crate::components::cs_ui::ui_layer::UiLayer;
 use crate::components::cs_util::camera::CustomCamera;
-use crate::components::cs_util::input::Input;
+use crate::components::cs_util::input::{Cursor, Input};
 use crate::main_loop::{DummyTest, Render};
 
 cfg_if::cfg_if! {
@@ -66,7 +67,8 @@ impl State {
         let (device, queue) = adapter.request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
-                features: wgpu::Features::empty(),
+                // optional, used by the gpu timings of the performance overlay
+                features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
                 limits: wgpu::Limits::default(),
             },
             None,
@@ -127,6 +129,19 @@ impl State {
                     ElementState::Released => key_input.release(*keycode),
                 }
             }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let mut mouse_input = world.get_resource_mut::<Input<MouseButton>>().unwrap();
+                mouse_input.bypass_change_detection();
+
+                match state {
+                    ElementState::Pressed => mouse_input.press(*button),
+                    ElementState::Released => mouse_input.release(*button),
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let mut cursor = world.get_resource_mut::<Cursor>().unwrap();
+                cursor.position = Vector2::new(position.x as f32, position.y as f32);
+            }
             _ => {}
         };
 
@@ -198,6 +213,7 @@ pub async fn platform_specific_init(window: Window, world: &mut World) -> (State
 }
 
 pub fn check_window_events(state: &mut State, control_flow: &mut ControlFlow, event: &WindowEvent, world: &mut World) {
+    if UiLayer::on_window_event(world, event) { return; }
     if state.input(event, world) { return; }
     match event {
         WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
@@ -2,6 +2,19 @@
 use std::{hash::Hash, collections::HashSet};
 
 use bevy_ecs::system::Resource;
+use cgmath::Vector2;
+
+/// Last known cursor position in physical window pixels.
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct Cursor {
+    pub position: Vector2<f32>,
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self { position: Vector2::new(0.0, 0.0) }
+    }
+}
 #[derive(Debug, Clone, Resource)]
 pub struct Input<T: Copy + Eq + Hash + Send + Sync + 'static> {
     /// A collection of every button that is currently being pressed.
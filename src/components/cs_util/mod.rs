@@ -1,4 +1,7 @@
+#[allow(clippy::deprecated_cfg_attr, clippy::unnecessary_cast, clippy::useless_conversion, clippy::manual_clamp, clippy::assign_op_pattern)]
 pub mod camera;
 pub mod input;
-pub mod fps_counter;
+pub mod performance;
+#[allow(clippy::absurd_extreme_comparisons)]
 pub mod cs_window;
+pub mod time;
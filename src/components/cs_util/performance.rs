@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy_ecs::system::{Res, ResMut, Resource};
+use winit::event::VirtualKeyCode;
+
+use crate::components::cs_util::input::Input;
+
+/// Number of frames kept for the frame time graph.
+pub const FRAME_HISTORY: usize = 240;
+
+/// Timings and counters of the main loop, times are in milliseconds.
+#[derive(Debug, Clone, Resource)]
+pub struct PerformanceStats {
+    pub frames_per_second: u32,
+    /// Fixed update ticks during the last second.
+    pub updates_per_second: u32,
+    pub frame_time: f32,
+    /// Average duration of one update tick during the last frame.
+    pub tick_time: f32,
+    pub ticks_per_frame: u32,
+    /// CPU time spent recording and submitting the last frame.
+    pub render_time: f32,
+    pub visible_tiles: u32,
+    pub draw_calls: u32,
+    frame_times: VecDeque<f32>,
+    timer: Duration,
+    frame_counter: u32,
+    update_counter: u32,
+    frame_ticks: u32,
+    frame_tick_time: Duration,
+}
+
+impl Default for PerformanceStats {
+    fn default() -> Self {
+        Self {
+            frames_per_second: 0,
+            updates_per_second: 0,
+            frame_time: 0.0,
+            tick_time: 0.0,
+            ticks_per_frame: 0,
+            render_time: 0.0,
+            visible_tiles: 0,
+            draw_calls: 0,
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY),
+            timer: Duration::ZERO,
+            frame_counter: 0,
+            update_counter: 0,
+            frame_ticks: 0,
+            frame_tick_time: Duration::ZERO,
+        }
+    }
+}
+
+impl PerformanceStats {
+    /// Called after every run of the update schedule.
+    pub fn record_tick(&mut self, tick_time: Duration) {
+        self.update_counter += 1;
+        self.frame_ticks += 1;
+        self.frame_tick_time += tick_time;
+    }
+
+    /// Called once per redraw after all update ticks of the frame ran.
+    pub fn record_frame(&mut self, frame_time: Duration) {
+        self.frame_time = frame_time.as_secs_f32() * 1000.0;
+        self.ticks_per_frame = self.frame_ticks;
+        if self.frame_ticks > 0 {
+            self.tick_time = self.frame_tick_time.as_secs_f32() * 1000.0 / self.frame_ticks as f32;
+        }
+        self.frame_ticks = 0;
+        self.frame_tick_time = Duration::ZERO;
+
+        if self.frame_times.len() == FRAME_HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(self.frame_time);
+
+        self.frame_counter += 1;
+        self.timer += frame_time;
+        if self.timer < Duration::from_secs(1) {
+            return;
+        }
+        self.frames_per_second = self.frame_counter;
+        self.updates_per_second = self.update_counter;
+        self.frame_counter = 0;
+        self.update_counter = 0;
+        self.timer = Duration::ZERO;
+    }
+
+    pub fn record_render(&mut self, render_time: Duration, visible_tiles: u32, draw_calls: u32) {
+        self.render_time = render_time.as_secs_f32() * 1000.0;
+        self.visible_tiles = visible_tiles;
+        self.draw_calls = draw_calls;
+    }
+
+    /// Frame times of the last `FRAME_HISTORY` frames, oldest first.
+    pub fn frame_times(&self) -> impl Iterator<Item = f32> + '_ {
+        self.frame_times.iter().copied()
+    }
+}
+
+/// Whether the performance overlay is shown, toggled with F3.
+#[derive(Debug, Clone, Copy, Default, Resource)]
+pub struct PerformanceOverlay {
+    pub visible: bool,
+}
+
+pub fn toggle_performance_overlay(keyboard_input: Res<Input<VirtualKeyCode>>, mut overlay: ResMut<PerformanceOverlay>) {
+    if keyboard_input.just_pressed(VirtualKeyCode::F3) {
+        overlay.visible = !overlay.visible;
+    }
+}
@@ -0,0 +1,8 @@
+use bevy_ecs::system::Resource;
+
+/// Simulation time, advanced by the fixed update loop.
+#[derive(Debug, Clone, Copy, Default, Resource)]
+pub struct GameTime {
+    pub elapsed: f64,
+    pub delta: f64,
+}
@@ -0,0 +1,81 @@
+use cgmath::Vector2;
+use serde::Deserialize;
+
+// neighbour bits in map space, cardinal directions first so a cardinal mask is always < 16
+pub const NORTH: u8 = 1;
+pub const EAST: u8 = 2;
+pub const SOUTH: u8 = 4;
+pub const WEST: u8 = 8;
+pub const NORTH_EAST: u8 = 16;
+pub const SOUTH_EAST: u8 = 32;
+pub const SOUTH_WEST: u8 = 64;
+pub const NORTH_WEST: u8 = 128;
+
+pub const NEIGHBOURS: [(u8, Vector2<i32>); 8] = [
+    (NORTH, Vector2::new(0, -1)),
+    (EAST, Vector2::new(1, 0)),
+    (SOUTH, Vector2::new(0, 1)),
+    (WEST, Vector2::new(-1, 0)),
+    (NORTH_EAST, Vector2::new(1, -1)),
+    (SOUTH_EAST, Vector2::new(1, 1)),
+    (SOUTH_WEST, Vector2::new(-1, 1)),
+    (NORTH_WEST, Vector2::new(-1, -1)),
+];
+
+/// Number of distinct variants a full (8 neighbour) autotile set needs.
+pub const FULL_VARIANT_COUNT: usize = 47;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum AutotileMode {
+    /// 4 neighbours, 16 variants (roads, walls).
+    Cardinal,
+    /// 8 neighbours with corners reduced to 47 variants (water shorelines).
+    Full,
+}
+
+/// Builds the neighbour bitmask for a tile, `connects` tells if the neighbour at the given offset joins with the tile.
+pub fn neighbour_mask(mode: AutotileMode, connects: impl Fn(Vector2<i32>) -> bool) -> u8 {
+    let mut mask = 0;
+    for (bit, offset) in NEIGHBOURS.iter() {
+        if mode == AutotileMode::Cardinal && *bit > WEST {
+            break;
+        }
+        if connects(*offset) {
+            mask |= bit;
+        }
+    }
+
+    match mode {
+        AutotileMode::Cardinal => mask,
+        AutotileMode::Full => reduce_corners(mask),
+    }
+}
+
+/// A corner only matters if both cardinal neighbours next to it connect too.
+pub fn reduce_corners(mask: u8) -> u8 {
+    let mut reduced = mask & (NORTH | EAST | SOUTH | WEST);
+    let corners = [
+        (NORTH_EAST, NORTH | EAST),
+        (SOUTH_EAST, SOUTH | EAST),
+        (SOUTH_WEST, SOUTH | WEST),
+        (NORTH_WEST, NORTH | WEST),
+    ];
+    for (corner, sides) in corners {
+        if mask & corner != 0 && mask & sides == sides {
+            reduced |= corner;
+        }
+    }
+    reduced
+}
+
+/// Maps a mask to the index of its sprite inside the variant strip of the atlas.
+pub fn variant_index(mode: AutotileMode, mask: u8) -> u8 {
+    match mode {
+        AutotileMode::Cardinal => mask & (NORTH | EAST | SOUTH | WEST),
+        AutotileMode::Full => {
+            let reduced = reduce_corners(mask);
+            // variants are stored in ascending order of their reduced mask
+            (0..reduced).filter(|candidate| reduce_corners(*candidate) == *candidate).count() as u8
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::{fmt, ops};
+
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::With;
+use bevy_ecs::schedule::{IntoSystemConfig, Schedule};
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::World;
+use cgmath::Vector2;
+use thiserror::Error;
+
+use crate::components::cs_render::overlay;
+use crate::components::cs_world::map::{self, Map};
+use crate::components::cs_world::placement;
+use crate::components::cs_world::position::TilePosition;
+use crate::components::cs_world::tile_registry::TileRegistry;
+use crate::components::cs_world::unit::Unit;
+
+pub type BuildingKindId = u16;
+
+/// Quarter turns of a building. The camera never rotates, so turns relative to the view are turns on the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl Rotation {
+    /// The next quarter turn clockwise on screen.
+    pub fn next(self) -> Self {
+        match self {
+            Rotation::None => Rotation::Quarter,
+            Rotation::Quarter => Rotation::Half,
+            Rotation::Half => Rotation::ThreeQuarters,
+            Rotation::ThreeQuarters => Rotation::None,
+        }
+    }
+
+    /// Odd quarter turns swap the sides of the footprint and mirror the sprite.
+    pub fn is_sideways(self) -> bool {
+        matches!(self, Rotation::Quarter | Rotation::ThreeQuarters)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BuildingCost {
+    pub wood: u32,
+    pub stone: u32,
+    pub gold: u32,
+}
+
+impl ops::Add for BuildingCost {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self { wood: self.wood + other.wood, stone: self.stone + other.stone, gold: self.gold + other.gold }
+    }
+}
+
+impl ops::AddAssign for BuildingCost {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl fmt::Display for BuildingCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = [(self.wood, "wood"), (self.stone, "stone"), (self.gold, "gold")]
+            .iter()
+            .filter(|(amount, _)| *amount > 0)
+            .map(|(amount, resource)| format!("{} {}", amount, resource))
+            .collect();
+        match parts.is_empty() {
+            true => write!(f, "free"),
+            false => write!(f, "{}", parts.join(", ")),
+        }
+    }
+}
+
+/// Pixel rectangle in the sprite sheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpriteRect {
+    pub origin: Vector2<u32>,
+    pub size: Vector2<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BuildingDefinition {
+    pub name: &'static str,
+    /// Tiles along the map x and y axis without rotation.
+    pub footprint: Vector2<i32>,
+    /// Names of the tiles every footprint tile has to be.
+    pub terrain: Vec<&'static str>,
+    pub cost: BuildingCost,
+    /// 16 pixels wide per footprint tile along both axes, the top corner of the
+    /// footprint is `16 * footprint.y` pixels from the left.
+    pub sprite: SpriteRect,
+}
+
+impl BuildingDefinition {
+    fn new(name: &'static str, footprint: Vector2<i32>, cost: BuildingCost, sprite: SpriteRect) -> Self {
+        Self {
+            name,
+            footprint,
+            terrain: vec!["grass"],
+            cost,
+            sprite,
+        }
+    }
+
+    fn with_terrain(mut self, terrain: Vec<&'static str>) -> Self {
+        self.terrain = terrain;
+        self
+    }
+
+    pub fn footprint(&self, rotation: Rotation) -> Vector2<i32> {
+        match rotation.is_sideways() {
+            true => Vector2::new(self.footprint.y, self.footprint.x),
+            false => self.footprint,
+        }
+    }
+
+    /// Origin that puts the middle of the footprint on `tile`.
+    pub fn origin_around(&self, tile: Vector2<i32>, rotation: Rotation) -> Vector2<i32> {
+        let footprint = self.footprint(rotation);
+        tile - Vector2::new((footprint.x - 1) / 2, (footprint.y - 1) / 2)
+    }
+
+    pub fn tiles(&self, origin: Vector2<i32>, rotation: Rotation) -> Vec<Vector2<i32>> {
+        let footprint = self.footprint(rotation);
+        (0..footprint.y)
+            .flat_map(|y| (0..footprint.x).map(move |x| origin + Vector2::new(x, y)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct BuildingRegistry {
+    definitions: Vec<BuildingDefinition>,
+    by_name: HashMap<&'static str, BuildingKindId>,
+}
+
+impl BuildingRegistry {
+    pub fn new(definitions: Vec<BuildingDefinition>) -> Self {
+        let by_name = definitions.iter()
+            .enumerate()
+            .map(|(id, definition)| (definition.name, id as BuildingKindId))
+            .collect();
+        Self { definitions, by_name }
+    }
+
+    /// Inserts the registry, an empty occupancy for the map and the placement and wall tools.
+    pub fn register(self, map_size: Vector2<i32>, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(self);
+        world.insert_resource(Occupancy::new(map_size));
+        world.insert_resource(placement::PlacementTool::default());
+        world.insert_resource(placement::WallTool::default());
+        schedule.add_system(placement::update_placement_tool.after(overlay::update_hovered_tile).before(overlay::update_overlay_instances).before(map::publish_tile_changes));
+        schedule.add_system(placement::update_wall_tool.after(overlay::update_hovered_tile).before(overlay::update_overlay_instances).before(map::publish_tile_changes));
+    }
+
+    pub fn get(&self, kind: BuildingKindId) -> &BuildingDefinition {
+        &self.definitions[kind as usize]
+    }
+
+    pub fn id(&self, name: &str) -> Option<BuildingKindId> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BuildingKindId, &BuildingDefinition)> {
+        self.definitions.iter().enumerate().map(|(id, definition)| (id as BuildingKindId, definition))
+    }
+}
+
+fn sprite(x: u32, y: u32, width: u32, height: u32) -> SpriteRect {
+    SpriteRect { origin: Vector2::new(x, y), size: Vector2::new(width, height) }
+}
+
+impl Default for BuildingRegistry {
+    fn default() -> Self {
+        Self::new(vec![
+            BuildingDefinition::new("house", Vector2::new(2, 2), BuildingCost { wood: 20, ..Default::default() }, sprite(80, 0, 64, 56)),
+            BuildingDefinition::new("farm", Vector2::new(3, 3), BuildingCost { wood: 10, ..Default::default() }, sprite(80, 56, 96, 48)),
+            // wells also go next to roads inside the town
+            BuildingDefinition::new("well", Vector2::new(1, 1), BuildingCost { stone: 10, ..Default::default() }, sprite(80, 104, 32, 32))
+                .with_terrain(vec!["grass", "road"]),
+            BuildingDefinition::new("tower", Vector2::new(2, 2), BuildingCost { stone: 40, ..Default::default() }, sprite(80, 136, 64, 80)),
+            BuildingDefinition::new("granary", Vector2::new(3, 2), BuildingCost { wood: 30, gold: 5, ..Default::default() }, sprite(80, 216, 80, 60)),
+        ])
+    }
+}
+
+/// A placed building, its entity also has the `TilePosition` of the footprint centre.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct Building {
+    pub kind: BuildingKindId,
+    pub origin: Vector2<i32>,
+    pub rotation: Rotation,
+}
+
+/// Building entity standing on each tile of the map.
+#[derive(Debug, Clone, Resource)]
+pub struct Occupancy {
+    size: Vector2<i32>,
+    tiles: Vec<Option<Entity>>,
+}
+
+impl Occupancy {
+    pub fn new(size: Vector2<i32>) -> Self {
+        Self { size, tiles: vec![None; (size.x * size.y) as usize] }
+    }
+
+    fn index(&self, pos: Vector2<i32>) -> Option<usize> {
+        let inside = pos.x >= 0 && pos.y >= 0 && pos.x < self.size.x && pos.y < self.size.y;
+        inside.then(|| (pos.y * self.size.x + pos.x) as usize)
+    }
+
+    pub fn get(&self, pos: Vector2<i32>) -> Option<Entity> {
+        self.index(pos).and_then(|index| self.tiles[index])
+    }
+
+    pub fn is_occupied(&self, pos: Vector2<i32>) -> bool {
+        self.get(pos).is_some()
+    }
+
+    pub fn set(&mut self, pos: Vector2<i32>, building: Option<Entity>) {
+        if let Some(index) = self.index(pos) {
+            self.tiles[index] = building;
+        }
+    }
+}
+
+/// Why a building cannot be placed, shown to the player as is.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PlacementError {
+    #[error("outside the map")]
+    OutsideMap,
+    #[error("cannot build on {0}")]
+    WrongTerrain(String),
+    #[error("blocked by a building")]
+    Occupied,
+    #[error("units are in the way")]
+    UnitsInTheWay,
+    #[error("gatehouses go into a wall")]
+    NotOnWall,
+    #[error("gatehouses need a straight wall")]
+    NotStraight,
+}
+
+/// Checks the footprint against the map, the buildings and the units standing on it.
+pub fn check_placement(world: &mut World, kind: BuildingKindId, origin: Vector2<i32>, rotation: Rotation) -> Result<(), PlacementError> {
+    let definition = world.resource::<BuildingRegistry>().get(kind);
+    let tiles = definition.tiles(origin, rotation);
+    let map = world.resource::<Map>();
+    let tile_registry = world.resource::<TileRegistry>();
+    for tile in tiles.iter() {
+        let Some(tile_kind) = map.kind(*tile) else {
+            return Err(PlacementError::OutsideMap);
+        };
+        let terrain = &tile_registry.get(tile_kind).name;
+        if !definition.terrain.contains(&terrain.as_str()) {
+            return Err(PlacementError::WrongTerrain(terrain.clone()));
+        }
+    }
+    let occupancy = world.resource::<Occupancy>();
+    if tiles.iter().any(|tile| occupancy.is_occupied(*tile)) {
+        return Err(PlacementError::Occupied);
+    }
+    let mut units = world.query_filtered::<&TilePosition, With<Unit>>();
+    if units.iter(world).any(|position| tiles.contains(&position.tile())) {
+        return Err(PlacementError::UnitsInTheWay);
+    }
+    Ok(())
+}
+
+/// Spawns the building and occupies its footprint, the cost grid follows through the published tile changes.
+pub fn place_building(world: &mut World, kind: BuildingKindId, origin: Vector2<i32>, rotation: Rotation) -> Result<Entity, PlacementError> {
+    check_placement(world, kind, origin, rotation)?;
+    Ok(spawn_building(world, Building { kind, origin, rotation }))
+}
+
+/// Spawns the building without checking its footprint, used to restore buildings that stood there before.
+pub fn spawn_building(world: &mut World, building: Building) -> Entity {
+    let definition = world.resource::<BuildingRegistry>().get(building.kind);
+    let tiles = definition.tiles(building.origin, building.rotation);
+    let footprint = definition.footprint(building.rotation);
+    let centre = Vector2::new(building.origin.x as f32 + footprint.x as f32 / 2.0, building.origin.y as f32 + footprint.y as f32 / 2.0);
+    let entity = world.spawn((building, TilePosition(centre))).id();
+    set_footprint(world, &tiles, Some(entity));
+    entity
+}
+
+/// Removes the building and frees its footprint, returns the removed building.
+pub fn demolish_building(world: &mut World, entity: Entity) -> Option<Building> {
+    let building = *world.get::<Building>(entity)?;
+    let tiles = world.resource::<BuildingRegistry>().get(building.kind).tiles(building.origin, building.rotation);
+    set_footprint(world, &tiles, None);
+    world.despawn(entity);
+    Some(building)
+}
+
+fn set_footprint(world: &mut World, tiles: &[Vector2<i32>], building: Option<Entity>) {
+    let mut occupancy = world.resource_mut::<Occupancy>();
+    for tile in tiles.iter() {
+        occupancy.set(*tile, building);
+    }
+    let mut map = world.resource_mut::<Map>();
+    for tile in tiles.iter() {
+        map.mark_changed(*tile);
+    }
+}
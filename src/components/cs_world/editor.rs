@@ -0,0 +1,397 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy_ecs::schedule::{IntoSystemConfig, Schedule};
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::{Mut, World};
+use cgmath::Vector2;
+use futures_util::FutureExt;
+use thiserror::Error;
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::components::cs_io::asset_path::AssetPath;
+use crate::components::cs_io::{AssetIoError, UserStore, WritableAssetIo};
+use crate::components::cs_render::overlay::{self, TileOverlay};
+use crate::components::cs_ui::ui_layer::UiFocus;
+use crate::components::cs_util::input::Input;
+use crate::components::cs_world::history;
+use crate::components::cs_world::map::{self, HeightEdit, Map, MapEdit, TileEdit, TilePreview};
+use crate::components::cs_world::map_data::{MapData, MapDataError};
+use crate::components::cs_world::placement::{self, PlacementTool, WallTool};
+use crate::components::cs_world::tile_registry::{TileKindId, TileRegistry};
+
+pub const EDITOR_LAYER: &str = "editor";
+/// Directory of the user's store that saved maps go into.
+pub const MAP_DIRECTORY: &str = "maps";
+pub const MAP_EXTENSION: &str = "csmp";
+pub const MAX_BRUSH_RADIUS: i32 = 8;
+const BRUSH_COLOR: [f32; 4] = [0.95, 0.85, 0.3, 0.4];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Brush {
+    Single,
+    Square,
+    Circle,
+    /// Every connected tile with the same kind or height as the clicked one.
+    Fill,
+}
+
+/// What a stroke changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorLayer {
+    Terrain,
+    Height,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeightMode {
+    Raise,
+    Lower,
+    /// Sets every tile to `MapEditor::height`.
+    Level,
+}
+
+/// Save or load picked in the editor window, run by the next update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorRequest {
+    Save,
+    Load(String),
+}
+
+#[derive(Error, Debug)]
+pub enum EditorError {
+    #[error("map names cannot be empty or contain slashes")]
+    InvalidName,
+
+    #[error("storage is busy, try again")]
+    StoragePending,
+
+    #[error(transparent)]
+    Storage(#[from] AssetIoError),
+
+    #[error(transparent)]
+    MapData(#[from] MapDataError),
+
+    #[error("saved map is {}x{} tiles but this one is {}x{}", saved.x, saved.y, current.x, current.y)]
+    SizeMismatch { saved: Vector2<i32>, current: Vector2<i32> },
+}
+
+/// Changes of the stroke in progress, they are already on the map and go into the history as one edit.
+#[derive(Debug, Default)]
+struct Stroke {
+    edit: MapEdit,
+    painted: HashSet<Vector2<i32>>,
+}
+
+/// In-game map editor, F2 turns it on and off. A stroke lasts from press to release of the left button.
+#[derive(Debug, Resource)]
+pub struct MapEditor {
+    pub active: bool,
+    pub brush: Brush,
+    /// Tiles from the centre to the edge of square and circle brushes.
+    pub radius: i32,
+    pub layer: EditorLayer,
+    /// Tile kind the terrain layer paints.
+    pub tile: TileKindId,
+    pub height_mode: HeightMode,
+    /// Height `HeightMode::Level` sets.
+    pub height: u8,
+    /// Name to save under, without directory and extension.
+    pub map_name: String,
+    /// Names of the maps in the user's store, refreshed when the editor opens and after saving.
+    pub saved_maps: Vec<String>,
+    /// Result of the last save or load, shown in the editor window.
+    pub status: Option<Result<String, EditorError>>,
+    pub request: Option<EditorRequest>,
+    stroke: Option<Stroke>,
+}
+
+impl Default for MapEditor {
+    fn default() -> Self {
+        Self {
+            active: false,
+            brush: Brush::Single,
+            radius: 1,
+            layer: EditorLayer::Terrain,
+            tile: 0,
+            height_mode: HeightMode::Raise,
+            height: 0,
+            map_name: "custom".to_string(),
+            saved_maps: Vec::new(),
+            status: None,
+            request: None,
+            stroke: None,
+        }
+    }
+}
+
+impl MapEditor {
+    /// Inserts the editor, it runs after the building tools so its preview wins while it is open.
+    pub fn register(self, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(self);
+        schedule.add_system(
+            update_map_editor
+                .after(overlay::update_hovered_tile)
+                .after(placement::update_placement_tool)
+                .after(placement::update_wall_tool)
+                .before(overlay::update_overlay_instances)
+                .before(map::publish_tile_changes),
+        );
+    }
+
+    pub fn is_painting(&self) -> bool {
+        self.stroke.is_some()
+    }
+
+    /// History label of a stroke with the current settings.
+    pub fn stroke_label(&self, registry: &TileRegistry) -> String {
+        match self.layer {
+            EditorLayer::Terrain => format!("paint {}", registry.get(self.tile).name),
+            EditorLayer::Height => match self.height_mode {
+                HeightMode::Raise => "raise terrain".to_string(),
+                HeightMode::Lower => "lower terrain".to_string(),
+                HeightMode::Level => format!("level terrain to {}", self.height),
+            },
+        }
+    }
+}
+
+/// Tiles the brush covers around `center`, clipped to the map.
+pub fn brush_tiles(map: &Map, brush: Brush, layer: EditorLayer, center: Vector2<i32>, radius: i32) -> Vec<Vector2<i32>> {
+    if !map.in_bounds(center) {
+        return Vec::new();
+    }
+    let radius = radius.clamp(0, MAX_BRUSH_RADIUS);
+    let area = (-radius..=radius).flat_map(|y| (-radius..=radius).map(move |x| Vector2::new(x, y)));
+    match brush {
+        Brush::Single => vec![center],
+        Brush::Square => area.map(|offset| center + offset).filter(|tile| map.in_bounds(*tile)).collect(),
+        // the extra radius rounds off the points a plain distance check leaves on the axes
+        Brush::Circle => area
+            .filter(|offset| offset.x * offset.x + offset.y * offset.y <= radius * radius + radius)
+            .map(|offset| center + offset)
+            .filter(|tile| map.in_bounds(*tile))
+            .collect(),
+        Brush::Fill => flood_fill(map, layer, center),
+    }
+}
+
+/// Tiles reachable from `start` through edges without the kind or height changing.
+fn flood_fill(map: &Map, layer: EditorLayer, start: Vector2<i32>) -> Vec<Vector2<i32>> {
+    let value = |pos: Vector2<i32>| match layer {
+        EditorLayer::Terrain => map.kind(pos),
+        EditorLayer::Height => map.height(pos).map(TileKindId::from),
+    };
+    let target = value(start);
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    let mut tiles = Vec::new();
+    while let Some(tile) = queue.pop_front() {
+        tiles.push(tile);
+        for offset in [Vector2::new(1, 0), Vector2::new(-1, 0), Vector2::new(0, 1), Vector2::new(0, -1)] {
+            let next = tile + offset;
+            if value(next) == target && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    tiles
+}
+
+/// Paints the brush at `center` into the current stroke, starting one if needed.
+/// Every tile is only changed once per stroke, so holding the button still raises by a single level.
+pub fn paint(world: &mut World, center: Vector2<i32>) {
+    world.resource_scope(|world, mut editor: Mut<MapEditor>| {
+        world.resource_scope(|world, mut map: Mut<Map>| {
+            let registry = world.resource::<TileRegistry>();
+            let editor = editor.as_mut();
+            let tiles = brush_tiles(&map, editor.brush, editor.layer, center, editor.radius);
+            let stroke = editor.stroke.get_or_insert_with(Stroke::default);
+            let tiles: Vec<Vector2<i32>> = tiles.into_iter().filter(|tile| stroke.painted.insert(*tile)).collect();
+            match editor.layer {
+                EditorLayer::Terrain => {
+                    let changes: Vec<(Vector2<i32>, TileKindId)> = tiles.iter().map(|tile| (*tile, editor.tile)).collect();
+                    let edit = TileEdit::new(&map, &changes);
+                    edit.apply(&mut map, registry);
+                    stroke.edit.tiles.extend(edit);
+                }
+                EditorLayer::Height => {
+                    let changes: Vec<(Vector2<i32>, u8)> = tiles.iter()
+                        .map(|tile| {
+                            let height = map.height(*tile).unwrap();
+                            let height = match editor.height_mode {
+                                HeightMode::Raise => height.saturating_add(1),
+                                HeightMode::Lower => height.saturating_sub(1),
+                                HeightMode::Level => editor.height,
+                            };
+                            (*tile, height)
+                        })
+                        .collect();
+                    let edit = HeightEdit::new(&map, &changes);
+                    edit.apply(&mut map);
+                    stroke.edit.heights.extend(edit);
+                }
+            }
+        });
+    });
+}
+
+/// Ends the stroke and records it as one undo step, strokes that changed nothing are dropped.
+pub fn finish_stroke(world: &mut World) {
+    let Some(stroke) = world.resource_mut::<MapEditor>().stroke.take() else {
+        return;
+    };
+    if !stroke.edit.is_empty() {
+        let label = world.resource::<MapEditor>().stroke_label(world.resource::<TileRegistry>());
+        history::record(world, label, stroke.edit);
+    }
+}
+
+/// Takes the stroke back without recording it.
+pub fn cancel_stroke(world: &mut World) {
+    let Some(stroke) = world.resource_mut::<MapEditor>().stroke.take() else {
+        return;
+    };
+    world.resource_scope(|world, mut map: Mut<Map>| stroke.edit.revert(&mut map, world.resource::<TileRegistry>()));
+}
+
+/// Path of a saved map in the user's store.
+pub fn map_path(name: &str) -> Result<AssetPath, EditorError> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(EditorError::InvalidName);
+    }
+    AssetPath::new(&format!("{}/{}.{}", MAP_DIRECTORY, name, MAP_EXTENSION)).map_err(|_| EditorError::InvalidName)
+}
+
+/// Names of the maps saved in the store, sorted.
+pub fn saved_maps(store: &dyn WritableAssetIo) -> Result<Vec<String>, EditorError> {
+    let directory = AssetPath::new(MAP_DIRECTORY).unwrap();
+    let paths = store.list(&directory).now_or_never().ok_or(EditorError::StoragePending)??;
+    Ok(paths.iter()
+        .filter(|path| path.extension() == Some(MAP_EXTENSION))
+        .map(|path| path.file_name().trim_end_matches(MAP_EXTENSION).trim_end_matches('.').to_string())
+        .collect())
+}
+
+pub fn save_map(world: &World, store: &dyn WritableAssetIo, name: &str) -> Result<(), EditorError> {
+    let path = map_path(name)?;
+    let bytes = MapData::from_map(world.resource::<Map>(), world.resource::<TileRegistry>()).to_bytes();
+    store.save(&path, &bytes).now_or_never().ok_or(EditorError::StoragePending)??;
+    Ok(())
+}
+
+/// Replaces kinds and heights with the saved map as one undoable edit. Buildings and units stay where they are.
+pub fn load_map(world: &mut World, store: &dyn WritableAssetIo, name: &str) -> Result<(), EditorError> {
+    let path = map_path(name)?;
+    let bytes = store.load_path(&path).now_or_never().ok_or(EditorError::StoragePending)??;
+    let data = MapData::from_slice(&bytes)?;
+    replace_map(world, &data, format!("load {}", name.trim()))
+}
+
+/// Replaces kinds and heights of the map with `data` as one undoable edit named `label`.
+pub fn replace_map(world: &mut World, data: &MapData, label: String) -> Result<(), EditorError> {
+    let map = world.resource::<Map>();
+    if data.size != map.size {
+        return Err(EditorError::SizeMismatch { saved: data.size, current: map.size });
+    }
+    let saved = data.to_map(world.resource::<TileRegistry>())?;
+    let tiles: Vec<(Vector2<i32>, TileKindId)> = (0..map.size.y)
+        .flat_map(|y| (0..map.size.x).map(move |x| Vector2::new(x, y)))
+        .map(|pos| (pos, saved.kind(pos).unwrap()))
+        .collect();
+    let heights: Vec<(Vector2<i32>, u8)> = tiles.iter().map(|(pos, _)| (*pos, saved.height(*pos).unwrap())).collect();
+    let edit = MapEdit { tiles: TileEdit::new(map, &tiles), heights: HeightEdit::new(map, &heights) };
+    if !edit.is_empty() {
+        history::execute(world, label, edit);
+    }
+    Ok(())
+}
+
+/// F2 toggles the editor. While it is open the brush follows the cursor and the left button paints,
+/// a right click takes the current stroke back. Save and load requests from the window run here.
+pub fn update_map_editor(world: &mut World) {
+    let toggle = world.resource::<Input<VirtualKeyCode>>().just_pressed(VirtualKeyCode::F2) && !world.resource::<UiFocus>().keyboard;
+    if toggle {
+        set_active(world, !world.resource::<MapEditor>().active);
+    }
+    if !world.resource::<MapEditor>().active {
+        return;
+    }
+    run_request(world);
+
+    let mouse_input = world.resource::<Input<MouseButton>>();
+    let (pressed, held, cancel) = (
+        mouse_input.just_pressed(MouseButton::Left),
+        mouse_input.pressed(MouseButton::Left),
+        mouse_input.just_pressed(MouseButton::Right),
+    );
+    let hovered = world.resource::<TileOverlay>().hovered_tile;
+    if cancel {
+        cancel_stroke(world);
+    }
+    let painting = world.resource::<MapEditor>().is_painting();
+    match hovered {
+        Some(tile) if pressed && placement::pointer_on_world(world) => paint(world, tile),
+        // a fill covers the whole area on the first click, dragging would spill into the neighbours
+        Some(tile) if held && painting && world.resource::<MapEditor>().brush != Brush::Fill => paint(world, tile),
+        _ => {}
+    }
+    if !held {
+        finish_stroke(world);
+    }
+
+    let Some(hovered) = hovered else {
+        world.resource_mut::<TileOverlay>().remove_layer(EDITOR_LAYER);
+        world.resource_mut::<TilePreview>().tiles.clear();
+        return;
+    };
+    let editor = world.resource::<MapEditor>();
+    let tiles = brush_tiles(world.resource::<Map>(), editor.brush, editor.layer, hovered, editor.radius);
+    let preview = match (editor.layer, editor.is_painting()) {
+        (EditorLayer::Terrain, false) => tiles.iter().map(|tile| (*tile, editor.tile)).collect(),
+        _ => Vec::new(),
+    };
+    world.resource_mut::<TilePreview>().tiles = preview;
+    world.resource_mut::<TileOverlay>().set_layer(EDITOR_LAYER, tiles, BRUSH_COLOR);
+}
+
+/// Opens or closes the editor. Opening it puts the building tools away, closing it keeps the current stroke.
+pub fn set_active(world: &mut World, active: bool) {
+    if active {
+        world.resource_mut::<PlacementTool>().select(None);
+        world.resource_mut::<WallTool>().select(None);
+        let saved = world.get_resource::<UserStore>().map(|store| saved_maps(store.0.as_ref()));
+        if let Some(Ok(saved)) = saved {
+            world.resource_mut::<MapEditor>().saved_maps = saved;
+        }
+    } else {
+        finish_stroke(world);
+        world.resource_mut::<TileOverlay>().remove_layer(EDITOR_LAYER);
+        world.resource_mut::<TilePreview>().tiles.clear();
+    }
+    world.resource_mut::<MapEditor>().active = active;
+}
+
+fn run_request(world: &mut World) {
+    let Some(request) = world.resource_mut::<MapEditor>().request.take() else {
+        return;
+    };
+    let status = world.resource_scope(|world, store: Mut<UserStore>| {
+        let store = store.0.as_ref();
+        match request {
+            EditorRequest::Save => {
+                let name = world.resource::<MapEditor>().map_name.trim().to_string();
+                let result = save_map(world, store, &name).map(|_| format!("saved {}", name));
+                if let Ok(saved) = saved_maps(store) {
+                    world.resource_mut::<MapEditor>().saved_maps = saved;
+                }
+                result
+            }
+            EditorRequest::Load(name) => {
+                finish_stroke(world);
+                load_map(world, store, &name).map(|_| format!("loaded {}", name))
+            }
+        }
+    });
+    world.resource_mut::<MapEditor>().status = Some(status);
+}
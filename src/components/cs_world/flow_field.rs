@@ -0,0 +1,255 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+use bevy_ecs::schedule::{IntoSystemConfig, Schedule};
+use bevy_ecs::system::{Res, ResMut, Resource};
+use bevy_ecs::world::World;
+use cgmath::{InnerSpace, Vector2};
+
+use crate::components::cs_world::pathfinding::{self, CostGrid, DIRECTIONS};
+use crate::components::cs_world::steering::{self, SteeringSettings};
+
+/// Stale fields rebuilt per update, the others keep guiding their units until it is their turn.
+const REBUILDS_PER_UPDATE: usize = 2;
+
+/// Direction of tiles that are a target or cannot reach one.
+const NO_DIRECTION: u8 = u8::MAX;
+
+/// Cost from every tile to the nearest of a set of targets and the neighbour to step onto to get there.
+/// One field guides any number of units, so armies heading to the same gate share it.
+#[derive(Debug, Clone)]
+pub struct FlowField {
+    size: Vector2<i32>,
+    /// Targets as requested, blocked ones start guiding units once they open up.
+    targets: Vec<Vector2<i32>>,
+    /// Cost to the nearest target, infinite for tiles that cannot reach one.
+    integration: Vec<f32>,
+    /// Index into `DIRECTIONS` of the next tile.
+    directions: Vec<u8>,
+    /// Version of the grid the field was computed for, `None` until it was computed once.
+    grid_version: Option<u32>,
+}
+
+impl FlowField {
+    /// Blocked targets and targets outside the grid are ignored.
+    pub fn new(grid: &CostGrid, targets: &[Vector2<i32>]) -> Self {
+        let mut field = Self::unbuilt(grid, targets);
+        field.integrate(grid);
+        field
+    }
+
+    /// A field no tile can reach a target on yet, it is computed once it is stale.
+    fn unbuilt(grid: &CostGrid, targets: &[Vector2<i32>]) -> Self {
+        let size = grid.size();
+        Self {
+            size,
+            targets: targets.to_vec(),
+            integration: vec![f32::INFINITY; (size.x * size.y) as usize],
+            directions: vec![NO_DIRECTION; (size.x * size.y) as usize],
+            grid_version: None,
+        }
+    }
+
+    /// Dijkstra outwards from the targets, a tile points at the neighbour it was reached from.
+    fn integrate(&mut self, grid: &CostGrid) {
+        self.integration.fill(f32::INFINITY);
+        self.directions.fill(NO_DIRECTION);
+        self.grid_version = Some(grid.version());
+        let mut open = BinaryHeap::new();
+        for target in self.targets.iter().filter(|target| grid.is_passable(**target)) {
+            let index = grid.index(*target);
+            self.integration[index] = 0.0;
+            open.push(Frontier { index, cost: 0.0 });
+        }
+        while let Some(Frontier { index, cost }) = open.pop() {
+            if cost > self.integration[index] {
+                continue;
+            }
+            let pos = grid.position(index);
+            for (direction, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+                // the step from the neighbour onto this tile, so corner rules apply from its side
+                let neighbour = pos - Vector2::new(*dx, *dy);
+                if !grid.is_passable(neighbour) {
+                    continue;
+                }
+                let Some(step) = grid.step_cost(neighbour, (*dx, *dy)) else {
+                    continue;
+                };
+                let neighbour_index = grid.index(neighbour);
+                let neighbour_cost = cost + step;
+                if neighbour_cost < self.integration[neighbour_index] {
+                    self.integration[neighbour_index] = neighbour_cost;
+                    self.directions[neighbour_index] = direction as u8;
+                    open.push(Frontier { index: neighbour_index, cost: neighbour_cost });
+                }
+            }
+        }
+    }
+
+    pub fn targets(&self) -> &[Vector2<i32>] {
+        &self.targets
+    }
+
+    /// Whether the grid changed since the field was computed or it was not computed yet.
+    pub fn is_stale(&self, grid: &CostGrid) -> bool {
+        self.grid_version != Some(grid.version())
+    }
+
+    fn index(&self, pos: Vector2<i32>) -> Option<usize> {
+        let inside = pos.x >= 0 && pos.y >= 0 && pos.x < self.size.x && pos.y < self.size.y;
+        inside.then(|| (pos.y * self.size.x + pos.x) as usize)
+    }
+
+    /// Cost of walking from `pos` to the nearest target, `None` if no target can be reached.
+    pub fn cost(&self, pos: Vector2<i32>) -> Option<f32> {
+        self.index(pos).map(|index| self.integration[index]).filter(|cost| cost.is_finite())
+    }
+
+    pub fn is_target(&self, pos: Vector2<i32>) -> bool {
+        self.cost(pos) == Some(0.0)
+    }
+
+    /// The neighbour to step onto, `None` on targets and tiles that cannot reach one.
+    pub fn next_tile(&self, pos: Vector2<i32>) -> Option<Vector2<i32>> {
+        let direction = self.directions[self.index(pos)?];
+        let (dx, dy) = DIRECTIONS.get(direction as usize)?;
+        Some(pos + Vector2::new(*dx, *dy))
+    }
+
+    /// Offset from a position in tile space to the centre of the tile to head for, which is the
+    /// centre of the current tile once it is a target. `None` if no target can be reached.
+    pub fn steer(&self, position: Vector2<f32>) -> Option<Vector2<f32>> {
+        let tile = Vector2::new(position.x.floor() as i32, position.y.floor() as i32);
+        self.cost(tile)?;
+        let next = self.next_tile(tile).unwrap_or(tile);
+        Some(Vector2::new(next.x as f32 + 0.5, next.y as f32 + 0.5) - position)
+    }
+
+    /// Unit direction of `steer`, zero where there is nothing left to do.
+    pub fn direction(&self, position: Vector2<f32>) -> Vector2<f32> {
+        match self.steer(position) {
+            Some(offset) if offset.magnitude2() > f32::EPSILON => offset.normalize(),
+            _ => Vector2::new(0.0, 0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Frontier {
+    index: usize,
+    cost: f32,
+}
+
+impl PartialEq for Frontier {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Frontier {}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlowFieldId(u32);
+
+#[derive(Debug)]
+struct SharedField {
+    field: FlowField,
+    users: usize,
+}
+
+/// Flow fields in use, requests for the same targets share one field until every user released it.
+#[derive(Debug, Default, Resource)]
+pub struct FlowFields {
+    next_id: u32,
+    fields: HashMap<FlowFieldId, SharedField>,
+    /// Order the fields are checked in by `rebuild_stale`, fields that waited longest come first.
+    rebuild_order: VecDeque<FlowFieldId>,
+}
+
+impl FlowFields {
+    /// Inserts the fields and the steering settings, `steer_flow_followers` moves the units after the fields are rebuilt.
+    pub fn register(self, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(self);
+        world.insert_resource(SteeringSettings::default());
+        schedule.add_system(rebuild_flow_fields.after(pathfinding::update_cost_grid));
+        schedule.add_system(steering::steer_flow_followers.after(rebuild_flow_fields));
+    }
+
+    /// Field towards the nearest of `targets`, computed by the next `rebuild_stale` unless another user asked for
+    /// the same targets. Units following it wait until then.
+    pub fn request(&mut self, grid: &CostGrid, targets: &[Vector2<i32>]) -> FlowFieldId {
+        let mut targets = targets.to_vec();
+        targets.sort_by_key(|target| (target.y, target.x));
+        targets.dedup();
+        let shared = self.fields.iter_mut().find(|(_, shared)| shared.field.targets == targets);
+        if let Some((id, shared)) = shared {
+            shared.users += 1;
+            return *id;
+        }
+        let id = FlowFieldId(self.next_id);
+        self.next_id += 1;
+        self.fields.insert(id, SharedField { field: FlowField::unbuilt(grid, &targets), users: 1 });
+        self.rebuild_order.push_back(id);
+        id
+    }
+
+    /// Drops the field once the last user released it.
+    pub fn release(&mut self, id: FlowFieldId) {
+        if let Some(shared) = self.fields.get_mut(&id) {
+            shared.users -= 1;
+            if shared.users == 0 {
+                self.fields.remove(&id);
+                self.rebuild_order.retain(|other| *other != id);
+            }
+        }
+    }
+
+    pub fn get(&self, id: FlowFieldId) -> Option<&FlowField> {
+        self.fields.get(&id).map(|shared| &shared.field)
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Recomputes at most `limit` fields the grid changed under, returns how many were rebuilt. Checked fields
+    /// go to the back of the order, so on a grid that keeps changing every field gets its turn.
+    pub fn rebuild_stale(&mut self, grid: &CostGrid, limit: usize) -> usize {
+        let mut rebuilt = 0;
+        for _ in 0..self.rebuild_order.len() {
+            if rebuilt == limit {
+                break;
+            }
+            let Some(id) = self.rebuild_order.pop_front() else {
+                break;
+            };
+            let field = &mut self.fields.get_mut(&id).unwrap().field;
+            if field.is_stale(grid) {
+                field.integrate(grid);
+                rebuilt += 1;
+            }
+            self.rebuild_order.push_back(id);
+        }
+        rebuilt
+    }
+}
+
+pub fn rebuild_flow_fields(mut flow_fields: ResMut<FlowFields>, grid: Res<CostGrid>) {
+    flow_fields.rebuild_stale(&grid, REBUILDS_PER_UPDATE);
+}
@@ -0,0 +1,154 @@
+use bevy_ecs::component::Component;
+use bevy_ecs::schedule::Schedule;
+use bevy_ecs::system::{Query, ResMut, Resource};
+use bevy_ecs::world::World;
+use cgmath::Vector2;
+
+use crate::components::cs_world::position::TilePosition;
+
+pub type PlayerId = u8;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileVisibility {
+    Unexplored = 0,
+    Explored = 1,
+    Visible = 2,
+}
+
+/// Lets the owning player see every tile within `radius` tiles, used by units and buildings.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct VisionSource {
+    pub player: PlayerId,
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct FogOfWar {
+    size: Vector2<i32>,
+    /// One byte per tile and player, values of `TileVisibility`.
+    players: Vec<Vec<u8>>,
+    /// Indices of the tiles each player currently sees.
+    visible: Vec<Vec<usize>>,
+    /// Player whose view is rendered.
+    pub active_player: PlayerId,
+    /// Tile states of the active player as last handed to the renderer, empty before the first upload.
+    uploaded: Vec<u8>,
+    /// Tiles of the active player that may differ from `uploaded`.
+    dirty: Vec<usize>,
+}
+
+impl FogOfWar {
+    pub fn new(size: Vector2<i32>, player_count: usize) -> Self {
+        Self {
+            size,
+            players: vec![vec![TileVisibility::Unexplored as u8; (size.x * size.y) as usize]; player_count],
+            visible: vec![Vec::new(); player_count],
+            active_player: 0,
+            uploaded: Vec::new(),
+            dirty: Vec::new(),
+        }
+    }
+
+    /// Inserts the fog and the system applying the vision sources every tick.
+    pub fn register(self, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(self);
+        schedule.add_system(update_fog_of_war);
+    }
+
+    /// `None` if the player does not exist, tiles outside the map are unexplored.
+    pub fn visibility(&self, player: PlayerId, pos: Vector2<i32>) -> Option<TileVisibility> {
+        let tiles = self.players.get(player as usize)?;
+        if pos.x < 0 || pos.y < 0 || pos.x >= self.size.x || pos.y >= self.size.y {
+            return Some(TileVisibility::Unexplored);
+        }
+        Some(match tiles[(pos.y * self.size.x + pos.x) as usize] {
+            2 => TileVisibility::Visible,
+            1 => TileVisibility::Explored,
+            _ => TileVisibility::Unexplored,
+        })
+    }
+
+    /// Turns every currently visible tile into an explored one, called before the vision sources are applied again.
+    pub fn begin_update(&mut self) {
+        for (player, visible) in self.visible.iter_mut().enumerate() {
+            for index in visible.drain(..) {
+                self.players[player][index] = TileVisibility::Explored as u8;
+                if player == self.active_player as usize {
+                    self.dirty.push(index);
+                }
+            }
+        }
+    }
+
+    /// Makes the tiles within `radius` of `center` visible, unknown players are ignored.
+    pub fn reveal(&mut self, player: PlayerId, center: Vector2<f32>, radius: f32) {
+        let Some(tiles) = self.players.get_mut(player as usize) else {
+            return;
+        };
+        let min = Vector2::new((center.x - radius).floor().max(0.0) as i32, (center.y - radius).floor().max(0.0) as i32);
+        let max = Vector2::new(
+            ((center.x + radius).ceil() as i32).min(self.size.x - 1),
+            ((center.y + radius).ceil() as i32).min(self.size.y - 1),
+        );
+        let radius_squared = radius * radius;
+        let active = player == self.active_player;
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let dx = x as f32 + 0.5 - center.x;
+                let dy = y as f32 + 0.5 - center.y;
+                let index = (y * self.size.x + x) as usize;
+                if dx * dx + dy * dy > radius_squared || tiles[index] == TileVisibility::Visible as u8 {
+                    continue;
+                }
+                tiles[index] = TileVisibility::Visible as u8;
+                self.visible[player as usize].push(index);
+                if active {
+                    self.dirty.push(index);
+                }
+            }
+        }
+    }
+
+    /// Tile states of the active player that changed since the last call, as the byte offset of the first
+    /// changed tile and the tiles from there to the last changed one. Both ends are widened to whole
+    /// u32 words as the shader reads four tiles per word, only the end of the map can be shorter.
+    pub fn take_changed(&mut self) -> Option<(usize, &[u8])> {
+        let tiles = &self.players[self.active_player as usize];
+        if self.uploaded.is_empty() {
+            self.uploaded.clone_from(tiles);
+            self.dirty.clear();
+            return Some((0, tiles));
+        }
+        let mut changed: Option<(usize, usize)> = None;
+        for index in self.dirty.drain(..) {
+            if self.uploaded[index] != tiles[index] {
+                self.uploaded[index] = tiles[index];
+                changed = Some(match changed {
+                    Some((first, last)) => (first.min(index), last.max(index)),
+                    None => (index, index),
+                });
+            }
+        }
+        let (first, last) = changed?;
+        let start = first / 4 * 4;
+        let end = ((last / 4 + 1) * 4).min(tiles.len());
+        Some((start, &tiles[start..end]))
+    }
+
+    /// Renders the view of another player, unknown players are ignored.
+    pub fn set_active_player(&mut self, player: PlayerId) {
+        if (player as usize) < self.players.len() {
+            self.active_player = player;
+            self.uploaded.clear();
+            self.dirty.clear();
+        }
+    }
+}
+
+pub fn update_fog_of_war(mut fog_of_war: ResMut<FogOfWar>, vision_sources: Query<(&TilePosition, &VisionSource)>) {
+    fog_of_war.begin_update();
+    for (position, vision) in vision_sources.iter() {
+        fog_of_war.reveal(vision.player, position.0, vision.radius);
+    }
+}
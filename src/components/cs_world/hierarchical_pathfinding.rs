@@ -0,0 +1,622 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use bevy_ecs::event::EventReader;
+use bevy_ecs::schedule::{IntoSystemConfig, Schedule};
+use bevy_ecs::system::{Res, ResMut, Resource};
+use bevy_ecs::world::World;
+use cgmath::Vector2;
+
+use crate::components::cs_world::map::TilesChanged;
+use crate::components::cs_world::pathfinding::{self, CostGrid, DIRECTIONS, PathResult, PathSearch, TileBounds};
+
+/// Width and height of the chunks the map is split into.
+pub const CHUNK_SIZE: i32 = 16;
+/// Border openings get one portal per this many tiles, spread evenly.
+const TILES_PER_PORTAL: usize = 6;
+
+type NodeId = usize;
+
+/// A tile next to a chunk border that paths use to enter the neighbouring chunk.
+#[derive(Debug, Clone)]
+struct PortalNode {
+    pos: Vector2<i32>,
+    chunk: usize,
+    /// The node on the other side of the border and the cost of stepping onto it.
+    partner: (NodeId, f32),
+    /// Cheapest costs to the other nodes of the chunk without leaving it.
+    intra: Vec<(NodeId, f32)>,
+}
+
+/// Border between a chunk and its right or lower neighbour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Border {
+    Right(usize),
+    Down(usize),
+}
+
+/// Abstract graph of chunk portals over a `CostGrid`. Searches run on the portals and only
+/// the next hop of a path is refined into tiles, see `HierarchicalPath`.
+#[derive(Debug, Clone, Resource)]
+pub struct PortalGraph {
+    size: Vector2<i32>,
+    chunks: Vector2<i32>,
+    nodes: Vec<Option<PortalNode>>,
+    free_nodes: Vec<NodeId>,
+    chunk_nodes: Vec<Vec<NodeId>>,
+    border_nodes: HashMap<Border, Vec<NodeId>>,
+    /// Chunks with tiles that changed since the last `repair`.
+    dirty: HashSet<usize>,
+}
+
+impl PortalGraph {
+    pub fn build(grid: &CostGrid) -> Self {
+        let size = grid.size();
+        let chunks = Vector2::new((size.x + CHUNK_SIZE - 1) / CHUNK_SIZE, (size.y + CHUNK_SIZE - 1) / CHUNK_SIZE);
+        let mut graph = Self {
+            size,
+            chunks,
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            chunk_nodes: vec![Vec::new(); (chunks.x * chunks.y) as usize],
+            border_nodes: HashMap::new(),
+            dirty: HashSet::new(),
+        };
+        for chunk in 0..graph.chunk_nodes.len() {
+            // every border is owned by the chunk left of or above it, so it is built once
+            for border in graph.borders(chunk).into_iter().flatten() {
+                if graph.owns(chunk, border) {
+                    graph.rebuild_border(grid, border);
+                }
+            }
+        }
+        for chunk in 0..graph.chunk_nodes.len() {
+            graph.rebuild_intra(grid, chunk);
+        }
+        graph
+    }
+
+    pub fn register(self, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(self);
+        schedule.add_system(repair_portal_graph.after(pathfinding::update_cost_grid).before(pathfinding::run_path_jobs));
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() - self.free_nodes.len()
+    }
+
+    fn chunk_of(&self, pos: Vector2<i32>) -> usize {
+        ((pos.y / CHUNK_SIZE) * self.chunks.x + pos.x / CHUNK_SIZE) as usize
+    }
+
+    fn chunk_position(&self, chunk: usize) -> Vector2<i32> {
+        Vector2::new(chunk as i32 % self.chunks.x, chunk as i32 / self.chunks.x)
+    }
+
+    fn chunk_bounds(&self, chunk: usize) -> TileBounds {
+        chunk_bounds(self.size, self.chunk_position(chunk))
+    }
+
+    /// Right, lower, left and upper border of a chunk, `None` at the edge of the map.
+    fn borders(&self, chunk: usize) -> [Option<Border>; 4] {
+        let pos = self.chunk_position(chunk);
+        let index = |x: i32, y: i32| (y * self.chunks.x + x) as usize;
+        [
+            (pos.x + 1 < self.chunks.x).then_some(Border::Right(chunk)),
+            (pos.y + 1 < self.chunks.y).then_some(Border::Down(chunk)),
+            (pos.x > 0).then(|| Border::Right(index(pos.x - 1, pos.y))),
+            (pos.y > 0).then(|| Border::Down(index(pos.x, pos.y - 1))),
+        ]
+    }
+
+    fn owns(&self, chunk: usize, border: Border) -> bool {
+        matches!(border, Border::Right(owner) | Border::Down(owner) if owner == chunk)
+    }
+
+    /// Tile pairs across the border, the first tile of each pair is in the owning chunk.
+    fn border_pairs(&self, border: Border) -> Vec<(Vector2<i32>, Vector2<i32>)> {
+        let (chunk, step, along) = match border {
+            Border::Right(chunk) => (chunk, Vector2::new(1, 0), Vector2::new(0, 1)),
+            Border::Down(chunk) => (chunk, Vector2::new(0, 1), Vector2::new(1, 0)),
+        };
+        let bounds = self.chunk_bounds(chunk);
+        let first = match border {
+            Border::Right(_) => Vector2::new(bounds.max.x - 1, bounds.min.y),
+            Border::Down(_) => Vector2::new(bounds.min.x, bounds.max.y - 1),
+        };
+        let length = match border {
+            Border::Right(_) => bounds.max.y - bounds.min.y,
+            Border::Down(_) => bounds.max.x - bounds.min.x,
+        };
+        (0..length).map(|offset| {
+            let inside = first + along * offset;
+            (inside, inside + step)
+        }).collect()
+    }
+
+    fn add_node(&mut self, pos: Vector2<i32>) -> NodeId {
+        let chunk = self.chunk_of(pos);
+        let node = PortalNode { pos, chunk, partner: (0, 0.0), intra: Vec::new() };
+        let id = match self.free_nodes.pop() {
+            Some(id) => {
+                self.nodes[id] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.chunk_nodes[chunk].push(id);
+        id
+    }
+
+    fn node(&self, id: NodeId) -> &PortalNode {
+        self.nodes[id].as_ref().unwrap()
+    }
+
+    /// Replaces the portals of a border, wide openings get several so paths do not bend towards one point.
+    fn rebuild_border(&mut self, grid: &CostGrid, border: Border) {
+        for id in self.border_nodes.remove(&border).unwrap_or_default() {
+            let chunk = self.node(id).chunk;
+            self.chunk_nodes[chunk].retain(|node| *node != id);
+            self.nodes[id] = None;
+            self.free_nodes.push(id);
+        }
+
+        let pairs = self.border_pairs(border);
+        let mut openings = Vec::new();
+        let mut opening_start = None;
+        for (offset, (inside, outside)) in pairs.iter().enumerate() {
+            let open = grid.is_passable(*inside) && grid.is_passable(*outside);
+            match (open, opening_start) {
+                (true, None) => opening_start = Some(offset),
+                (false, Some(start)) => {
+                    openings.push(start..offset);
+                    opening_start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = opening_start {
+            openings.push(start..pairs.len());
+        }
+
+        let mut ids = Vec::new();
+        for opening in openings {
+            let count = opening.len().div_ceil(TILES_PER_PORTAL);
+            // the middle of each of `count` equal parts of the opening
+            let portals = (0..count).map(|part| opening.start + (2 * part + 1) * opening.len() / (2 * count));
+            for offset in portals {
+                let (inside, outside) = pairs[offset];
+                let inside_id = self.add_node(inside);
+                let outside_id = self.add_node(outside);
+                self.nodes[inside_id].as_mut().unwrap().partner = (outside_id, grid.cost(outside).unwrap());
+                self.nodes[outside_id].as_mut().unwrap().partner = (inside_id, grid.cost(inside).unwrap());
+                ids.extend([inside_id, outside_id]);
+            }
+        }
+        self.border_nodes.insert(border, ids);
+    }
+
+    fn rebuild_intra(&mut self, grid: &CostGrid, chunk: usize) {
+        let bounds = self.chunk_bounds(chunk);
+        let ids = self.chunk_nodes[chunk].clone();
+        for id in ids.iter() {
+            let costs = ChunkCosts::search(grid, bounds, self.node(*id).pos, false);
+            let intra = ids.iter()
+                .filter(|other| *other != id)
+                .filter_map(|other| costs.get(self.node(*other).pos).map(|cost| (*other, cost)))
+                .collect();
+            self.nodes[*id].as_mut().unwrap().intra = intra;
+        }
+    }
+
+    /// Remembers that the cost of a tile changed, the graph is updated by the next `repair`.
+    pub fn mark_changed(&mut self, pos: Vector2<i32>) {
+        if pos.x >= 0 && pos.y >= 0 && pos.x < self.size.x && pos.y < self.size.y {
+            self.dirty.insert(self.chunk_of(pos));
+        }
+    }
+
+    pub fn needs_repair(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Rebuilds the borders of all changed chunks and the portal costs inside them and their neighbours.
+    pub fn repair(&mut self, grid: &CostGrid) {
+        let dirty: Vec<usize> = self.dirty.drain().collect();
+        let mut borders = HashSet::new();
+        let mut chunks = HashSet::new();
+        for chunk in dirty {
+            chunks.insert(chunk);
+            for border in self.borders(chunk).into_iter().flatten() {
+                borders.insert(border);
+                // both chunks at a rebuilt border get new portals
+                let (owner, neighbour) = match border {
+                    Border::Right(owner) => (owner, owner + 1),
+                    Border::Down(owner) => (owner, owner + self.chunks.x as usize),
+                };
+                chunks.insert(owner);
+                chunks.insert(neighbour);
+            }
+        }
+        for border in borders {
+            self.rebuild_border(grid, border);
+        }
+        for chunk in chunks {
+            self.rebuild_intra(grid, chunk);
+        }
+    }
+
+    /// Plans a path over the portals, `None` if the goal cannot be reached.
+    pub fn find_path(&self, grid: &CostGrid, start: Vector2<i32>, goal: Vector2<i32>) -> Option<HierarchicalPath> {
+        let mut search = self.search(grid, start, goal);
+        search.step(self, grid, usize::MAX);
+        match search.take_result() {
+            Some(PathResult::Portals(path)) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// A plan over the portals that is worked on in steps like a `PathSearch`.
+    pub fn search(&self, grid: &CostGrid, start: Vector2<i32>, goal: Vector2<i32>) -> PortalSearch {
+        let start_id = self.nodes.len();
+        let mut search = PortalSearch {
+            start,
+            goal,
+            grid_version: grid.version(),
+            region: None,
+            start_costs: None,
+            goal_costs: None,
+            start_id,
+            goal_id: start_id + 1,
+            open: BinaryHeap::new(),
+            reached: HashMap::new(),
+            result: None,
+        };
+        if !grid.in_bounds(start) || !grid.is_passable(goal) {
+            search.result = Some(PathResult::NoPath);
+            return search;
+        }
+        let start_chunk = self.chunk_of(start);
+        let goal_chunk = self.chunk_of(goal);
+        // portals are a poor fit for short paths, nearby goals are searched directly around both chunks
+        let (start_position, goal_position) = (self.chunk_position(start_chunk), self.chunk_position(goal_chunk));
+        if (start_position.x - goal_position.x).abs() <= 1 && (start_position.y - goal_position.y).abs() <= 1 {
+            let min = Vector2::new(start_position.x.min(goal_position.x) - 1, start_position.y.min(goal_position.y) - 1);
+            let max = Vector2::new(start_position.x.max(goal_position.x) + 2, start_position.y.max(goal_position.y) + 2);
+            let region = TileBounds {
+                min: Vector2::new((min.x * CHUNK_SIZE).max(0), (min.y * CHUNK_SIZE).max(0)),
+                max: Vector2::new((max.x * CHUNK_SIZE).min(self.size.x), (max.y * CHUNK_SIZE).min(self.size.y)),
+            };
+            search.region = Some((PathSearch::within(grid, start, goal, region), region));
+        }
+        search.start_costs = Some(ChunkCosts::new(self.chunk_bounds(start_chunk), start, false));
+        search.goal_costs = Some(ChunkCosts::new(self.chunk_bounds(goal_chunk), goal, true));
+        search.open.push(Candidate { id: start_id, cost: 0.0, estimate: grid.heuristic(start, goal) });
+        search.reached.insert(start_id, (0.0, start_id));
+        search
+    }
+}
+
+/// Plan over the portals of a `PortalGraph`, paused after a number of node expansions and continued later.
+/// Plans take a few steps at most, so any change to the grid simply starts them again.
+#[derive(Debug, Clone)]
+pub struct PortalSearch {
+    start: Vector2<i32>,
+    goal: Vector2<i32>,
+    grid_version: u32,
+    /// Search around both chunks for nearby goals, dropped if it finds no path.
+    region: Option<(PathSearch, TileBounds)>,
+    start_costs: Option<ChunkCosts>,
+    goal_costs: Option<ChunkCosts>,
+    start_id: NodeId,
+    goal_id: NodeId,
+    open: BinaryHeap<Candidate>,
+    /// Best known cost and predecessor of every reached node.
+    reached: HashMap<NodeId, (f32, NodeId)>,
+    result: Option<PathResult>,
+}
+
+impl PortalSearch {
+    pub fn start(&self) -> Vector2<i32> {
+        self.start
+    }
+
+    pub fn goal(&self) -> Vector2<i32> {
+        self.goal
+    }
+
+    /// `None` while the plan is running, a found plan is `PathResult::Portals`.
+    pub fn result(&self) -> Option<&PathResult> {
+        self.result.as_ref()
+    }
+
+    pub(crate) fn take_result(&mut self) -> Option<PathResult> {
+        self.result.take()
+    }
+
+    /// Expands up to `budget` tiles and portals, returns the work used. `graph` must be the graph the
+    /// plan was started on, repaired along with `grid`.
+    pub fn step(&mut self, graph: &PortalGraph, grid: &CostGrid, budget: usize) -> usize {
+        if self.result.is_some() {
+            return 0;
+        }
+        if self.grid_version != grid.version() {
+            *self = graph.search(grid, self.start, self.goal);
+        }
+        let mut used = 0;
+        if let Some((search, region)) = &mut self.region {
+            used += search.step(grid, budget);
+            match search.result() {
+                None => return used,
+                Some(PathResult::Found(path)) => {
+                    let path = HierarchicalPath { hops: vec![self.start, self.goal], next_hop: 1, cost: path.cost, region: Some(*region) };
+                    self.result = Some(PathResult::Portals(path));
+                    return used;
+                }
+                Some(_) => self.region = None,
+            }
+        }
+        let (Some(start_costs), Some(goal_costs)) = (&mut self.start_costs, &mut self.goal_costs) else {
+            return used;
+        };
+        used += start_costs.step(grid, budget.saturating_sub(used));
+        used += goal_costs.step(grid, budget.saturating_sub(used));
+        if !start_costs.is_finished() || !goal_costs.is_finished() {
+            return used;
+        }
+
+        let (start, goal, start_id, goal_id) = (self.start, self.goal, self.start_id, self.goal_id);
+        let (start_chunk, goal_chunk) = (graph.chunk_of(start), graph.chunk_of(goal));
+        let position = |id: NodeId| match id {
+            id if id == start_id => start,
+            id if id == goal_id => goal,
+            id => graph.node(id).pos,
+        };
+        while used < budget {
+            let Some(candidate) = self.open.pop() else {
+                self.result = Some(PathResult::NoPath);
+                break;
+            };
+            used += 1;
+            if self.reached[&candidate.id].0 < candidate.cost {
+                continue;
+            }
+            if candidate.id == goal_id {
+                let mut hops = vec![goal];
+                let mut id = goal_id;
+                while id != start_id {
+                    id = self.reached[&id].1;
+                    hops.push(position(id));
+                }
+                hops.reverse();
+                hops.dedup();
+                self.result = Some(PathResult::Portals(HierarchicalPath::new(hops, candidate.cost)));
+                break;
+            }
+
+            let mut edges: Vec<(NodeId, f32)> = Vec::new();
+            if candidate.id == start_id {
+                edges.extend(graph.chunk_nodes[start_chunk].iter()
+                    .filter_map(|id| start_costs.get(graph.node(*id).pos).map(|cost| (*id, cost))));
+            } else {
+                let node = graph.node(candidate.id);
+                edges.push(node.partner);
+                edges.extend(node.intra.iter().copied());
+                if node.chunk == goal_chunk {
+                    if let Some(cost) = goal_costs.get(node.pos) {
+                        edges.push((goal_id, cost));
+                    }
+                }
+            }
+            for (next, edge_cost) in edges {
+                let cost = candidate.cost + edge_cost;
+                if self.reached.get(&next).is_some_and(|(known, _)| *known <= cost) {
+                    continue;
+                }
+                self.reached.insert(next, (cost, candidate.id));
+                self.open.push(Candidate { id: next, cost, estimate: cost + grid.heuristic(position(next), goal) });
+            }
+        }
+        used
+    }
+}
+
+fn chunk_bounds(size: Vector2<i32>, chunk: Vector2<i32>) -> TileBounds {
+    let min = chunk * CHUNK_SIZE;
+    TileBounds {
+        min,
+        max: Vector2::new((min.x + CHUNK_SIZE).min(size.x), (min.y + CHUNK_SIZE).min(size.y)),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    id: usize,
+    cost: f32,
+    estimate: f32,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+/// Cheapest costs from one tile to every tile of a chunk without leaving it.
+#[derive(Debug, Clone)]
+struct ChunkCosts {
+    bounds: TileBounds,
+    costs: Vec<f32>,
+    /// With `reverse` the costs are those of walking from each tile to the start instead.
+    reverse: bool,
+    open: BinaryHeap<Candidate>,
+}
+
+impl ChunkCosts {
+    fn new(bounds: TileBounds, from: Vector2<i32>, reverse: bool) -> Self {
+        let width = bounds.max.x - bounds.min.x;
+        let height = bounds.max.y - bounds.min.y;
+        let mut costs = Self { bounds, costs: vec![f32::INFINITY; (width * height) as usize], reverse, open: BinaryHeap::new() };
+        let index = costs.local(from);
+        costs.costs[index] = 0.0;
+        costs.open.push(Candidate { id: index, cost: 0.0, estimate: 0.0 });
+        costs
+    }
+
+    fn search(grid: &CostGrid, bounds: TileBounds, from: Vector2<i32>, reverse: bool) -> Self {
+        let mut costs = Self::new(bounds, from, reverse);
+        costs.step(grid, usize::MAX);
+        costs
+    }
+
+    fn is_finished(&self) -> bool {
+        self.open.is_empty()
+    }
+
+    fn local(&self, pos: Vector2<i32>) -> usize {
+        let width = self.bounds.max.x - self.bounds.min.x;
+        ((pos.y - self.bounds.min.y) * width + pos.x - self.bounds.min.x) as usize
+    }
+
+    /// Expands up to `budget` tiles, returns how many were.
+    fn step(&mut self, grid: &CostGrid, budget: usize) -> usize {
+        let width = self.bounds.max.x - self.bounds.min.x;
+        let mut used = 0;
+        while used < budget {
+            let Some(candidate) = self.open.pop() else {
+                break;
+            };
+            used += 1;
+            if self.costs[candidate.id] < candidate.cost {
+                continue;
+            }
+            let pos = self.bounds.min + Vector2::new(candidate.id as i32 % width, candidate.id as i32 / width);
+            for direction in DIRECTIONS {
+                let next = pos + Vector2::new(direction.0, direction.1);
+                if !self.bounds.contains(next) {
+                    continue;
+                }
+                let Some(step_cost) = grid.step_cost(pos, direction) else {
+                    continue;
+                };
+                // walking backwards the step ends on `pos`, so its cost is paid instead
+                let step_cost = match self.reverse {
+                    true => {
+                        let length = if direction.0 != 0 && direction.1 != 0 { std::f32::consts::SQRT_2 } else { 1.0 };
+                        grid.cost(pos).unwrap_or(f32::INFINITY) * length
+                    }
+                    false => step_cost,
+                };
+                let cost = candidate.cost + step_cost;
+                let index = self.local(next);
+                if cost < self.costs[index] {
+                    self.costs[index] = cost;
+                    self.open.push(Candidate { id: index, cost, estimate: cost });
+                }
+            }
+        }
+        used
+    }
+
+    fn get(&self, pos: Vector2<i32>) -> Option<f32> {
+        let cost = self.costs[self.local(pos)];
+        cost.is_finite().then_some(cost)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Refinement {
+    /// Waypoints up to and including the next hop.
+    Segment(Vec<Vector2<i32>>),
+    /// The map changed and the next hop cannot be reached anymore, the path has to be planned again.
+    Blocked,
+    Finished,
+}
+
+/// Path over portals, each hop is turned into tiles only when a unit gets there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HierarchicalPath {
+    hops: Vec<Vector2<i32>>,
+    next_hop: usize,
+    cost: f32,
+    /// Set for short paths that were searched directly, the only hop is refined within it.
+    region: Option<TileBounds>,
+}
+
+impl HierarchicalPath {
+    fn new(hops: Vec<Vector2<i32>>, cost: f32) -> Self {
+        Self { hops, next_hop: 1, cost, region: None }
+    }
+
+    /// Start, portal tiles and goal.
+    pub fn hops(&self) -> &[Vector2<i32>] {
+        &self.hops
+    }
+
+    /// Cost of the abstract path, refinement stays within the costs the portals were planned with.
+    pub fn cost(&self) -> f32 {
+        self.cost
+    }
+
+    pub fn refine_next(&mut self, grid: &CostGrid) -> Refinement {
+        let Some(to) = self.hops.get(self.next_hop).copied() else {
+            return Refinement::Finished;
+        };
+        let from = self.hops[self.next_hop - 1];
+        self.next_hop += 1;
+        let from_chunk = Vector2::new(from.x / CHUNK_SIZE, from.y / CHUNK_SIZE);
+        let to_chunk = Vector2::new(to.x / CHUNK_SIZE, to.y / CHUNK_SIZE);
+        // a hop into the next chunk is a single step across the border
+        if from_chunk != to_chunk && self.region.is_none() {
+            return match grid.is_passable(to) {
+                true => Refinement::Segment(vec![to]),
+                false => Refinement::Blocked,
+            };
+        }
+        let bounds = self.region.unwrap_or_else(|| chunk_bounds(grid.size(), from_chunk));
+        let mut search = PathSearch::within(grid, from, to, bounds);
+        search.step(grid, usize::MAX);
+        match search.result() {
+            Some(PathResult::Found(path)) => Refinement::Segment(path.waypoints[1..].to_vec()),
+            _ => Refinement::Blocked,
+        }
+    }
+
+    /// Refines the remaining hops at once, the first waypoint is the start.
+    pub fn refine_all(mut self, grid: &CostGrid) -> Option<Vec<Vector2<i32>>> {
+        let mut waypoints = vec![self.hops[self.next_hop - 1]];
+        loop {
+            match self.refine_next(grid) {
+                Refinement::Segment(segment) => waypoints.extend(segment),
+                Refinement::Blocked => return None,
+                Refinement::Finished => return Some(waypoints),
+            }
+        }
+    }
+}
+
+pub fn repair_portal_graph(mut graph: ResMut<PortalGraph>, grid: Res<CostGrid>, mut tiles_changed: EventReader<TilesChanged>) {
+    for index in tiles_changed.iter().flat_map(|event| event.indices.iter()) {
+        graph.mark_changed(grid.position(*index));
+    }
+    if graph.needs_repair() {
+        graph.repair(&grid);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+
+use bevy_ecs::schedule::{IntoSystemConfig, Schedule};
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::{Mut, World};
+use winit::event::VirtualKeyCode;
+
+use crate::components::cs_ui::ui_layer::UiFocus;
+use crate::components::cs_util::input::Input;
+use crate::components::cs_world::building::{self, Building, Occupancy};
+use crate::components::cs_world::map::{self, Map, MapEdit, TileEdit};
+use crate::components::cs_world::tile_registry::TileRegistry;
+
+/// Edits kept for undo, the oldest are dropped first.
+pub const HISTORY_LIMIT: usize = 100;
+
+/// A world edit that can be taken back. Commands hold everything `revert` needs,
+/// so applying and reverting them in turn always gives the same world.
+pub trait Command: Send + Sync + 'static {
+    fn apply(&mut self, world: &mut World);
+    fn revert(&mut self, world: &mut World);
+}
+
+impl Command for TileEdit {
+    fn apply(&mut self, world: &mut World) {
+        world.resource_scope(|world, mut map: Mut<Map>| TileEdit::apply(self, &mut map, world.resource::<TileRegistry>()));
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        world.resource_scope(|world, mut map: Mut<Map>| TileEdit::revert(self, &mut map, world.resource::<TileRegistry>()));
+    }
+}
+
+impl Command for MapEdit {
+    fn apply(&mut self, world: &mut World) {
+        world.resource_scope(|world, mut map: Mut<Map>| MapEdit::apply(self, &mut map, world.resource::<TileRegistry>()));
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        world.resource_scope(|world, mut map: Mut<Map>| MapEdit::revert(self, &mut map, world.resource::<TileRegistry>()));
+    }
+}
+
+/// Places a building whose footprint was checked, buildings are found again by their origin tile
+/// because undoing a demolish spawns a new entity.
+pub struct PlaceBuilding(pub Building);
+
+impl Command for PlaceBuilding {
+    fn apply(&mut self, world: &mut World) {
+        building::spawn_building(world, self.0);
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        remove_building_at(world, self.0);
+    }
+}
+
+pub struct DemolishBuilding(pub Building);
+
+impl Command for DemolishBuilding {
+    fn apply(&mut self, world: &mut World) {
+        remove_building_at(world, self.0);
+    }
+
+    fn revert(&mut self, world: &mut World) {
+        building::spawn_building(world, self.0);
+    }
+}
+
+fn remove_building_at(world: &mut World, building: Building) {
+    if let Some(entity) = world.resource::<Occupancy>().get(building.origin) {
+        building::demolish_building(world, entity);
+    }
+}
+
+struct Entry {
+    label: String,
+    command: Box<dyn Command>,
+}
+
+/// Applied commands that can be undone and undone commands that can be redone.
+#[derive(Resource)]
+pub struct History {
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(HISTORY_LIMIT)
+    }
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self { undo: VecDeque::new(), redo: Vec::new(), limit }
+    }
+
+    /// Inserts the history with the Ctrl+Z, Ctrl+Y and Ctrl+Shift+Z bindings.
+    pub fn register(self, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(self);
+        schedule.add_system(undo_redo_keys.before(map::publish_tile_changes));
+    }
+
+    /// Label of the command the next undo takes back.
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.back().map(|entry| entry.label.as_str())
+    }
+
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo.last().map(|entry| entry.label.as_str())
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn push(&mut self, entry: Entry) {
+        self.redo.clear();
+        self.undo.push_back(entry);
+        while self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+    }
+}
+
+/// Applies the command and records it, anything that was undone can no longer be redone.
+pub fn execute(world: &mut World, label: impl Into<String>, mut command: impl Command) {
+    command.apply(world);
+    world.resource_mut::<History>().push(Entry { label: label.into(), command: Box::new(command) });
+}
+
+/// Records a command whose changes are already in the world, like a brush stroke painted tile by tile.
+pub fn record(world: &mut World, label: impl Into<String>, command: impl Command) {
+    world.resource_mut::<History>().push(Entry { label: label.into(), command: Box::new(command) });
+}
+
+/// Reverts the last applied command, returns false if there is none.
+pub fn undo(world: &mut World) -> bool {
+    let Some(mut entry) = world.resource_mut::<History>().undo.pop_back() else {
+        return false;
+    };
+    entry.command.revert(world);
+    world.resource_mut::<History>().redo.push(entry);
+    true
+}
+
+/// Applies the last undone command again, returns false if there is none.
+pub fn redo(world: &mut World) -> bool {
+    let Some(mut entry) = world.resource_mut::<History>().redo.pop() else {
+        return false;
+    };
+    entry.command.apply(world);
+    world.resource_mut::<History>().undo.push_back(entry);
+    true
+}
+
+pub fn undo_redo_keys(world: &mut World) {
+    if world.resource::<UiFocus>().keyboard {
+        return;
+    }
+    let keyboard_input = world.resource::<Input<VirtualKeyCode>>();
+    if !keyboard_input.any_pressed([VirtualKeyCode::LControl, VirtualKeyCode::RControl]) {
+        return;
+    }
+    let shift = keyboard_input.any_pressed([VirtualKeyCode::LShift, VirtualKeyCode::RShift]);
+    let z = keyboard_input.just_pressed(VirtualKeyCode::Z);
+    let y = keyboard_input.just_pressed(VirtualKeyCode::Y);
+    if z && !shift {
+        undo(world);
+    } else if y || (z && shift) {
+        redo(world);
+    }
+}
@@ -1,33 +1,416 @@
+use std::collections::HashMap;
+use std::iter;
+
+use bevy_ecs::event::{EventWriter, Events};
+use bevy_ecs::schedule::Schedule;
+use bevy_ecs::system::{ResMut, Resource};
+use bevy_ecs::world::World;
 use cgmath::Vector2;
-use rand::{Rng, thread_rng};
 
 use crate::components::cs_render::shader_types::tile_instance::{AtlasCoordinate, TileInstance};
+use crate::components::cs_world::autotile;
+use crate::components::cs_world::tile_registry::{TileKindId, TileRegistry};
 
 pub const SIZE: i32 = 200;
 pub const TILE_SIZE: Vector2<f32> = Vector2::new(32.0, 16.0);
 pub const TILE_SIZE_HALF: Vector2<f32> = Vector2::new(16.0, 8.0);
+pub const MAX_HEIGHT: u8 = 8;
+/// Pixels a tile is raised per height level.
+pub const HEIGHT_STEP: f32 = 4.0;
 
 
-pub(crate) struct Map {
-    pub(crate) tiles: Vec<TileInstance>,
+#[derive(Resource)]
+pub struct Map {
+    pub size: Vector2<i32>,
+    pub tiles: Vec<TileInstance>,
+    pub kinds: Vec<TileKindId>,
+    /// Height level of every tile, from 0 up to `MAX_HEIGHT`.
+    pub heights: Vec<u8>,
+    changed_tiles: Vec<usize>,
 }
 
-pub(crate) fn generate_instances() -> Map {
-    let mut rng = thread_rng();
-    let mut instances: Vec<TileInstance> = Vec::new();
-    for y in 0..SIZE {
-        for x in 0..SIZE {
-            let pos = map_to_screen_tile_pos(Vector2::new(x as f32, y as f32));
-            let z = (y as f32 * SIZE as f32 + x as f32) / (SIZE * SIZE) as f32;
-            instances.push(
-                TileInstance {
-                    position: [pos.x, pos.y, 1.0 - z],
-                    atlas_coordinate: AtlasCoordinate { coordinate: [rng.gen_range(0..28), 0], index: 0 },
-                }
-            );
+impl Map {
+    pub fn new(size: Vector2<i32>, kind: TileKindId, registry: &TileRegistry) -> Self {
+        let mut map = Self {
+            size,
+            tiles: Vec::with_capacity((size.x * size.y) as usize),
+            kinds: vec![kind; (size.x * size.y) as usize],
+            heights: vec![0; (size.x * size.y) as usize],
+            changed_tiles: Vec::new(),
+        };
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let pos = map_to_screen_tile_pos(Vector2::new(x as f32, y as f32));
+                let z = (y as f32 * size.x as f32 + x as f32) / (size.x * size.y) as f32;
+                map.tiles.push(
+                    TileInstance {
+                        position: [pos.x, pos.y, 1.0 - z],
+                        atlas_coordinate: AtlasCoordinate { coordinate: [0, 0], index: 0, animation: 0 },
+                    }
+                );
+            }
+        }
+        for y in 0..size.y {
+            for x in 0..size.x {
+                map.refresh_atlas_coordinate(Vector2::new(x, y), registry);
+            }
+        }
+        map.changed_tiles.clear();
+        map
+    }
+
+    pub fn in_bounds(&self, pos: Vector2<i32>) -> bool {
+        pos.x >= 0 && pos.y >= 0 && pos.x < self.size.x && pos.y < self.size.y
+    }
+
+    pub fn index(&self, pos: Vector2<i32>) -> usize {
+        (pos.y * self.size.x + pos.x) as usize
+    }
+
+    pub fn kind(&self, pos: Vector2<i32>) -> Option<TileKindId> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+        Some(self.kinds[self.index(pos)])
+    }
+
+    pub fn height(&self, pos: Vector2<i32>) -> Option<u8> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+        Some(self.heights[self.index(pos)])
+    }
+
+    /// Tile drawn at a world position. Raised tiles are drawn further up and hide the tiles behind them,
+    /// where no tile is drawn the flat tile under the position is returned.
+    pub fn pick_tile(&self, position: Vector2<f32>) -> Vector2<i32> {
+        (0..=MAX_HEIGHT)
+            .map(|height| (height, screen_to_map_tile(position + Vector2::new(0.0, height as f32 * HEIGHT_STEP))))
+            .filter(|(height, tile)| self.height(*tile) == Some(*height))
+            .max_by_key(|(_, tile)| tile.x + tile.y)
+            .map_or_else(|| screen_to_map_tile(position), |(_, tile)| tile)
+    }
+
+    /// Raises or lowers the tile, heights above `MAX_HEIGHT` are clamped.
+    pub fn set_height(&mut self, pos: Vector2<i32>, height: u8) {
+        if !self.in_bounds(pos) {
+            return;
+        }
+        let index = self.index(pos);
+        let height = height.min(MAX_HEIGHT);
+        if self.heights[index] == height {
+            return;
+        }
+        self.heights[index] = height;
+        let ground = map_to_screen_tile_pos(Vector2::new(pos.x as f32, pos.y as f32));
+        self.tiles[index].position[1] = ground.y - height as f32 * HEIGHT_STEP;
+        self.changed_tiles.push(index);
+    }
+
+    /// Changes the kind of a tile and re-evaluates the autotile variant of the tile and its neighbours.
+    pub fn set_tile(&mut self, pos: Vector2<i32>, kind: TileKindId, registry: &TileRegistry) {
+        if !self.in_bounds(pos) {
+            return;
+        }
+        let index = self.index(pos);
+        if self.kinds[index] == kind {
+            return;
+        }
+        self.kinds[index] = kind;
+        // published even if it looks the same, the movement cost may differ
+        self.changed_tiles.push(index);
+        self.refresh_atlas_coordinate(pos, registry);
+        for (_, offset) in autotile::NEIGHBOURS.iter() {
+            self.refresh_atlas_coordinate(pos + offset, registry);
+        }
+    }
+
+    /// Re-evaluates the look of every tile after the tile definitions changed, all tiles are published
+    /// as their movement costs and minimap colours may differ too.
+    pub fn refresh_tiles(&mut self, registry: &TileRegistry) {
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                self.refresh_atlas_coordinate(Vector2::new(x, y), registry);
+            }
+        }
+        self.changed_tiles = (0..self.tiles.len()).collect();
+    }
+
+    fn refresh_atlas_coordinate(&mut self, pos: Vector2<i32>, registry: &TileRegistry) {
+        let Some(kind) = self.kind(pos) else {
+            return;
+        };
+        let (coordinate, animation) = atlas_coordinate(pos, kind, |other| self.kind(other), registry);
+        let index = self.index(pos);
+        let atlas_coordinate = &mut self.tiles[index].atlas_coordinate;
+        if atlas_coordinate.coordinate != coordinate || atlas_coordinate.animation != animation {
+            atlas_coordinate.coordinate = coordinate;
+            atlas_coordinate.animation = animation;
+            self.changed_tiles.push(index);
         }
     }
-    Map { tiles: instances }
+
+    /// Instances of the given tiles and their neighbours as they would look with the new kinds, the map itself is not changed.
+    pub fn preview_tiles(&self, changes: &[(Vector2<i32>, TileKindId)], registry: &TileRegistry) -> Vec<(usize, TileInstance)> {
+        let overrides: HashMap<Vector2<i32>, TileKindId> = changes.iter().copied().collect();
+        let kind_at = |pos: Vector2<i32>| overrides.get(&pos).copied().or_else(|| self.kind(pos));
+        let mut affected: Vec<usize> = changes.iter()
+            .flat_map(|(pos, _)| iter::once(*pos).chain(autotile::NEIGHBOURS.iter().map(move |(_, offset)| pos + offset)))
+            .filter(|pos| self.in_bounds(*pos))
+            .map(|pos| self.index(pos))
+            .collect();
+        affected.sort_unstable();
+        affected.dedup();
+        affected.into_iter()
+            .map(|index| {
+                let pos = Vector2::new(index as i32 % self.size.x, index as i32 / self.size.x);
+                let (coordinate, animation) = atlas_coordinate(pos, kind_at(pos).unwrap(), kind_at, registry);
+                let mut instance = self.tiles[index];
+                instance.atlas_coordinate.coordinate = coordinate;
+                instance.atlas_coordinate.animation = animation;
+                (index, instance)
+            })
+            .collect()
+    }
+
+    /// Publishes a tile as changed without touching its kind, e.g. when a building starts or stops occupying it.
+    pub fn mark_changed(&mut self, pos: Vector2<i32>) {
+        if self.in_bounds(pos) {
+            let index = self.index(pos);
+            self.changed_tiles.push(index);
+        }
+    }
+
+    /// Returns the indices of all tiles that changed since the last call, sorted and without duplicates.
+    pub fn take_changed_tiles(&mut self) -> Vec<usize> {
+        let mut changed = std::mem::take(&mut self.changed_tiles);
+        changed.sort_unstable();
+        changed.dedup();
+        changed
+    }
+}
+
+/// Atlas cell and animation id of a tile of `kind` at `pos`, `kind_at` gives the kinds around it.
+fn atlas_coordinate(pos: Vector2<i32>, kind: TileKindId, kind_at: impl Fn(Vector2<i32>) -> Option<TileKindId>, registry: &TileRegistry) -> ([u8; 2], u8) {
+    let definition = registry.get(kind);
+    let coordinate = match &definition.autotile {
+        Some(rule) => {
+            let mask = autotile::neighbour_mask(rule.mode, |offset| {
+                kind_at(pos + offset).is_some_and(|other| definition.connects_with(registry.get(other)))
+            });
+            rule.atlas_coordinate(mask)
+        }
+        None => {
+            let variation = (position_hash(pos) % definition.variations.max(1) as u32) as u8;
+            [definition.atlas_coordinate[0] + variation, definition.atlas_coordinate[1]]
+        }
+    };
+    (coordinate, registry.animation_id(kind))
+}
+
+/// Sent once per update with the indices of all tiles changed through `Map::set_tile` or `Map::mark_changed`.
+#[derive(Debug, Clone)]
+pub struct TilesChanged {
+    pub indices: Vec<usize>,
+}
+
+/// One tile whose kind an edit changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileChange {
+    pub pos: Vector2<i32>,
+    pub before: TileKindId,
+    pub after: TileKindId,
+}
+
+/// Tile changes applied and reverted as a whole, e.g. every wall segment of one drag.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TileEdit {
+    pub changes: Vec<TileChange>,
+}
+
+impl TileEdit {
+    /// Records the current kinds of the tiles, tiles outside the map or already of the new kind are left out.
+    pub fn new(map: &Map, tiles: &[(Vector2<i32>, TileKindId)]) -> Self {
+        let mut changes = Vec::with_capacity(tiles.len());
+        let later = tiles.iter()
+            .filter_map(|(pos, kind)| map.kind(*pos).map(|before| TileChange { pos: *pos, before, after: *kind }));
+        merge_changes(&mut changes, later);
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn apply(&self, map: &mut Map, registry: &TileRegistry) {
+        for change in self.changes.iter() {
+            map.set_tile(change.pos, change.after, registry);
+        }
+    }
+
+    pub fn revert(&self, map: &mut Map, registry: &TileRegistry) {
+        for change in self.changes.iter().rev() {
+            map.set_tile(change.pos, change.before, registry);
+        }
+    }
+
+    /// Adds an edit made after this one, tiles both change keep the kind from before this edit.
+    pub fn extend(&mut self, later: TileEdit) {
+        merge_changes(&mut self.changes, later.changes);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeightChange {
+    pub pos: Vector2<i32>,
+    pub before: u8,
+    pub after: u8,
+}
+
+/// Height changes applied and reverted as a whole, like `TileEdit` for kinds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeightEdit {
+    pub changes: Vec<HeightChange>,
+}
+
+impl HeightEdit {
+    /// Records the current heights of the tiles, new heights are clamped to `MAX_HEIGHT`.
+    pub fn new(map: &Map, tiles: &[(Vector2<i32>, u8)]) -> Self {
+        let mut changes = Vec::with_capacity(tiles.len());
+        let later = tiles.iter()
+            .filter_map(|(pos, height)| map.height(*pos).map(|before| HeightChange { pos: *pos, before, after: (*height).min(MAX_HEIGHT) }));
+        merge_changes(&mut changes, later);
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn apply(&self, map: &mut Map) {
+        for change in self.changes.iter() {
+            map.set_height(change.pos, change.after);
+        }
+    }
+
+    pub fn revert(&self, map: &mut Map) {
+        for change in self.changes.iter().rev() {
+            map.set_height(change.pos, change.before);
+        }
+    }
+
+    pub fn extend(&mut self, later: HeightEdit) {
+        merge_changes(&mut self.changes, later.changes);
+    }
+}
+
+/// Lets tile and height edits merge their changes the same way.
+trait Change {
+    fn pos(&self) -> Vector2<i32>;
+    /// Takes the value `later` leaves the tile at.
+    fn follow(&mut self, later: &Self);
+    fn is_unchanged(&self) -> bool;
+}
+
+impl Change for TileChange {
+    fn pos(&self) -> Vector2<i32> {
+        self.pos
+    }
+
+    fn follow(&mut self, later: &Self) {
+        self.after = later.after;
+    }
+
+    fn is_unchanged(&self) -> bool {
+        self.before == self.after
+    }
+}
+
+impl Change for HeightChange {
+    fn pos(&self) -> Vector2<i32> {
+        self.pos
+    }
+
+    fn follow(&mut self, later: &Self) {
+        self.after = later.after;
+    }
+
+    fn is_unchanged(&self) -> bool {
+        self.before == self.after
+    }
+}
+
+/// Appends `later` to `changes`, a tile changed by both keeps its first `before` and its last `after`.
+/// Tiles that end up as they started are dropped.
+fn merge_changes<C: Change>(changes: &mut Vec<C>, later: impl IntoIterator<Item = C>) {
+    let mut positions: HashMap<Vector2<i32>, usize> = changes.iter().enumerate().map(|(index, change)| (change.pos(), index)).collect();
+    for change in later {
+        match positions.get(&change.pos()) {
+            Some(index) => changes[*index].follow(&change),
+            None => {
+                positions.insert(change.pos(), changes.len());
+                changes.push(change);
+            }
+        }
+    }
+    changes.retain(|change| !change.is_unchanged());
+}
+
+/// Kinds and heights changed together, e.g. by loading a map over the current one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MapEdit {
+    pub tiles: TileEdit,
+    pub heights: HeightEdit,
+}
+
+impl MapEdit {
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty() && self.heights.is_empty()
+    }
+
+    pub fn apply(&self, map: &mut Map, registry: &TileRegistry) {
+        self.tiles.apply(map, registry);
+        self.heights.apply(map);
+    }
+
+    pub fn revert(&self, map: &mut Map, registry: &TileRegistry) {
+        self.heights.revert(map);
+        self.tiles.revert(map, registry);
+    }
+}
+
+/// Tiles a tool would change, drawn in place of the map tiles until the tool clears them.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct TilePreview {
+    pub tiles: Vec<(Vector2<i32>, TileKindId)>,
+}
+
+/// Inserts the map as resource together with the systems that publish its changes.
+pub fn insert_map(map: Map, world: &mut World, schedule: &mut Schedule) {
+    world.insert_resource(map);
+    world.init_resource::<TilePreview>();
+    world.init_resource::<Events<TilesChanged>>();
+    schedule.add_system(Events::<TilesChanged>::update_system);
+    schedule.add_system(publish_tile_changes);
+}
+
+pub fn publish_tile_changes(mut map: ResMut<Map>, mut tiles_changed: EventWriter<TilesChanged>) {
+    let indices = map.take_changed_tiles();
+    if !indices.is_empty() {
+        tiles_changed.send(TilesChanged { indices });
+    }
+}
+
+pub fn generate_instances(registry: &TileRegistry) -> Map {
+    Map::new(Vector2::new(SIZE, SIZE), registry.id("grass").unwrap(), registry)
+}
+
+/// Cheap deterministic hash so tile variations do not change when a tile is repainted.
+fn position_hash(pos: Vector2<i32>) -> u32 {
+    let mut hash = (pos.x as u32).wrapping_mul(0x8da6_b343) ^ (pos.y as u32).wrapping_mul(0xd816_3841);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2c1b_3c6d);
+    hash ^ (hash >> 12)
 }
 
 
@@ -47,4 +430,22 @@ pub fn screen_to_map_pos(position: Vector2<f32>) -> Vector2<i32> {
     let x = (position.y / TILE_SIZE.y) + (position.x / TILE_SIZE.x);
     let y = (position.y / TILE_SIZE.y) - (position.x / TILE_SIZE.x);
     Vector2::new(x as i32, y as i32)
-}
\ No newline at end of file
+}
+
+/// Screen position of a point in tile space, where tile `n` covers `n..n + 1` as in `TilePosition`.
+pub fn position_to_screen(position: Vector2<f32>) -> Vector2<f32> {
+    map_to_screen_pos_centered(position - Vector2::new(0.5, 0.5))
+}
+
+/// Inverse of `map_to_screen_pos_centered` without rounding to a tile.
+pub fn screen_to_map_pos_f32(position: Vector2<f32>) -> Vector2<f32> {
+    let x = (position.y / TILE_SIZE.y) + (position.x / TILE_SIZE.x);
+    let y = (position.y / TILE_SIZE.y) - (position.x / TILE_SIZE.x);
+    Vector2::new(x, y)
+}
+
+/// Tile whose diamond contains the given world position.
+pub fn screen_to_map_tile(position: Vector2<f32>) -> Vector2<i32> {
+    let pos = screen_to_map_pos_f32(position);
+    Vector2::new(pos.x.round() as i32, pos.y.round() as i32)
+}
@@ -0,0 +1,143 @@
+use cgmath::Vector2;
+use thiserror::Error;
+
+use crate::components::cs_io::asset_path::AssetPath;
+use crate::components::cs_io::assets::{Asset, AssetError, LoadContext};
+use crate::components::cs_io::byte_reader::{ByteReader, UnexpectedEnd};
+use crate::components::cs_world::map::Map;
+use crate::components::cs_world::tile_registry::{TileKindId, TileRegistry};
+
+/// Map the game starts with, a generated one is used while it does not load.
+pub const START_MAP: &str = "assets/maps/start.map";
+const MAGIC: &[u8; 4] = b"CSMP";
+const VERSION: u16 = 2;
+/// Version 1 files have no heights, they load flat.
+const FIRST_VERSION: u16 = 1;
+/// Widest and tallest map a file may describe, anything larger is taken for a corrupt file.
+pub const MAX_MAP_SIZE: u32 = 1024;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MapDataError {
+    #[error("not a map file")]
+    InvalidMagic,
+
+    #[error("unsupported map file version {0}")]
+    UnsupportedVersion(u16),
+
+    #[error("map file ends unexpectedly")]
+    UnexpectedEnd,
+
+    #[error("map size {0}x{1} is not between 1x1 and {MAX_MAP_SIZE}x{MAX_MAP_SIZE}")]
+    InvalidSize(u32, u32),
+
+    #[error("map uses tile kind {0} which is not in the registry")]
+    UnknownTileKind(String),
+}
+
+impl From<UnexpectedEnd> for MapDataError {
+    fn from(_: UnexpectedEnd) -> Self {
+        MapDataError::UnexpectedEnd
+    }
+}
+
+/// Tile kinds of a map stored by name, so saved maps survive changes to the order of the tile registry.
+///
+/// File layout, little endian: `CSMP`, version u16, width u32, height u32,
+/// name count u16 followed by `len u8, utf8` names, then one u16 name index per tile row by row
+/// and since version 2 one u8 height per tile in the same order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapData {
+    pub size: Vector2<i32>,
+    pub kind_names: Vec<String>,
+    /// Index into `kind_names` for every tile.
+    pub tiles: Vec<u16>,
+    /// Height level of every tile.
+    pub heights: Vec<u8>,
+}
+
+impl MapData {
+    pub fn from_map(map: &Map, registry: &TileRegistry) -> Self {
+        let kind_names = registry.iter().map(|(_, definition)| definition.name.to_string()).collect();
+        Self {
+            size: map.size,
+            kind_names,
+            tiles: map.kinds.clone(),
+            heights: map.heights.clone(),
+        }
+    }
+
+    pub fn to_map(&self, registry: &TileRegistry) -> Result<Map, MapDataError> {
+        let ids = self.kind_names.iter()
+            .map(|name| registry.id(name).ok_or_else(|| MapDataError::UnknownTileKind(name.clone())))
+            .collect::<Result<Vec<TileKindId>, _>>()?;
+        let first = ids.first().copied().unwrap_or(0);
+        let mut map = Map::new(self.size, first, registry);
+        for (index, tile) in self.tiles.iter().enumerate() {
+            let kind = *ids.get(*tile as usize).ok_or(MapDataError::UnexpectedEnd)?;
+            if kind != first {
+                let pos = Vector2::new(index as i32 % self.size.x, index as i32 / self.size.x);
+                map.set_tile(pos, kind, registry);
+            }
+        }
+        for (index, height) in self.heights.iter().enumerate() {
+            map.set_height(Vector2::new(index as i32 % self.size.x, index as i32 / self.size.x), *height);
+        }
+        map.take_changed_tiles();
+        Ok(map)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.tiles.len() * 3);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.size.x as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.size.y as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.kind_names.len() as u16).to_le_bytes());
+        for name in self.kind_names.iter() {
+            // registries refuse longer names, see `MAX_NAME_LEN`
+            bytes.push(u8::try_from(name.len()).expect("tile name too long for a map file"));
+            bytes.extend_from_slice(name.as_bytes());
+        }
+        for tile in self.tiles.iter() {
+            bytes.extend_from_slice(&tile.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.heights);
+        bytes
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, MapDataError> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.take(4)? != MAGIC {
+            return Err(MapDataError::InvalidMagic);
+        }
+        let version = reader.u16()?;
+        if !(FIRST_VERSION..=VERSION).contains(&version) {
+            return Err(MapDataError::UnsupportedVersion(version));
+        }
+        let (width, height) = (reader.u32()?, reader.u32()?);
+        let valid = (1..=MAX_MAP_SIZE).contains(&width) && (1..=MAX_MAP_SIZE).contains(&height);
+        let tile_count = valid
+            .then(|| (width as usize).checked_mul(height as usize))
+            .flatten()
+            .ok_or(MapDataError::InvalidSize(width, height))?;
+        let size = Vector2::new(width as i32, height as i32);
+        let name_count = reader.u16()?;
+        let mut kind_names = Vec::with_capacity(name_count as usize);
+        for _ in 0..name_count {
+            let length = reader.u8()? as usize;
+            kind_names.push(String::from_utf8_lossy(reader.take(length)?).into_owned());
+        }
+        let tiles = (0..tile_count).map(|_| reader.u16()).collect::<Result<_, _>>()?;
+        let heights = match version {
+            FIRST_VERSION => vec![0; tile_count],
+            _ => reader.take(tile_count)?.to_vec(),
+        };
+        Ok(Self { size, kind_names, tiles, heights })
+    }
+}
+
+impl Asset for MapData {
+    fn from_bytes(bytes: Vec<u8>, path: &AssetPath, _context: &LoadContext) -> Result<Self, AssetError> {
+        MapData::from_slice(&bytes).map_err(|error| AssetError::decode(path, error))
+    }
+}
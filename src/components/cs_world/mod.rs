@@ -1 +1,16 @@
-pub mod map;
\ No newline at end of file
+pub mod map;
+pub mod autotile;
+pub mod tile_registry;
+pub mod fog_of_war;
+pub mod position;
+pub mod pathfinding;
+pub mod hierarchical_pathfinding;
+pub mod flow_field;
+pub mod steering;
+pub mod unit;
+pub mod building;
+pub mod placement;
+pub mod wall;
+pub mod history;
+pub mod editor;
+pub mod map_data;
@@ -0,0 +1,622 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+use bevy_ecs::event::EventReader;
+use bevy_ecs::schedule::{IntoSystemConfig, Schedule};
+use bevy_ecs::system::{Res, ResMut, Resource};
+use bevy_ecs::world::World;
+use cgmath::Vector2;
+
+use crate::components::cs_world::building::Occupancy;
+use crate::components::cs_world::hierarchical_pathfinding::{HierarchicalPath, PortalGraph, PortalSearch, CHUNK_SIZE};
+use crate::components::cs_world::map::{self, Map, TilesChanged};
+use crate::components::cs_world::tile_registry::TileRegistry;
+
+/// Node expansions all path jobs together may use per update.
+pub const DEFAULT_BUDGET: usize = 20_000;
+/// Expansions one job gets before the next job's turn, so a long search does not starve short ones.
+const JOB_SLICE: usize = 500;
+/// Requests at least this many tiles apart are planned over the portal graph when there is one.
+const PORTAL_DISTANCE: i32 = 2 * CHUNK_SIZE;
+/// Changes the cost grid remembers, a job that fell further behind restarts.
+const CHANGE_LOG_LEN: usize = 4096;
+
+const DIAGONAL: f32 = std::f32::consts::SQRT_2;
+
+/// The 8 neighbours of a tile, orthogonal first.
+pub(crate) const DIRECTIONS: [(i32, i32); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Movement cost of every tile, `None` for tiles that cannot be entered.
+#[derive(Debug, Clone, Resource)]
+pub struct CostGrid {
+    size: Vector2<i32>,
+    costs: Vec<Option<f32>>,
+    /// Lowest cost on the grid, keeps the heuristic admissible when roads are cheaper than 1.
+    min_cost: f32,
+    /// Tiles costing `min_cost`, the minimum is only searched again when the last of them changes.
+    min_count: usize,
+    /// Increased with every change, running searches check the changes since their last step.
+    version: u32,
+    /// Indices of the latest changes, the last one made the current version.
+    changes: VecDeque<usize>,
+}
+
+impl CostGrid {
+    pub fn new(size: Vector2<i32>, cost: Option<f32>) -> Self {
+        Self {
+            size,
+            costs: vec![cost; (size.x * size.y) as usize],
+            min_cost: cost.unwrap_or(1.0),
+            min_count: if cost.is_some() { (size.x * size.y) as usize } else { 0 },
+            version: 0,
+            changes: VecDeque::new(),
+        }
+    }
+
+    pub fn from_map(map: &Map, registry: &TileRegistry) -> Self {
+        let costs = map.kinds.iter().map(|kind| registry.get(*kind).movement_cost).collect();
+        let mut grid = Self { size: map.size, costs, min_cost: 1.0, min_count: 0, version: 0, changes: VecDeque::new() };
+        grid.update_min_cost();
+        grid
+    }
+
+    pub fn size(&self) -> Vector2<i32> {
+        self.size
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn in_bounds(&self, pos: Vector2<i32>) -> bool {
+        pos.x >= 0 && pos.y >= 0 && pos.x < self.size.x && pos.y < self.size.y
+    }
+
+    pub(crate) fn index(&self, pos: Vector2<i32>) -> usize {
+        (pos.y * self.size.x + pos.x) as usize
+    }
+
+    pub(crate) fn position(&self, index: usize) -> Vector2<i32> {
+        Vector2::new(index as i32 % self.size.x, index as i32 / self.size.x)
+    }
+
+    /// `None` outside the grid and for blocked tiles.
+    pub fn cost(&self, pos: Vector2<i32>) -> Option<f32> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+        self.costs[self.index(pos)]
+    }
+
+    pub fn is_passable(&self, pos: Vector2<i32>) -> bool {
+        self.cost(pos).is_some()
+    }
+
+    pub fn set_cost(&mut self, pos: Vector2<i32>, cost: Option<f32>) {
+        if !self.in_bounds(pos) {
+            return;
+        }
+        let index = self.index(pos);
+        if self.costs[index] == cost {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.costs[index], cost);
+        self.version = self.version.wrapping_add(1);
+        if self.changes.len() == CHANGE_LOG_LEN {
+            self.changes.pop_front();
+        }
+        self.changes.push_back(index);
+        match cost {
+            Some(cost) if cost < self.min_cost => {
+                self.min_cost = cost;
+                self.min_count = 1;
+                return;
+            }
+            Some(cost) if cost == self.min_cost => self.min_count += 1,
+            _ => {}
+        }
+        if previous == Some(self.min_cost) {
+            self.min_count -= 1;
+            if self.min_count == 0 {
+                self.update_min_cost();
+            }
+        }
+    }
+
+    /// Tiles changed after `version`, `None` when the log no longer reaches back that far.
+    fn changes_since(&self, version: u32) -> Option<impl Iterator<Item = usize> + '_> {
+        let count = self.version.wrapping_sub(version) as usize;
+        (count <= self.changes.len()).then(|| self.changes.range(self.changes.len() - count..).copied())
+    }
+
+    fn update_min_cost(&mut self) {
+        self.min_cost = self.costs.iter().flatten().copied().fold(f32::INFINITY, f32::min);
+        self.min_count = self.costs.iter().filter(|cost| **cost == Some(self.min_cost)).count();
+        if !self.min_cost.is_finite() {
+            self.min_cost = 1.0;
+        }
+    }
+
+    /// Cost of a single step, diagonal steps are only allowed when both tiles next to the corner are passable.
+    pub(crate) fn step_cost(&self, from: Vector2<i32>, direction: (i32, i32)) -> Option<f32> {
+        let to = from + Vector2::new(direction.0, direction.1);
+        let cost = self.cost(to)?;
+        if direction.0 != 0 && direction.1 != 0 {
+            if !self.is_passable(Vector2::new(to.x, from.y)) || !self.is_passable(Vector2::new(from.x, to.y)) {
+                return None;
+            }
+            return Some(cost * DIAGONAL);
+        }
+        Some(cost)
+    }
+
+    /// Octile distance scaled by the cheapest tile.
+    pub(crate) fn heuristic(&self, from: Vector2<i32>, to: Vector2<i32>) -> f32 {
+        let dx = (from.x - to.x).abs() as f32;
+        let dy = (from.y - to.y).abs() as f32;
+        (dx.max(dy) + (DIAGONAL - 1.0) * dx.min(dy)) * self.min_cost
+    }
+
+    /// Whether a unit can walk straight from `from` to `to` without entering a tile that is blocked
+    /// or more expensive than `max_cost`. Lines through a tile corner need both tiles at the corner.
+    pub fn line_of_sight(&self, from: Vector2<i32>, to: Vector2<i32>, max_cost: f32) -> bool {
+        let walkable = |pos: Vector2<i32>| self.cost(pos).is_some_and(|cost| cost <= max_cost);
+        let dx = (to.x - from.x).abs();
+        let dy = (to.y - from.y).abs();
+        let step = Vector2::new((to.x - from.x).signum(), (to.y - from.y).signum());
+        let mut pos = from;
+        let mut error = dx - dy;
+        loop {
+            if !walkable(pos) {
+                return false;
+            }
+            if pos == to {
+                return true;
+            }
+            match error.cmp(&0) {
+                Ordering::Greater => {
+                    pos.x += step.x;
+                    error -= 2 * dy;
+                }
+                Ordering::Less => {
+                    pos.y += step.y;
+                    error += 2 * dx;
+                }
+                Ordering::Equal => {
+                    if !walkable(Vector2::new(pos.x + step.x, pos.y)) || !walkable(Vector2::new(pos.x, pos.y + step.y)) {
+                        return false;
+                    }
+                    pos += step;
+                    error += 2 * dx - 2 * dy;
+                }
+            }
+        }
+    }
+}
+
+/// Rectangle of tiles, `max` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileBounds {
+    pub min: Vector2<i32>,
+    pub max: Vector2<i32>,
+}
+
+impl TileBounds {
+    pub fn contains(&self, pos: Vector2<i32>) -> bool {
+        pos.x >= self.min.x && pos.y >= self.min.y && pos.x < self.max.x && pos.y < self.max.y
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    /// Tiles from start to goal, straight runs between them are free of obstacles.
+    pub waypoints: Vec<Vector2<i32>>,
+    /// Cost of the unsmoothed path.
+    pub cost: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathResult {
+    Found(Path),
+    /// A long path planned over the chunk portals, its hops are refined into tiles as the unit walks them.
+    Portals(HierarchicalPath),
+    NoPath,
+}
+
+/// Candidate in the open list, ordered so the `BinaryHeap` pops the lowest estimate first.
+#[derive(Debug, Clone, Copy)]
+struct OpenNode {
+    index: usize,
+    cost: f32,
+    estimate: f32,
+    heuristic: f32,
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenNode {
+    // ties prefer the node closer to the goal, which explores far fewer nodes on open ground
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+            .then_with(|| other.heuristic.total_cmp(&self.heuristic))
+    }
+}
+
+/// An A* search that can be paused after a number of node expansions and continued later.
+#[derive(Debug, Clone)]
+pub struct PathSearch {
+    start: Vector2<i32>,
+    goal: Vector2<i32>,
+    grid_version: u32,
+    /// The heuristic is only admissible while the grid has no cheaper tile.
+    min_cost: f32,
+    /// Tiles outside are not explored, used to refine hierarchical paths inside one chunk.
+    bounds: Option<TileBounds>,
+    open: BinaryHeap<OpenNode>,
+    /// Best known cost and predecessor of every reached tile.
+    reached: HashMap<usize, (f32, usize)>,
+    /// Set once the goal is reached, the path is smoothed before it becomes the result.
+    smoothing: Option<Smoothing>,
+    result: Option<PathResult>,
+}
+
+impl PathSearch {
+    pub fn new(grid: &CostGrid, start: Vector2<i32>, goal: Vector2<i32>) -> Self {
+        Self::create(grid, start, goal, None)
+    }
+
+    /// A search that stays inside `bounds`, which must contain start and goal.
+    pub fn within(grid: &CostGrid, start: Vector2<i32>, goal: Vector2<i32>, bounds: TileBounds) -> Self {
+        Self::create(grid, start, goal, Some(bounds))
+    }
+
+    fn create(grid: &CostGrid, start: Vector2<i32>, goal: Vector2<i32>, bounds: Option<TileBounds>) -> Self {
+        let mut search = Self {
+            start,
+            goal,
+            grid_version: grid.version,
+            min_cost: grid.min_cost,
+            bounds,
+            open: BinaryHeap::new(),
+            reached: HashMap::new(),
+            smoothing: None,
+            result: None,
+        };
+        // the start may be blocked, e.g. a unit standing in a gate that just closed
+        let outside = bounds.is_some_and(|bounds| !bounds.contains(start) || !bounds.contains(goal));
+        if !grid.in_bounds(start) || !grid.is_passable(goal) || outside {
+            search.result = Some(PathResult::NoPath);
+            return search;
+        }
+        let index = grid.index(start);
+        let heuristic = grid.heuristic(start, goal);
+        search.open.push(OpenNode { index, cost: 0.0, estimate: heuristic, heuristic });
+        search.reached.insert(index, (0.0, index));
+        search
+    }
+
+    pub fn start(&self) -> Vector2<i32> {
+        self.start
+    }
+
+    pub fn goal(&self) -> Vector2<i32> {
+        self.goal
+    }
+
+    /// `None` while the search is running.
+    pub fn result(&self) -> Option<&PathResult> {
+        self.result.as_ref()
+    }
+
+    /// Expands up to `budget` nodes and smooths the found path, returns the work used. The last
+    /// line of sight check may go over the budget, so a long one still makes progress.
+    pub fn step(&mut self, grid: &CostGrid, budget: usize) -> usize {
+        let mut used = self.expand(grid, budget);
+        if let Some(smoothing) = &mut self.smoothing {
+            used += smoothing.step(grid, budget.saturating_sub(used));
+            if smoothing.is_finished() {
+                let smoothing = self.smoothing.take().unwrap();
+                self.result = Some(PathResult::Found(Path { waypoints: smoothing.waypoints, cost: smoothing.cost }));
+            }
+        }
+        used
+    }
+
+    /// Catches up with the cost changes since the last step. Only changes next to explored tiles can
+    /// alter the outcome, everything else is read when the search gets there.
+    fn sync(&mut self, grid: &CostGrid) {
+        if self.grid_version == grid.version {
+            return;
+        }
+        let changes: Option<Vec<_>> = grid.changes_since(self.grid_version).map(|changes| changes.map(|index| grid.position(index)).collect());
+        let touched = match &changes {
+            Some(changes) => grid.min_cost < self.min_cost || changes.iter().any(|pos| self.explored_near(grid, *pos)),
+            None => true,
+        };
+        if touched {
+            *self = Self::create(grid, self.start, self.goal, self.bounds);
+            return;
+        }
+        self.grid_version = grid.version;
+        // lines of sight may cross tiles the search never reached
+        if let Some(smoothing) = &mut self.smoothing {
+            if changes.into_iter().flatten().any(|pos| smoothing.area.contains(pos)) {
+                *smoothing = Smoothing::new(grid, std::mem::take(&mut smoothing.tiles), smoothing.cost);
+            }
+        }
+    }
+
+    fn explored_near(&self, grid: &CostGrid, pos: Vector2<i32>) -> bool {
+        std::iter::once(pos)
+            .chain(DIRECTIONS.iter().map(|direction| pos + Vector2::new(direction.0, direction.1)))
+            .any(|pos| grid.in_bounds(pos) && self.reached.contains_key(&grid.index(pos)))
+    }
+
+    fn expand(&mut self, grid: &CostGrid, budget: usize) -> usize {
+        let goal_index = grid.index(self.goal);
+        let mut used = 0;
+        while self.result.is_none() && self.smoothing.is_none() && used < budget {
+            let Some(node) = self.open.pop() else {
+                self.result = Some(PathResult::NoPath);
+                break;
+            };
+            used += 1;
+            let cost = node.cost;
+            // stale entry, the tile was reached more cheaply after it was queued
+            if self.reached[&node.index].0 < cost {
+                continue;
+            }
+            if node.index == goal_index {
+                self.smoothing = Some(Smoothing::new(grid, self.trace_path(grid), cost));
+                break;
+            }
+            let pos = grid.position(node.index);
+            for direction in DIRECTIONS {
+                let Some(step_cost) = grid.step_cost(pos, direction) else {
+                    continue;
+                };
+                let next = pos + Vector2::new(direction.0, direction.1);
+                if self.bounds.is_some_and(|bounds| !bounds.contains(next)) {
+                    continue;
+                }
+                let next_index = grid.index(next);
+                let next_cost = cost + step_cost;
+                if self.reached.get(&next_index).is_some_and(|(known, _)| *known <= next_cost) {
+                    continue;
+                }
+                self.reached.insert(next_index, (next_cost, node.index));
+                let heuristic = grid.heuristic(next, self.goal);
+                self.open.push(OpenNode { index: next_index, cost: next_cost, estimate: next_cost + heuristic, heuristic });
+            }
+        }
+        used
+    }
+
+    fn trace_path(&self, grid: &CostGrid) -> Vec<Vector2<i32>> {
+        let mut tiles = vec![self.goal];
+        let mut index = grid.index(self.goal);
+        while let Some((_, previous)) = self.reached.get(&index).filter(|(_, previous)| *previous != index) {
+            index = *previous;
+            tiles.push(grid.position(index));
+        }
+        tiles.reverse();
+        tiles
+    }
+}
+
+/// Searches a path in one go, for callers that are not bound by the update budget.
+pub fn find_path(grid: &CostGrid, start: Vector2<i32>, goal: Vector2<i32>) -> PathResult {
+    let mut search = PathSearch::new(grid, start, goal);
+    search.step(grid, usize::MAX);
+    search.result.unwrap_or(PathResult::NoPath)
+}
+
+/// Removes waypoints that can be skipped by walking straight. A shortcut may not cross tiles that are
+/// more expensive than the ones it replaces, otherwise a path along a road would be cut through a field.
+pub fn smooth_path(grid: &CostGrid, tiles: &[Vector2<i32>]) -> Vec<Vector2<i32>> {
+    let mut smoothing = Smoothing::new(grid, tiles.to_vec(), 0.0);
+    smoothing.step(grid, usize::MAX);
+    smoothing.waypoints
+}
+
+/// String pulling over the tiles of a path: a shortcut from the last waypoint is extended one tile at a
+/// time and the tile before the first blocked line becomes the next waypoint.
+#[derive(Debug, Clone)]
+struct Smoothing {
+    tiles: Vec<Vector2<i32>>,
+    cost: f32,
+    waypoints: Vec<Vector2<i32>>,
+    anchor: usize,
+    candidate: usize,
+    /// Highest cost between the anchor and the candidate.
+    max_cost: f32,
+    /// Box around the tiles, which holds every line between them.
+    area: TileBounds,
+}
+
+impl Smoothing {
+    fn new(grid: &CostGrid, tiles: Vec<Vector2<i32>>, cost: f32) -> Self {
+        let waypoints = tiles.first().copied().into_iter().collect();
+        let max_cost = tiles.get(1).map_or(0.0, |tile| grid.cost(*tile).unwrap_or(f32::INFINITY));
+        let area = tiles.iter().fold(TileBounds { min: Vector2::new(i32::MAX, i32::MAX), max: Vector2::new(i32::MIN, i32::MIN) }, |area, tile| TileBounds {
+            min: Vector2::new(area.min.x.min(tile.x), area.min.y.min(tile.y)),
+            max: Vector2::new(area.max.x.max(tile.x + 1), area.max.y.max(tile.y + 1)),
+        });
+        Self { tiles, cost, waypoints, anchor: 0, candidate: 2, max_cost, area }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.anchor + 1 >= self.tiles.len()
+    }
+
+    /// Checks lines of sight until `budget` tiles were walked, returns how many were.
+    fn step(&mut self, grid: &CostGrid, budget: usize) -> usize {
+        let mut used = 0;
+        while !self.is_finished() && used < budget {
+            let Some(candidate) = self.tiles.get(self.candidate) else {
+                self.waypoints.push(self.tiles[self.tiles.len() - 1]);
+                self.anchor = self.tiles.len() - 1;
+                break;
+            };
+            let max_cost = self.max_cost.max(grid.cost(*candidate).unwrap_or(f32::INFINITY));
+            used += self.candidate - self.anchor;
+            if grid.line_of_sight(self.tiles[self.anchor], *candidate, max_cost) {
+                self.max_cost = max_cost;
+                self.candidate += 1;
+                continue;
+            }
+            self.anchor = self.candidate - 1;
+            self.waypoints.push(self.tiles[self.anchor]);
+            self.max_cost = grid.cost(*candidate).unwrap_or(f32::INFINITY);
+            self.candidate += 1;
+        }
+        used
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PathRequestId(u64);
+
+/// A path request searched tile by tile or planned over the portals, both share the update budget.
+// only a handful of jobs are queued at a time, the size difference does not matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+enum PathJob {
+    Tiles(PathSearch),
+    Portals(PortalSearch),
+}
+
+/// Runs path requests as jobs over several updates, spending at most `budget` node expansions per update.
+/// Long requests are planned over the `PortalGraph` if one is passed to `run`.
+#[derive(Debug, Resource)]
+pub struct Pathfinder {
+    pub budget: usize,
+    next_id: u64,
+    jobs: VecDeque<(PathRequestId, PathJob)>,
+    results: HashMap<PathRequestId, PathResult>,
+}
+
+impl Default for Pathfinder {
+    fn default() -> Self {
+        Self {
+            budget: DEFAULT_BUDGET,
+            next_id: 0,
+            jobs: VecDeque::new(),
+            results: HashMap::new(),
+        }
+    }
+}
+
+impl Pathfinder {
+    /// Inserts the pathfinder and the cost grid of the map, `update_cost_grid` keeps the grid in sync with tile changes.
+    pub fn register(self, grid: CostGrid, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(self);
+        world.insert_resource(grid);
+        schedule.add_system(update_cost_grid.after(map::publish_tile_changes));
+        schedule.add_system(run_path_jobs);
+    }
+
+    pub fn with_budget(budget: usize) -> Self {
+        Self { budget, ..Self::default() }
+    }
+
+    pub fn request(&mut self, grid: &CostGrid, start: Vector2<i32>, goal: Vector2<i32>) -> PathRequestId {
+        let id = PathRequestId(self.next_id);
+        self.next_id += 1;
+        self.jobs.push_back((id, PathJob::Tiles(PathSearch::new(grid, start, goal))));
+        id
+    }
+
+    /// Drops the job or its result.
+    pub fn cancel(&mut self, id: PathRequestId) {
+        self.jobs.retain(|(job, _)| *job != id);
+        self.results.remove(&id);
+    }
+
+    pub fn is_pending(&self, id: PathRequestId) -> bool {
+        self.jobs.iter().any(|(job, _)| *job == id)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// The result once the job finished, it is handed out only once.
+    pub fn take_result(&mut self, id: PathRequestId) -> Option<PathResult> {
+        self.results.remove(&id)
+    }
+
+    /// Works on the jobs in turns until the budget is used up or all jobs finished.
+    pub fn run(&mut self, grid: &CostGrid, graph: Option<&PortalGraph>) {
+        let mut remaining = self.budget;
+        while remaining > 0 {
+            let Some((id, job)) = self.jobs.pop_front() else {
+                break;
+            };
+            let (start, goal) = match &job {
+                PathJob::Tiles(search) => (search.start, search.goal),
+                PathJob::Portals(search) => (search.start(), search.goal()),
+            };
+            let offset = goal - start;
+            let long = offset.x.abs().max(offset.y.abs()) >= PORTAL_DISTANCE;
+            // the graph may come or go between updates, e.g. while a map is loaded
+            let mut job = match (job, graph) {
+                (PathJob::Tiles(_), Some(graph)) if long => PathJob::Portals(graph.search(grid, start, goal)),
+                (PathJob::Portals(_), None) => PathJob::Tiles(PathSearch::new(grid, start, goal)),
+                (job, _) => job,
+            };
+            let result = match (&mut job, graph) {
+                (PathJob::Portals(search), Some(graph)) => {
+                    remaining -= search.step(graph, grid, remaining.min(JOB_SLICE)).min(remaining);
+                    search.take_result()
+                }
+                (PathJob::Tiles(search), _) => {
+                    search.sync(grid);
+                    remaining -= search.step(grid, remaining.min(JOB_SLICE)).min(remaining);
+                    search.result.take()
+                }
+                (PathJob::Portals(_), None) => unreachable!("portal jobs are searched by tile without a graph"),
+            };
+            match result {
+                Some(result) => {
+                    self.results.insert(id, result);
+                }
+                None => self.jobs.push_back((id, job)),
+            }
+        }
+    }
+}
+
+/// Tiles under a building are impassable whatever their kind.
+pub fn update_cost_grid(
+    mut grid: ResMut<CostGrid>,
+    map: Res<Map>,
+    registry: Res<TileRegistry>,
+    occupancy: Option<Res<Occupancy>>,
+    mut tiles_changed: EventReader<TilesChanged>,
+) {
+    for index in tiles_changed.iter().flat_map(|event| event.indices.iter()) {
+        let pos = Vector2::new(*index as i32 % map.size.x, *index as i32 / map.size.x);
+        let cost = match occupancy.as_ref().is_some_and(|occupancy| occupancy.is_occupied(pos)) {
+            true => None,
+            false => registry.get(map.kinds[*index]).movement_cost,
+        };
+        grid.set_cost(pos, cost);
+    }
+}
+
+pub fn run_path_jobs(mut pathfinder: ResMut<Pathfinder>, grid: Res<CostGrid>, graph: Option<Res<PortalGraph>>) {
+    pathfinder.run(&grid, graph.as_deref());
+}
@@ -0,0 +1,233 @@
+use bevy_ecs::system::Resource;
+use bevy_ecs::world::World;
+use cgmath::Vector2;
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::components::cs_render::minimap::Minimap;
+use crate::components::cs_render::overlay::{TileOverlay, INVALID_COLOR};
+use crate::components::cs_ui::ui_layer::UiFocus;
+use crate::components::cs_util::input::{Cursor, Input};
+use crate::components::cs_world::building::{self, Building, BuildingKindId, BuildingRegistry, Occupancy, PlacementError, Rotation};
+use crate::components::cs_world::history::{self, DemolishBuilding, PlaceBuilding};
+use crate::components::cs_world::map::TilePreview;
+use crate::components::cs_world::wall::{self, WallPlan};
+
+pub const PLACEMENT_LAYER: &str = "placement";
+pub const WALL_LAYER: &str = "walls";
+pub const WALL_BLOCKED_LAYER: &str = "walls_blocked";
+const VALID_COLOR: [f32; 4] = [0.2, 0.85, 0.3, 0.45];
+
+/// Building the player is about to place and how it fits under the cursor.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct PlacementTool {
+    /// `None` when the tool is off.
+    pub building: Option<BuildingKindId>,
+    /// Clicking removes buildings instead of placing them.
+    pub demolish: bool,
+    pub rotation: Rotation,
+    /// Footprint origin under the cursor and the result of `check_placement` there.
+    pub preview: Option<(Vector2<i32>, Result<(), PlacementError>)>,
+}
+
+impl PlacementTool {
+    pub fn select(&mut self, building: Option<BuildingKindId>) {
+        self.building = building;
+        self.demolish = false;
+        self.preview = None;
+    }
+
+    pub fn select_demolish(&mut self, demolish: bool) {
+        self.select(None);
+        self.demolish = demolish;
+    }
+
+    /// Why the building cannot go under the cursor, shown next to the tool.
+    pub fn invalid_reason(&self) -> Option<String> {
+        match &self.preview {
+            Some((_, Err(error))) => Some(error.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Previews the footprint under the cursor, R turns it and a left click places it. Escape leaves the tool.
+pub fn update_placement_tool(world: &mut World) {
+    let keyboard_input = world.resource::<Input<VirtualKeyCode>>();
+    let escape = keyboard_input.just_pressed(VirtualKeyCode::Escape);
+    let rotate = keyboard_input.just_pressed(VirtualKeyCode::R);
+    let keyboard_focus = world.resource::<UiFocus>().keyboard;
+    let mut tool = world.resource_mut::<PlacementTool>();
+    if escape && !keyboard_focus {
+        tool.select(None);
+    }
+    if rotate && !keyboard_focus {
+        tool.rotation = tool.rotation.next();
+    }
+    let (building, demolish, rotation) = (tool.building, tool.demolish, tool.rotation);
+    let hovered = world.resource::<TileOverlay>().hovered_tile;
+    let (Some(hovered), true) = (hovered, building.is_some() || demolish) else {
+        world.resource_mut::<PlacementTool>().preview = None;
+        world.resource_mut::<TileOverlay>().remove_layer(PLACEMENT_LAYER);
+        return;
+    };
+    let Some(kind) = building else {
+        update_demolish(world, hovered);
+        return;
+    };
+
+    let definition = world.resource::<BuildingRegistry>().get(kind);
+    let origin = definition.origin_around(hovered, rotation);
+    let tiles = definition.tiles(origin, rotation);
+    let name = definition.name;
+    let mut result = building::check_placement(world, kind, origin, rotation);
+    let clicked = world.resource::<Input<MouseButton>>().just_pressed(MouseButton::Left);
+    if clicked && result.is_ok() && pointer_on_world(world) {
+        history::execute(world, format!("place {}", name), PlaceBuilding(Building { kind, origin, rotation }));
+        // the footprint is taken now, the preview shows that until the cursor moves on
+        result = building::check_placement(world, kind, origin, rotation);
+    }
+    let color = match result {
+        Ok(()) => VALID_COLOR,
+        Err(_) => INVALID_COLOR,
+    };
+    world.resource_mut::<TileOverlay>().set_layer(PLACEMENT_LAYER, tiles, color);
+    world.resource_mut::<PlacementTool>().preview = Some((origin, result));
+}
+
+/// Marks the building under the cursor and removes it on a left click.
+fn update_demolish(world: &mut World, hovered: Vector2<i32>) {
+    let target = world.resource::<Occupancy>().get(hovered).and_then(|entity| world.get::<Building>(entity).copied());
+    let Some(target) = target else {
+        world.resource_mut::<TileOverlay>().remove_layer(PLACEMENT_LAYER);
+        return;
+    };
+    let clicked = world.resource::<Input<MouseButton>>().just_pressed(MouseButton::Left);
+    if clicked && pointer_on_world(world) {
+        let label = format!("demolish {}", world.resource::<BuildingRegistry>().get(target.kind).name);
+        history::execute(world, label, DemolishBuilding(target));
+        world.resource_mut::<TileOverlay>().remove_layer(PLACEMENT_LAYER);
+        return;
+    }
+    let tiles = world.resource::<BuildingRegistry>().get(target.kind).tiles(target.origin, target.rotation);
+    world.resource_mut::<TileOverlay>().set_layer(PLACEMENT_LAYER, tiles, INVALID_COLOR);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallMode {
+    /// Drags straight or L-shaped walls.
+    Walls,
+    /// Puts gatehouses into existing walls.
+    Gatehouse,
+}
+
+/// Wall drawing tool, a drag from press to release builds all segments as one edit.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct WallTool {
+    /// `None` when the tool is off.
+    pub mode: Option<WallMode>,
+    /// Walls get towers at their ends, corners and every `TOWER_SPACING` segments.
+    pub towers: bool,
+    drag_start: Option<Vector2<i32>>,
+    /// What a release or click would build, or why a gatehouse does not fit.
+    pub preview: Option<Result<WallPlan, PlacementError>>,
+}
+
+impl WallTool {
+    pub fn select(&mut self, mode: Option<WallMode>) {
+        self.mode = mode;
+        self.drag_start = None;
+        self.preview = None;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag_start.is_some()
+    }
+
+    /// Why the preview cannot be built, or why some of its tiles are skipped.
+    pub fn invalid_reason(&self) -> Option<String> {
+        match &self.preview {
+            Some(Ok(plan)) => plan.blocked_reason(),
+            Some(Err(error)) => Some(error.to_string()),
+            None => None,
+        }
+    }
+}
+
+/// Left drag draws walls and builds them on release, a right click cancels the drag. Escape leaves the tool.
+pub fn update_wall_tool(world: &mut World) {
+    let escape = world.resource::<Input<VirtualKeyCode>>().just_pressed(VirtualKeyCode::Escape);
+    let mouse_input = world.resource::<Input<MouseButton>>();
+    let (pressed, released) = (mouse_input.just_pressed(MouseButton::Left), mouse_input.just_released(MouseButton::Left));
+    let cancel = mouse_input.just_pressed(MouseButton::Right);
+    let keyboard_focus = world.resource::<UiFocus>().keyboard;
+    let hovered = world.resource::<TileOverlay>().hovered_tile;
+    let on_world = pointer_on_world(world);
+    let mut tool = world.resource_mut::<WallTool>();
+    if escape && !keyboard_focus {
+        tool.select(None);
+    }
+    if cancel {
+        tool.drag_start = None;
+    }
+    let (Some(mode), Some(hovered)) = (tool.mode, hovered) else {
+        // releasing off the map drops the drag instead of building it on the next hovered tile
+        if released {
+            tool.drag_start = None;
+        }
+        tool.preview = None;
+        clear_wall_preview(world);
+        return;
+    };
+
+    let preview = match mode {
+        WallMode::Walls => {
+            if pressed && on_world {
+                tool.drag_start = Some(hovered);
+            }
+            let (drag_start, towers) = (tool.drag_start, tool.towers);
+            let path = match drag_start {
+                Some(start) => wall::drag_path(start, hovered),
+                None => vec![hovered],
+            };
+            let plan = wall::plan_walls(world, &path, towers);
+            if released && drag_start.is_some() {
+                wall::build_walls(world, &plan, "build walls");
+                world.resource_mut::<WallTool>().drag_start = None;
+                Ok(wall::plan_walls(world, &[hovered], towers))
+            } else {
+                Ok(plan)
+            }
+        }
+        WallMode::Gatehouse => match wall::plan_gatehouse(world, hovered) {
+            Ok(plan) if pressed && on_world => {
+                wall::build_walls(world, &plan, "build gatehouse");
+                wall::plan_gatehouse(world, hovered)
+            }
+            result => result,
+        },
+    };
+
+    let (changes, blocked) = match &preview {
+        Ok(plan) => (plan.changes.clone(), plan.blocked.iter().map(|(tile, _)| *tile).collect()),
+        Err(_) => (Vec::new(), vec![hovered]),
+    };
+    let mut overlay = world.resource_mut::<TileOverlay>();
+    overlay.set_layer(WALL_LAYER, changes.iter().map(|(tile, _)| *tile).collect(), VALID_COLOR);
+    overlay.set_layer(WALL_BLOCKED_LAYER, blocked, INVALID_COLOR);
+    world.resource_mut::<TilePreview>().tiles = changes;
+    world.resource_mut::<WallTool>().preview = Some(preview);
+}
+
+fn clear_wall_preview(world: &mut World) {
+    world.resource_mut::<TilePreview>().tiles.clear();
+    let mut overlay = world.resource_mut::<TileOverlay>();
+    overlay.remove_layer(WALL_LAYER);
+    overlay.remove_layer(WALL_BLOCKED_LAYER);
+}
+
+/// The cursor is over the world and not over a panel or the minimap.
+pub(crate) fn pointer_on_world(world: &World) -> bool {
+    let cursor = world.resource::<Cursor>().position;
+    let over_minimap = world.get_resource::<Minimap>().is_some_and(|minimap| minimap.layout.screen_to_map(cursor).is_some());
+    !over_minimap && !world.resource::<UiFocus>().pointer
+}
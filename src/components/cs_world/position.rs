@@ -0,0 +1,16 @@
+use bevy_ecs::component::Component;
+use cgmath::Vector2;
+
+/// Position of an entity in map tile space, the fractional part is the position inside the tile.
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+pub struct TilePosition(pub Vector2<f32>);
+
+impl TilePosition {
+    pub fn tile(&self) -> Vector2<i32> {
+        Vector2::new(self.0.x.floor() as i32, self.0.y.floor() as i32)
+    }
+}
+
+/// Movement of an entity in tiles per second.
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+pub struct Velocity(pub Vector2<f32>);
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::system::{Query, Res, Resource};
+use cgmath::{InnerSpace, Vector2, Zero};
+
+use crate::components::cs_util::time::GameTime;
+use crate::components::cs_world::flow_field::{FlowFieldId, FlowFields};
+use crate::components::cs_world::pathfinding::CostGrid;
+use crate::components::cs_world::position::{TilePosition, Velocity};
+
+/// Moves the entity along a flow field while keeping its distance to other followers.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct FlowFollower {
+    pub field: FlowFieldId,
+    /// Tiles per second on ground with cost 1, cheaper tiles are walked faster.
+    pub max_speed: f32,
+    /// Followers push each other apart when their circles overlap.
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct SteeringSettings {
+    /// Weight of the push away from overlapping followers against following the field.
+    pub separation: f32,
+    /// How quickly the velocity turns towards the wanted one, per second.
+    pub acceleration: f32,
+    /// Followers stop within this distance of the centre of a target tile.
+    pub arrival_distance: f32,
+}
+
+impl Default for SteeringSettings {
+    fn default() -> Self {
+        Self {
+            separation: 1.5,
+            acceleration: 8.0,
+            arrival_distance: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Neighbour {
+    entity: Entity,
+    position: Vector2<f32>,
+    radius: f32,
+}
+
+/// Followers bucketed by tile, neighbours are only looked up in the surrounding tiles.
+struct Neighbours {
+    cells: HashMap<Vector2<i32>, Vec<Neighbour>>,
+}
+
+impl Neighbours {
+    fn new(followers: impl Iterator<Item = Neighbour>) -> Self {
+        let mut cells: HashMap<_, Vec<_>> = HashMap::new();
+        for neighbour in followers {
+            cells.entry(tile_of(neighbour.position)).or_default().push(neighbour);
+        }
+        Self { cells }
+    }
+
+    /// Sum of the pushes of all followers overlapping the circle, stronger the deeper they overlap.
+    /// Followers on the same spot are pushed apart along x, in an order given by their entities.
+    fn separation(&self, entity: Entity, position: Vector2<f32>, radius: f32) -> Vector2<f32> {
+        let tile = tile_of(position);
+        let mut push = Vector2::zero();
+        for y in tile.y - 1..=tile.y + 1 {
+            for x in tile.x - 1..=tile.x + 1 {
+                let Some(cell) = self.cells.get(&Vector2::new(x, y)) else {
+                    continue;
+                };
+                for other in cell.iter().filter(|other| other.entity != entity) {
+                    let reach = radius + other.radius;
+                    let offset = position - other.position;
+                    let distance = offset.magnitude();
+                    if distance >= reach {
+                        continue;
+                    }
+                    let away = if distance > 1e-4 {
+                        offset / distance
+                    } else if entity < other.entity {
+                        Vector2::new(-1.0, 0.0)
+                    } else {
+                        Vector2::new(1.0, 0.0)
+                    };
+                    push += away * ((reach - distance) / reach);
+                }
+            }
+        }
+        push
+    }
+}
+
+fn tile_of(position: Vector2<f32>) -> Vector2<i32> {
+    Vector2::new(position.x.floor() as i32, position.y.floor() as i32)
+}
+
+/// Moves `position` by `movement`, sliding along walls by dropping the blocked axis.
+/// Followers a wall was built on may walk off it in any direction.
+fn move_on_grid(grid: &CostGrid, position: Vector2<f32>, movement: Vector2<f32>) -> Option<Vector2<f32>> {
+    if !grid.is_passable(tile_of(position)) {
+        return Some(position + movement);
+    }
+    [movement, Vector2::new(movement.x, 0.0), Vector2::new(0.0, movement.y)]
+        .into_iter()
+        .map(|movement| position + movement)
+        .find(|moved| grid.is_passable(tile_of(*moved)))
+}
+
+pub fn steer_flow_followers(
+    time: Res<GameTime>,
+    settings: Res<SteeringSettings>,
+    flow_fields: Res<FlowFields>,
+    grid: Res<CostGrid>,
+    mut followers: Query<(Entity, &FlowFollower, &mut TilePosition, &mut Velocity)>,
+) {
+    let delta = time.delta as f32;
+    let neighbours = Neighbours::new(followers.iter().map(|(entity, follower, position, _)| Neighbour {
+        entity,
+        position: position.0,
+        radius: follower.radius,
+    }));
+
+    for (entity, follower, mut position, mut velocity) in followers.iter_mut() {
+        let tile = tile_of(position.0);
+        let speed = follower.max_speed / grid.cost(tile).unwrap_or(1.0);
+
+        let mut wanted = Vector2::zero();
+        if let Some(field) = flow_fields.get(follower.field) {
+            if let Some(offset) = field.steer(position.0) {
+                let distance = offset.magnitude();
+                // followers that arrived only move to make room for others
+                let arrived = field.is_target(tile) && distance <= settings.arrival_distance;
+                if !arrived && distance > f32::EPSILON {
+                    wanted = offset / distance * speed;
+                }
+            }
+        }
+        wanted += neighbours.separation(entity, position.0, follower.radius) * settings.separation * speed;
+        if wanted.magnitude2() > speed * speed {
+            wanted = wanted.normalize_to(speed);
+        }
+
+        let turn = (wanted - velocity.0) * (settings.acceleration * delta).min(1.0);
+        velocity.0 += turn;
+        match move_on_grid(&grid, position.0, velocity.0 * delta) {
+            Some(moved) => position.0 = moved,
+            None => velocity.0 = Vector2::zero(),
+        }
+    }
+}
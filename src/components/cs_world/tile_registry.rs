@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use bevy_ecs::system::Resource;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::components::cs_io::asset_path::AssetPath;
+use crate::components::cs_io::assets::{Asset, AssetError, LoadContext};
+use crate::components::cs_render::shader_types::tile_animation::{pack_frame, TileAnimationData};
+use crate::components::cs_world::autotile;
+use crate::components::cs_world::autotile::AutotileMode;
+
+pub type TileKindId = u16;
+
+/// Atlas cells per row of the tile atlas (2048 / 30).
+pub const ATLAS_COLUMNS: u8 = 68;
+/// Shortest frame the animation table hands to the shader, it divides the time by the frame duration.
+pub const MIN_FRAME_DURATION: f32 = 0.001;
+/// Tile kinds of the game, also compiled in for `TileRegistry::default`.
+pub const TILE_DEFINITIONS: &str = "assets/data/tiles.ron";
+/// Longest tile name in bytes, map files store the length of each name in one byte.
+pub const MAX_NAME_LEN: usize = u8::MAX as usize;
+
+#[derive(Error, Debug)]
+pub enum TileRegistryError {
+    #[error("could not read tile definitions: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+
+    #[error("tile kind {0} is defined twice")]
+    DuplicateName(String),
+
+    #[error("tile kind {0} has a name longer than {MAX_NAME_LEN} bytes")]
+    NameTooLong(String),
+
+    #[error("animation of tile kind {0} needs frames and a frame duration above zero")]
+    InvalidAnimation(String),
+
+    #[error("tile kind {0} needs a finite movement cost above zero")]
+    InvalidMovementCost(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum AutotileGroup {
+    Road,
+    Wall,
+    Water,
+}
+
+#[derive(Debug, Clone)]
+pub struct AutotileRule {
+    pub mode: AutotileMode,
+    /// Groups the tile joins with, a tile always joins with its own group.
+    pub connects_to: Vec<AutotileGroup>,
+    /// Atlas cell of the variant for mask 0, the other variants follow in the same row.
+    pub first_variant: [u8; 2],
+}
+
+impl AutotileRule {
+    pub fn atlas_coordinate(&self, mask: u8) -> [u8; 2] {
+        let index = self.first_variant[0] as u16 + autotile::variant_index(self.mode, mask) as u16;
+        [
+            (index % ATLAS_COLUMNS as u16) as u8,
+            self.first_variant[1] + (index / ATLAS_COLUMNS as u16) as u8,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum AnimationMode {
+    Loop = 0,
+    PingPong = 1,
+}
+
+impl AnimationMode {
+    /// Frame shown at the `step`th frame duration, the same as `current_animation_frame` in instancing.wgsl.
+    pub fn frame(self, step: u32, frame_count: u32) -> u32 {
+        match self {
+            AnimationMode::PingPong if frame_count > 1 => {
+                let period = 2 * (frame_count - 1);
+                let position = step % period;
+                position.min(period - position)
+            }
+            _ => step % frame_count.max(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TileAnimation {
+    /// Atlas cell offsets added to the tile's own atlas coordinate, so autotiled tiles keep their variant.
+    pub frames: Vec<[u8; 2]>,
+    /// Seconds each frame is shown.
+    pub frame_duration: f32,
+    pub mode: AnimationMode,
+}
+
+impl TileAnimation {
+    /// Index into `frames` shown `time` seconds into the animation.
+    pub fn frame_at(&self, time: f32) -> usize {
+        let step = (time / self.frame_duration.max(MIN_FRAME_DURATION)) as u32;
+        self.mode.frame(step, self.frames.len() as u32) as usize
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TileDefinition {
+    pub name: String,
+    pub atlas_coordinate: [u8; 2],
+    /// Number of cells after `atlas_coordinate` that are random looking variations of this tile.
+    pub variations: u8,
+    pub group: Option<AutotileGroup>,
+    pub autotile: Option<AutotileRule>,
+    pub animation: Option<TileAnimation>,
+    /// RGBA colour of the tile on the minimap.
+    pub minimap_color: [u8; 4],
+    /// Cost of walking onto the tile, `None` if units cannot enter it.
+    pub movement_cost: Option<f32>,
+}
+
+impl TileDefinition {
+    /// Whether this tile visually joins with a neighbour of the given definition.
+    pub fn connects_with(&self, other: &TileDefinition) -> bool {
+        let (Some(rule), Some(other_group)) = (&self.autotile, other.group) else {
+            return false;
+        };
+        self.group == Some(other_group) || rule.connects_to.contains(&other_group)
+    }
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct TileRegistry {
+    definitions: Vec<TileDefinition>,
+    by_name: HashMap<String, TileKindId>,
+    animation_ids: Vec<u8>,
+}
+
+impl TileRegistry {
+    /// Reads the definitions from the RON list in `TILE_DEFINITIONS`, ids follow the order of the list.
+    pub fn from_ron(source: &str) -> Result<Self, TileRegistryError> {
+        let data: Vec<TileData> = ron::from_str(source)?;
+        let mut definitions: Vec<TileDefinition> = Vec::with_capacity(data.len());
+        for tile in data {
+            if definitions.iter().any(|definition| definition.name == tile.name) {
+                return Err(TileRegistryError::DuplicateName(tile.name));
+            }
+            if tile.name.len() > MAX_NAME_LEN {
+                return Err(TileRegistryError::NameTooLong(tile.name));
+            }
+            if tile.animation.as_ref().is_some_and(|animation| animation.frames.is_empty() || animation.frame_duration <= 0.0) {
+                return Err(TileRegistryError::InvalidAnimation(tile.name));
+            }
+            // the path searches rely on positive costs, free or negative steps break their estimates
+            if tile.movement_cost.is_some_and(|cost| !cost.is_finite() || cost <= 0.0) {
+                return Err(TileRegistryError::InvalidMovementCost(tile.name));
+            }
+            definitions.push(tile.into());
+        }
+        Ok(Self::new(definitions))
+    }
+
+    pub fn new(definitions: Vec<TileDefinition>) -> Self {
+        assert!(definitions.iter().all(|definition| definition.name.len() <= MAX_NAME_LEN), "tile names are saved with a one byte length");
+        let by_name = definitions.iter()
+            .enumerate()
+            .map(|(id, definition)| (definition.name.clone(), id as TileKindId))
+            .collect();
+        let mut next_animation_id = 0;
+        let animation_ids = definitions.iter()
+            .map(|definition| match definition.animation {
+                Some(_) => {
+                    next_animation_id += 1;
+                    next_animation_id
+                }
+                None => 0,
+            })
+            .collect();
+        Self { definitions, by_name, animation_ids }
+    }
+
+    /// Id used by `AtlasCoordinate::animation`, 0 if the tile kind is static.
+    pub fn animation_id(&self, kind: TileKindId) -> u8 {
+        self.animation_ids[kind as usize]
+    }
+
+    /// Flattens all animations into the frame list and animation table read by `vs_main`.
+    /// The table entry for animation id `n` is stored at `n - 1`.
+    pub fn animation_table(&self) -> (Vec<u32>, Vec<TileAnimationData>) {
+        let mut frames = Vec::new();
+        let mut animations = Vec::new();
+        for animation in self.definitions.iter().filter_map(|definition| definition.animation.as_ref()) {
+            animations.push(TileAnimationData {
+                first_frame: frames.len() as u32,
+                frame_count: animation.frames.len() as u32,
+                frame_duration: animation.frame_duration.max(MIN_FRAME_DURATION),
+                mode: animation.mode as u32,
+            });
+            frames.extend(animation.frames.iter().map(|frame| pack_frame(*frame)));
+        }
+        (frames, animations)
+    }
+
+    pub fn get(&self, kind: TileKindId) -> &TileDefinition {
+        &self.definitions[kind as usize]
+    }
+
+    pub fn id(&self, name: &str) -> Option<TileKindId> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TileKindId, &TileDefinition)> {
+        self.definitions.iter().enumerate().map(|(id, definition)| (id as TileKindId, definition))
+    }
+}
+
+impl Default for TileRegistry {
+    fn default() -> Self {
+        Self::from_ron(include_str!("../../../assets/data/tiles.ron")).expect("built in tile definitions are valid")
+    }
+}
+
+impl Asset for TileRegistry {
+    fn from_bytes(bytes: Vec<u8>, path: &AssetPath, _context: &LoadContext) -> Result<Self, AssetError> {
+        let source = String::from_utf8(bytes).map_err(|error| AssetError::decode(path, error))?;
+        TileRegistry::from_ron(&source).map_err(|error| AssetError::decode(path, error))
+    }
+}
+
+/// A tile kind as written in the definitions file, everything but the name, atlas cell and colour is optional.
+#[derive(Deserialize)]
+struct TileData {
+    name: String,
+    atlas_coordinate: [u8; 2],
+    minimap_color: [u8; 3],
+    #[serde(default = "one")]
+    variations: u8,
+    #[serde(default)]
+    autotile: Option<AutotileData>,
+    #[serde(default)]
+    animation: Option<TileAnimation>,
+    #[serde(default = "walkable")]
+    movement_cost: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct AutotileData {
+    group: AutotileGroup,
+    mode: AutotileMode,
+    #[serde(default)]
+    connects_to: Vec<AutotileGroup>,
+    first_variant: [u8; 2],
+}
+
+fn one() -> u8 {
+    1
+}
+
+fn walkable() -> Option<f32> {
+    Some(1.0)
+}
+
+impl From<TileData> for TileDefinition {
+    fn from(data: TileData) -> Self {
+        let [r, g, b] = data.minimap_color;
+        Self {
+            name: data.name,
+            atlas_coordinate: data.atlas_coordinate,
+            variations: data.variations,
+            group: data.autotile.as_ref().map(|autotile| autotile.group),
+            autotile: data.autotile.map(|autotile| AutotileRule {
+                mode: autotile.mode,
+                connects_to: autotile.connects_to,
+                first_variant: autotile.first_variant,
+            }),
+            animation: data.animation,
+            minimap_color: [r, g, b, 255],
+            movement_cost: data.movement_cost,
+        }
+    }
+}
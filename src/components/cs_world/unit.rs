@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_4;
+
+use bevy_ecs::bundle::Bundle;
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::With;
+use bevy_ecs::schedule::{IntoSystemConfig, Schedule};
+use bevy_ecs::removal_detection::RemovedComponents;
+use bevy_ecs::system::{Commands, Local, Query, Res, ResMut, Resource};
+use bevy_ecs::world::World;
+use cgmath::{InnerSpace, Vector2, Zero};
+
+use crate::components::cs_util::time::GameTime;
+use crate::components::cs_world::hierarchical_pathfinding::{HierarchicalPath, Refinement};
+use crate::components::cs_world::pathfinding::{self, CostGrid, PathRequestId, PathResult, Pathfinder};
+use crate::components::cs_world::position::{TilePosition, Velocity};
+use crate::components::cs_world::steering;
+use crate::components::cs_world::tile_registry::{AnimationMode, MIN_FRAME_DURATION};
+
+pub type UnitKindId = u16;
+
+/// Size of one frame in the unit sprite sheet in pixels.
+pub const SPRITE_SIZE: Vector2<u32> = Vector2::new(16, 24);
+/// Pixels between the bottom of a frame and the feet of the unit.
+pub const SPRITE_FOOT_OFFSET: f32 = 2.0;
+/// Below this speed in tiles per second a unit counts as standing.
+const WALK_THRESHOLD: f32 = 0.05;
+
+/// Direction a unit looks at on screen, the value is the sprite sheet row relative to the first row of the unit.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub enum Facing {
+    East = 0,
+    SouthEast = 1,
+    South = 2,
+    SouthWest = 3,
+    West = 4,
+    NorthWest = 5,
+    North = 6,
+    NorthEast = 7,
+}
+
+impl Facing {
+    pub const ALL: [Facing; 8] = [
+        Facing::East,
+        Facing::SouthEast,
+        Facing::South,
+        Facing::SouthWest,
+        Facing::West,
+        Facing::NorthWest,
+        Facing::North,
+        Facing::NorthEast,
+    ];
+
+    /// Facing of a movement in tile space, as seen on the isometric screen. `None` for a zero vector.
+    pub fn from_direction(direction: Vector2<f32>) -> Option<Self> {
+        // same projection as `map::map_to_screen_pos_centered`, screen y points down
+        let screen = Vector2::new(direction.x - direction.y, (direction.x + direction.y) / 2.0);
+        if screen.magnitude2() <= f32::EPSILON {
+            return None;
+        }
+        let sector = (screen.y.atan2(screen.x) / FRAC_PI_4).round() as i32;
+        Some(Self::ALL[sector.rem_euclid(8) as usize])
+    }
+
+    pub fn row(self) -> u32 {
+        self as u32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitAnimationState {
+    Idle,
+    Walk,
+}
+
+#[derive(Debug, Clone)]
+pub struct UnitAnimation {
+    /// Sprite sheet column of the first frame, the others follow in the same row.
+    pub first_column: u32,
+    pub frames: u32,
+    /// Seconds each frame is shown.
+    pub frame_duration: f32,
+    pub mode: AnimationMode,
+}
+
+impl UnitAnimation {
+    pub fn new(first_column: u32, frames: u32, frame_duration: f32, mode: AnimationMode) -> Self {
+        Self { first_column, frames, frame_duration, mode }
+    }
+
+    /// Sprite sheet column shown `elapsed` seconds after the animation started.
+    pub fn column(&self, elapsed: f32) -> u32 {
+        let step = (elapsed / self.frame_duration.max(MIN_FRAME_DURATION)) as u32;
+        self.first_column + self.mode.frame(step, self.frames)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnitDefinition {
+    pub name: &'static str,
+    /// Sprite sheet row of the first facing, the 8 facings follow in the order of `Facing`.
+    pub first_row: u32,
+    /// Tiles per second on ground with cost 1.
+    pub max_speed: f32,
+    /// Radius in tiles other units keep their distance to.
+    pub radius: f32,
+    pub idle: UnitAnimation,
+    pub walk: UnitAnimation,
+}
+
+impl UnitDefinition {
+    fn new(name: &'static str, first_row: u32, max_speed: f32, radius: f32) -> Self {
+        Self {
+            name,
+            first_row,
+            max_speed,
+            radius,
+            idle: UnitAnimation::new(0, 1, 1.0, AnimationMode::Loop),
+            walk: UnitAnimation::new(1, 4, 0.15, AnimationMode::Loop),
+        }
+    }
+
+    fn with_walk(mut self, walk: UnitAnimation) -> Self {
+        self.walk = walk;
+        self
+    }
+
+    pub fn animation(&self, state: UnitAnimationState) -> &UnitAnimation {
+        match state {
+            UnitAnimationState::Idle => &self.idle,
+            UnitAnimationState::Walk => &self.walk,
+        }
+    }
+
+    /// Sprite sheet cell of the current frame.
+    pub fn sprite_cell(&self, facing: Facing, animation: &AnimationPlayer) -> Vector2<u32> {
+        Vector2::new(self.animation(animation.state).column(animation.elapsed), self.first_row + facing.row())
+    }
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct UnitRegistry {
+    definitions: Vec<UnitDefinition>,
+    by_name: HashMap<&'static str, UnitKindId>,
+}
+
+impl UnitRegistry {
+    pub fn new(definitions: Vec<UnitDefinition>) -> Self {
+        let by_name = definitions.iter()
+            .enumerate()
+            .map(|(id, definition)| (definition.name, id as UnitKindId))
+            .collect();
+        Self { definitions, by_name }
+    }
+
+    /// Inserts the registry, movement runs after the path jobs and the flow field steering of the update.
+    pub fn register(self, world: &mut World, schedule: &mut Schedule) {
+        world.insert_resource(self);
+        schedule.add_system(request_unit_paths.before(pathfinding::run_path_jobs));
+        schedule.add_system(follow_unit_paths.after(pathfinding::run_path_jobs));
+        schedule.add_system(animate_units.after(follow_unit_paths).after(steering::steer_flow_followers));
+    }
+
+    pub fn get(&self, kind: UnitKindId) -> &UnitDefinition {
+        &self.definitions[kind as usize]
+    }
+
+    pub fn id(&self, name: &str) -> Option<UnitKindId> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (UnitKindId, &UnitDefinition)> {
+        self.definitions.iter().enumerate().map(|(id, definition)| (id as UnitKindId, definition))
+    }
+}
+
+impl Default for UnitRegistry {
+    fn default() -> Self {
+        Self::new(vec![
+            UnitDefinition::new("villager", 0, 2.0, 0.25),
+            UnitDefinition::new("soldier", 8, 1.6, 0.3),
+            // sheep trot with a short bounce instead of a walk cycle
+            UnitDefinition::new("sheep", 16, 1.2, 0.3)
+                .with_walk(UnitAnimation::new(1, 2, 0.2, AnimationMode::PingPong)),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct Unit {
+    pub kind: UnitKindId,
+}
+
+/// Current animation of a unit and how long it has been playing.
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+pub struct AnimationPlayer {
+    pub state: UnitAnimationState,
+    pub elapsed: f32,
+}
+
+impl AnimationPlayer {
+    /// Restarts the animation when the state changes.
+    pub fn play(&mut self, state: UnitAnimationState) {
+        if self.state != state {
+            self.state = state;
+            self.elapsed = 0.0;
+        }
+    }
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        Self { state: UnitAnimationState::Idle, elapsed: 0.0 }
+    }
+}
+
+/// Components every unit has, villagers, soldiers and animals only differ in their kind.
+#[derive(Bundle)]
+pub struct UnitBundle {
+    pub unit: Unit,
+    pub position: TilePosition,
+    pub velocity: Velocity,
+    pub facing: Facing,
+    pub animation: AnimationPlayer,
+}
+
+impl UnitBundle {
+    pub fn new(kind: UnitKindId, position: Vector2<f32>) -> Self {
+        Self {
+            unit: Unit { kind },
+            position: TilePosition(position),
+            velocity: Velocity(Vector2::zero()),
+            facing: Facing::South,
+            animation: AnimationPlayer::default(),
+        }
+    }
+}
+
+/// Walks the unit to `goal` on a path from the `Pathfinder`, removed once the unit arrived or no path exists.
+#[derive(Debug, Clone, Component)]
+pub struct PathFollower {
+    goal: Vector2<i32>,
+    request: Option<PathRequestId>,
+    waypoints: Vec<Vector2<i32>>,
+    next_waypoint: usize,
+    /// Hops of a long path, the waypoints hold the refined part up to the current hop.
+    portals: Option<HierarchicalPath>,
+}
+
+impl PathFollower {
+    pub fn new(goal: Vector2<i32>) -> Self {
+        Self { goal, request: None, waypoints: Vec::new(), next_waypoint: 0, portals: None }
+    }
+
+    pub fn goal(&self) -> Vector2<i32> {
+        self.goal
+    }
+
+    /// Whether the unit is still waiting for its path.
+    pub fn is_planning(&self) -> bool {
+        self.waypoints.is_empty()
+    }
+}
+
+fn tile_centre(tile: Vector2<i32>) -> Vector2<f32> {
+    Vector2::new(tile.x as f32 + 0.5, tile.y as f32 + 0.5)
+}
+
+/// Asks for paths of new followers and collects finished ones, followers without a path stop. Requests of
+/// followers that were removed, replaced or despawned while they waited are cancelled.
+pub fn request_unit_paths(
+    mut commands: Commands,
+    mut pathfinder: ResMut<Pathfinder>,
+    grid: Res<CostGrid>,
+    mut followers: Query<(Entity, &TilePosition, &mut PathFollower)>,
+    mut removed: RemovedComponents<PathFollower>,
+    mut requests: Local<HashMap<Entity, PathRequestId>>,
+) {
+    for entity in removed.iter() {
+        if let Some(request) = requests.remove(&entity) {
+            pathfinder.cancel(request);
+        }
+    }
+    for (entity, position, mut follower) in followers.iter_mut().filter(|(.., follower)| follower.is_planning()) {
+        let Some(request) = follower.request else {
+            // a new follower that replaced one still waiting for its path
+            if let Some(previous) = requests.remove(&entity) {
+                pathfinder.cancel(previous);
+            }
+            let request = pathfinder.request(&grid, position.tile(), follower.goal);
+            follower.request = Some(request);
+            requests.insert(entity, request);
+            continue;
+        };
+        let result = pathfinder.take_result(request);
+        if result.is_some() {
+            requests.remove(&entity);
+        }
+        match result {
+            Some(PathResult::Found(path)) => {
+                follower.request = None;
+                follower.waypoints = path.waypoints;
+                follower.next_waypoint = 0;
+            }
+            Some(PathResult::Portals(path)) => {
+                follower.request = None;
+                follower.waypoints = vec![path.hops()[0]];
+                follower.next_waypoint = 0;
+                follower.portals = Some(path);
+            }
+            Some(PathResult::NoPath) => {
+                commands.entity(entity).remove::<PathFollower>();
+            }
+            None if !pathfinder.is_pending(request) => follower.request = None,
+            None => {}
+        }
+    }
+}
+
+/// Moves followers towards their next waypoint, a path that got blocked is planned again.
+pub fn follow_unit_paths(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    grid: Res<CostGrid>,
+    registry: Res<UnitRegistry>,
+    mut followers: Query<(Entity, &Unit, &mut TilePosition, &mut Velocity, &mut PathFollower)>,
+) {
+    let delta = time.delta as f32;
+    for (entity, unit, mut position, mut velocity, mut follower) in followers.iter_mut() {
+        if follower.is_planning() {
+            velocity.0 = Vector2::zero();
+            continue;
+        }
+        let speed = registry.get(unit.kind).max_speed / grid.cost(position.tile()).unwrap_or(1.0);
+        let mut travel = speed * delta;
+        while travel > 0.0 {
+            let Some(waypoint) = follower.waypoints.get(follower.next_waypoint).copied() else {
+                // the next hop of a long path is turned into tiles only once the unit got there
+                match follower.portals.as_mut().map(|path| path.refine_next(&grid)) {
+                    Some(Refinement::Segment(segment)) => {
+                        follower.waypoints = segment;
+                        follower.next_waypoint = 0;
+                        continue;
+                    }
+                    Some(Refinement::Blocked) => {
+                        *follower = PathFollower::new(follower.goal);
+                        velocity.0 = Vector2::zero();
+                        break;
+                    }
+                    Some(Refinement::Finished) | None => {}
+                }
+                velocity.0 = Vector2::zero();
+                commands.entity(entity).remove::<PathFollower>();
+                break;
+            };
+            let offset = tile_centre(waypoint) - position.0;
+            let distance = offset.magnitude();
+            if distance <= travel {
+                position.0 = tile_centre(waypoint);
+                follower.next_waypoint += 1;
+                travel -= distance;
+                continue;
+            }
+            let moved = position.0 + offset / distance * travel;
+            if !grid.is_passable(TilePosition(moved).tile()) {
+                *follower = PathFollower::new(follower.goal);
+                velocity.0 = Vector2::zero();
+                break;
+            }
+            velocity.0 = offset / distance * speed;
+            position.0 = moved;
+            break;
+        }
+    }
+}
+
+/// Turns units towards their movement and switches between standing and walking.
+pub fn animate_units(time: Res<GameTime>, mut units: Query<(&Velocity, &mut Facing, &mut AnimationPlayer), With<Unit>>) {
+    for (velocity, mut facing, mut animation) in units.iter_mut() {
+        if velocity.0.magnitude() < WALK_THRESHOLD {
+            animation.play(UnitAnimationState::Idle);
+        } else {
+            animation.play(UnitAnimationState::Walk);
+            if let Some(new_facing) = Facing::from_direction(velocity.0) {
+                if *facing != new_facing {
+                    *facing = new_facing;
+                }
+            }
+        }
+        animation.elapsed += time.delta as f32;
+    }
+}
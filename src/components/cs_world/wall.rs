@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+
+use bevy_ecs::query::With;
+use bevy_ecs::world::World;
+use cgmath::Vector2;
+
+use crate::components::cs_world::building::{BuildingCost, Occupancy, PlacementError};
+use crate::components::cs_world::history;
+use crate::components::cs_world::map::{Map, TileEdit};
+use crate::components::cs_world::position::TilePosition;
+use crate::components::cs_world::tile_registry::{TileKindId, TileRegistry};
+use crate::components::cs_world::unit::Unit;
+
+/// Walls with towers get one at least every this many segments.
+pub const TOWER_SPACING: usize = 6;
+/// Tiles new wall segments can go on.
+const WALL_TERRAIN: [&str; 2] = ["grass", "road"];
+
+/// Tile kinds that make up castle walls, they all autotile with each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallPiece {
+    Wall,
+    Tower,
+    Gatehouse,
+}
+
+impl WallPiece {
+    pub fn tile_name(self) -> &'static str {
+        match self {
+            WallPiece::Wall => "wall",
+            WallPiece::Tower => "wall_tower",
+            WallPiece::Gatehouse => "gatehouse",
+        }
+    }
+
+    pub fn cost(self) -> BuildingCost {
+        match self {
+            WallPiece::Wall => BuildingCost { stone: 2, ..Default::default() },
+            WallPiece::Tower => BuildingCost { stone: 12, ..Default::default() },
+            WallPiece::Gatehouse => BuildingCost { wood: 10, stone: 20, ..Default::default() },
+        }
+    }
+
+    fn from_tile_name(name: &str) -> Option<Self> {
+        [WallPiece::Wall, WallPiece::Tower, WallPiece::Gatehouse].into_iter().find(|piece| piece.tile_name() == name)
+    }
+}
+
+/// Tiles from `start` to `end` along the map axes. Straight when both share a row or column,
+/// otherwise an L that runs along the longer side first.
+pub fn drag_path(start: Vector2<i32>, end: Vector2<i32>) -> Vec<Vector2<i32>> {
+    let delta = end - start;
+    let corner = match delta.x.abs() >= delta.y.abs() {
+        true => Vector2::new(end.x, start.y),
+        false => Vector2::new(start.x, end.y),
+    };
+    let mut path = vec![start];
+    for (from, to) in [(start, corner), (corner, end)] {
+        let step = Vector2::new((to.x - from.x).signum(), (to.y - from.y).signum());
+        let mut tile = from;
+        while tile != to {
+            tile += step;
+            path.push(tile);
+        }
+    }
+    path
+}
+
+/// What a wall tool would change, tiles that cannot be built on are skipped with their reason.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WallPlan {
+    pub changes: Vec<(Vector2<i32>, TileKindId)>,
+    pub cost: BuildingCost,
+    pub blocked: Vec<(Vector2<i32>, PlacementError)>,
+}
+
+impl WallPlan {
+    /// Why some of the tiles are skipped, shown next to the tool.
+    pub fn blocked_reason(&self) -> Option<String> {
+        self.blocked.first().map(|(_, error)| error.to_string())
+    }
+
+    fn add(&mut self, pos: Vector2<i32>, piece: WallPiece, registry: &TileRegistry) {
+        self.changes.push((pos, registry.id(piece.tile_name()).unwrap()));
+        self.cost += piece.cost();
+    }
+}
+
+/// Plans wall segments along `path`. Existing walls are kept and only turned into towers where one is wanted.
+pub fn plan_walls(world: &mut World, path: &[Vector2<i32>], towers: bool) -> WallPlan {
+    let unit_tiles = unit_tiles(world);
+    let map = world.resource::<Map>();
+    let registry = world.resource::<TileRegistry>();
+    let occupancy = world.resource::<Occupancy>();
+    let mut plan = WallPlan::default();
+    for (index, tile) in path.iter().enumerate() {
+        let is_corner = index > 0 && index + 1 < path.len() && {
+            let before = *tile - path[index - 1];
+            let after = path[index + 1] - *tile;
+            before != after
+        };
+        let wants_tower = towers && (index == 0 || index + 1 == path.len() || is_corner || index % TOWER_SPACING == 0);
+        let piece = if wants_tower { WallPiece::Tower } else { WallPiece::Wall };
+
+        let Some(kind) = map.kind(*tile) else {
+            plan.blocked.push((*tile, PlacementError::OutsideMap));
+            continue;
+        };
+        if occupancy.is_occupied(*tile) {
+            plan.blocked.push((*tile, PlacementError::Occupied));
+            continue;
+        }
+        let name = &registry.get(kind).name;
+        match WallPiece::from_tile_name(name) {
+            // walls only ever get stronger, gates stay open
+            Some(WallPiece::Wall) if piece == WallPiece::Tower => plan.add(*tile, piece, registry),
+            Some(_) => {}
+            None if !WALL_TERRAIN.contains(&name.as_str()) => plan.blocked.push((*tile, PlacementError::WrongTerrain(name.clone()))),
+            None if unit_tiles.contains(tile) => plan.blocked.push((*tile, PlacementError::UnitsInTheWay)),
+            None => plan.add(*tile, piece, registry),
+        }
+    }
+    plan
+}
+
+/// Plans a gatehouse into the straight wall through `tile`, the segments on both sides become its towers.
+pub fn plan_gatehouse(world: &World, tile: Vector2<i32>) -> Result<WallPlan, PlacementError> {
+    let map = world.resource::<Map>();
+    let registry = world.resource::<TileRegistry>();
+    let piece_at = |pos: Vector2<i32>| map.kind(pos).and_then(|kind| WallPiece::from_tile_name(&registry.get(kind).name));
+    match map.kind(tile) {
+        None => return Err(PlacementError::OutsideMap),
+        Some(_) if piece_at(tile) != Some(WallPiece::Wall) => return Err(PlacementError::NotOnWall),
+        Some(_) => {}
+    }
+    let is_flank = |pos: Vector2<i32>| matches!(piece_at(pos), Some(WallPiece::Wall) | Some(WallPiece::Tower));
+    let along_x = is_flank(tile + Vector2::new(1, 0)) && is_flank(tile - Vector2::new(1, 0));
+    let along_y = is_flank(tile + Vector2::new(0, 1)) && is_flank(tile - Vector2::new(0, 1));
+    let crossing = |offset: Vector2<i32>| piece_at(tile + offset).is_some() || piece_at(tile - offset).is_some();
+    let axis = match (along_x, along_y) {
+        (true, false) if !crossing(Vector2::new(0, 1)) => Vector2::new(1, 0),
+        (false, true) if !crossing(Vector2::new(1, 0)) => Vector2::new(0, 1),
+        _ => return Err(PlacementError::NotStraight),
+    };
+
+    let mut plan = WallPlan::default();
+    plan.add(tile, WallPiece::Gatehouse, registry);
+    for flank in [tile - axis, tile + axis] {
+        if piece_at(flank) == Some(WallPiece::Wall) {
+            plan.add(flank, WallPiece::Tower, registry);
+        }
+    }
+    Ok(plan)
+}
+
+/// Builds the planned pieces as one edit in the history, so a whole drag is undone at once.
+pub fn build_walls(world: &mut World, plan: &WallPlan, label: &str) {
+    let edit = TileEdit::new(world.resource::<Map>(), &plan.changes);
+    if !edit.is_empty() {
+        history::execute(world, label, edit);
+    }
+}
+
+fn unit_tiles(world: &mut World) -> HashSet<Vector2<i32>> {
+    let mut units = world.query_filtered::<&TilePosition, With<Unit>>();
+    units.iter(world).map(|position| position.tile()).collect()
+}
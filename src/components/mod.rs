@@ -1,4 +1,5 @@
 pub mod cs_world;
 pub mod cs_io;
 pub mod cs_util;
-pub mod cs_render;
\ No newline at end of file
+pub mod cs_render;
+pub mod cs_ui;
\ No newline at end of file
@@ -1,36 +1,67 @@
-use std::path::Path;
 use std::time::Duration;
 
 use bevy_ecs::change_detection::DetectChangesMut;
-use bevy_ecs::schedule::Schedule;
+use bevy_ecs::schedule::{IntoSystemConfig, Schedule};
 use bevy_ecs::system::{Resource};
 use bevy_ecs::world::World;
 use cgmath::Vector2;
 use instant::Instant;
-use wgpu::{BindGroup, Buffer, ComputePipeline, Device, Queue, RenderPipeline, Surface, SurfaceConfiguration};
+use wgpu::{BindGroup, Buffer, ComputePipeline, Device, Queue, RenderPipeline, ShaderModule, Surface, SurfaceConfiguration};
 use winit::dpi::{LogicalSize, PhysicalSize};
-use winit::event::VirtualKeyCode;
+use winit::event::{MouseButton, VirtualKeyCode};
 use winit::event_loop::{EventLoop};
 use winit::window::{WindowBuilder};
 
 use crate::components::cs_io;
-use crate::components::cs_io::{AssetIo, loading_state};
+use crate::components::cs_io::assets::Assets;
+use crate::components::cs_io::loading_state;
+use crate::components::cs_render::minimap::{Minimap, MinimapImage};
+use crate::components::cs_render::font::FontAtlas;
+use crate::components::cs_render::gpu_timings::GpuProfiler;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::components::cs_render::hot_reload;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::components::cs_render::hot_reload::{RendererShaders, ShaderSource, WorldDataSources, WorldPipelineSources};
+use crate::components::cs_render::overlay;
+use crate::components::cs_render::overlay::OverlayRenderer;
 use crate::components::cs_render::render_loop::{render_game_world};
 use crate::components::cs_render::shader::{texture_sampler_binding};
 use crate::components::cs_render::shader::camera_binding::CameraBinding;
 use crate::components::cs_render::shader::compute_shader_binding::ComputeParamsBinding;
+use crate::components::cs_render::shader::fog_of_war_binding::{create_fog_of_war_buffer, FogOfWarBinding};
+use crate::components::cs_render::shader::tile_animation_binding::TileAnimationBinding;
 use crate::components::cs_render::shader_types::camera_uniform::CameraUniform;
 use crate::components::cs_render::shader_types::compute_params_uniform::ComputeParamsUniform;
 use crate::components::cs_render::shader_types::texture::Texture;
+use crate::components::cs_render::sprite::SpriteRenderer;
+use crate::components::cs_render::text::{TextRenderer, WorldLabel};
 use crate::components::cs_render::world_render_pipline;
+use crate::components::cs_ui::ui_layer::UiLayer;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::components::cs_ui::loading_screen::LoadingScreen;
 use crate::components::cs_util::camera::CustomCamera;
 use crate::components::cs_util::cs_window::{check_window_events, platform_specific_init, State};
 #[cfg(target_arch = "wasm32")]
 use crate::components::cs_util::cs_window::WinitWebResizing;
-use crate::components::cs_util::fps_counter::FPSCounter;
-use crate::components::cs_util::input::Input;
+use crate::components::cs_ui::performance_overlay;
+use crate::components::cs_ui::placement_window;
+use crate::components::cs_ui::editor_window;
+use crate::components::cs_util::input::{Cursor, Input};
+use crate::components::cs_util::performance;
+use crate::components::cs_util::performance::{PerformanceOverlay, PerformanceStats};
+use crate::components::cs_util::time::GameTime;
+use crate::components::cs_world::building::BuildingRegistry;
+use crate::components::cs_world::editor::MapEditor;
+use crate::components::cs_world::flow_field::FlowFields;
+use crate::components::cs_world::fog_of_war::{FogOfWar, VisionSource};
 use crate::components::cs_world::map;
-use crate::components::cs_world::map::SIZE;
+use crate::components::cs_world::hierarchical_pathfinding::PortalGraph;
+use crate::components::cs_world::history::History;
+use crate::components::cs_world::pathfinding::{CostGrid, Pathfinder};
+use crate::components::cs_world::position::TilePosition;
+use crate::components::cs_world::map_data::{MapData, START_MAP};
+use crate::components::cs_world::tile_registry::{TileRegistry, TILE_DEFINITIONS};
+use crate::components::cs_world::unit::{PathFollower, UnitBundle, UnitRegistry};
 
 #[derive(Resource)]
 pub struct Render {
@@ -52,7 +83,7 @@ pub struct DummyTest {
     pub(crate) instance_buffer_bind_group: BindGroup,
     pub(crate) compute_buffer_bind_group: BindGroup,
     pub(crate) compute_visible_buffer_bind_group: BindGroup,
-
+    pub(crate) all_tiles_buffer: Buffer,
 }
 
 pub async fn main_loop(width: u32, height: u32) {
@@ -61,70 +92,104 @@ pub async fn main_loop(width: u32, height: u32) {
     let (event_loop, mut state, render) = init_window(&mut world, width, height).await;
 
 
-    let asset_io: Box<dyn AssetIo> = cs_io::get_asset_store();
+    let mut assets = Assets::new(cs_io::get_asset_store().await);
+    let diffuse_texture = assets.load::<Texture>("assets/tiles30x64.png");
+    let shader = assets.load::<ShaderModule>("assets/shaders/instancing.wgsl");
+    let overlay_shader = assets.load::<ShaderModule>("assets/shaders/overlay.wgsl");
+    let text_shader = assets.load::<ShaderModule>("assets/shaders/text.wgsl");
+    let minimap_shader = assets.load::<ShaderModule>("assets/shaders/minimap.wgsl");
+    let sprite_shader = assets.load::<ShaderModule>("assets/shaders/sprite.wgsl");
+    let sprite_sheet = assets.load::<Texture>("assets/sprites.png");
+    let tile_definitions = assets.load::<TileRegistry>(TILE_DEFINITIONS);
+    let start_map = assets.load::<MapData>(START_MAP);
+    let font = assets.load::<FontAtlas>("assets/fonts/DejaVuSans.ttf");
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut loading_screen = LoadingScreen::new(&render);
+    assets.load_pending_with_progress(&render.device, &render.queue, |progress| {
+        loading_state::set_loading(Some(progress));
+        #[cfg(not(target_arch = "wasm32"))]
+        loading_screen.draw(&render, state.window(), progress);
+    }).await;
 
     //entity world
 
     let mut update_schedule = Schedule::default();
+    // runs once per rendered frame between UiLayer::begin_frame and UiLayer::end_frame
+    let mut ui_schedule = Schedule::default();
     //
 
     //image i draw
-    let diffuse_bytes = asset_io.load_path(Path::new("assets\\tiles30x64.png")).await;
-    let value_image = diffuse_bytes.unwrap();
-    let diffuse_image = Texture::from_bytes(&render.device, &render.queue, &value_image, "diffuse_texture").unwrap();
     let texture_bind_group_layout = texture_sampler_binding::create_texture_group_layout(&render.device);
     let diffuse_bind_group = texture_sampler_binding::create_diffuse_bind_group(
         &render.device,
-        &diffuse_image,
+        &diffuse_texture.expect_loaded(),
         &texture_bind_group_layout,
     );
     //
 
     //render pipline
-    let shader = world_render_pipline::load_shader(
-        &render.device,
-        &asset_io,
-        "assets\\shaders\\instancing.wgsl",
-    ).await;
     let geometry_buffer = world_render_pipline::create_geometry_buffer(&render.device);
     //
 
+    //map stuff
+    let tile_registry = TileRegistry::clone(&tile_definitions.expect_loaded());
+    let map = match start_map.get().map(|data| data.to_map(&tile_registry)) {
+        Some(Ok(map)) => map,
+        Some(Err(error)) => {
+            log::error!("generating a map, {} does not fit the tile definitions: {}", START_MAP, error);
+            map::generate_instances(&tile_registry)
+        }
+        None => map::generate_instances(&tile_registry),
+    };
+    // the start map may have any size, the camera and the starting area are placed at its centre
+    let center = map.size / 2;
+    //map stuff end
+
     //camera
     let camera = CustomCamera::new(
-        Vector2::new((SIZE / 2) as f32, (SIZE/2) as f32),
+        Vector2::new(center.x as f32, center.y as f32),
         //Vector2::new((0 / 2) as f32, (0/2) as f32),
         Vector2::new(render.config.width as f32, render.config.height as f32),
         &mut world,
         &mut update_schedule,
     );
+    world.insert_resource(GameTime::default());
     let camera_uniform = CameraUniform::new(&camera, &mut world, &mut update_schedule);
     let camera_bind_group = CameraBinding::new(&render.device, &camera_uniform, &mut world, &mut update_schedule);
     //camera end
 
-    //map stuff
-    let mut map = map::generate_instances();
-    //map stuff end
+    let fog_of_war_buffer = create_fog_of_war_buffer(&render.device, map.tiles.len());
+    let (instance_buffer_bind_group_layout, instance_buffer_bind_group, visible_tiles_buffer, visible_tile_indices_buffer) = world_render_pipline::create_visible_buffer(&render.device, &map.tiles, &fog_of_war_buffer);
+    FogOfWarBinding::register(fog_of_war_buffer, &mut world, &mut update_schedule);
+    FogOfWar::new(map.size, 1).register(&mut world, &mut update_schedule);
+    // starting area of the local player
+    world.spawn((
+        TilePosition(Vector2::new(center.x as f32, center.y as f32)),
+        VisionSource { player: 0, radius: 24.0 },
+        WorldLabel::new("Start"),
+    ));
 
-    let (instance_buffer_bind_group_layout, instance_buffer_bind_group, visible_tiles_buffer) = world_render_pipline::create_visible_buffer(&render.device, &mut map.tiles);
 
+    let tile_animation_bind_group_layout = TileAnimationBinding::new(&render.device, &tile_registry, &mut world);
 
     let bind_group_layout = [
         &texture_bind_group_layout,
         &camera_bind_group,
         &instance_buffer_bind_group_layout,
+        &tile_animation_bind_group_layout,
     ];
 
     let render_pipeline = world_render_pipline::create_render_pipline(
         &render.device,
         &render.config,
-        &shader,
+        &shader.expect_loaded(),
         &bind_group_layout,
     );
 
-    let compute_params_uniform = ComputeParamsUniform::new(&camera, &mut world, &mut update_schedule);
+    let compute_params_uniform = ComputeParamsUniform::new(&camera, map.size, &mut world, &mut update_schedule);
     let compute_params_bind_group = ComputeParamsBinding::new(&render.device, &compute_params_uniform, &mut world, &mut update_schedule);
-    let (compute_buffer_bind_group_layout, compute_buffer_bind_group) = world_render_pipline::create_compute_all_tiles_buffer(&render.device, &mut map.tiles);
-    let (compute_visible_buffer_bind_group_layout, compute_visible_buffer_bind_group) = world_render_pipline::create_compute_visible_tiles_buffer(&render.device, visible_tiles_buffer);
+    let (compute_buffer_bind_group_layout, compute_buffer_bind_group, all_tiles_buffer) = world_render_pipline::create_compute_all_tiles_buffer(&render.device, &map.tiles);
+    let (compute_visible_buffer_bind_group_layout, compute_visible_buffer_bind_group) = world_render_pipline::create_compute_visible_tiles_buffer(&render.device, visible_tiles_buffer, visible_tile_indices_buffer);
 
     let bind_group_layout = [
         &compute_params_bind_group,
@@ -134,7 +199,7 @@ pub async fn main_loop(width: u32, height: u32) {
 
     let compute_pipeline = world_render_pipline::create_compute_pipline(
         &render.device,
-        &shader,
+        &shader.expect_loaded(),
         &bind_group_layout,
     );
 
@@ -146,14 +211,96 @@ pub async fn main_loop(width: u32, height: u32) {
         instance_buffer_bind_group,
         compute_buffer_bind_group,
         compute_visible_buffer_bind_group,
+        all_tiles_buffer,
     };
 
     world.insert_resource(dummy_test);
+    let overlay_renderer = OverlayRenderer::new(&render.device, render.config.format, &overlay_shader.expect_loaded(), &camera_bind_group);
+    OverlayRenderer::register(overlay_renderer, &mut world, &mut update_schedule);
+    ui_schedule.add_system(overlay::overlay_settings_window);
+
+    let text_renderer = TextRenderer::create(
+        &render.device,
+        &render.queue,
+        render.config.format,
+        &text_shader.expect_loaded(),
+        &camera_bind_group,
+        FontAtlas::clone(&font.expect_loaded()),
+        Vector2::new(render.config.width as f32, render.config.height as f32),
+    );
+    TextRenderer::register(text_renderer, &mut world, &mut update_schedule);
+    let sprite_renderer = SpriteRenderer::create(
+        &render.device,
+        render.config.format,
+        &sprite_shader.expect_loaded(),
+        &camera_bind_group,
+        &sprite_sheet.expect_loaded(),
+    );
+    SpriteRenderer::register(sprite_renderer, &mut world, &mut update_schedule);
+
+    let minimap = Minimap::create(
+        &render.device,
+        &render.queue,
+        render.config.format,
+        &minimap_shader.expect_loaded(),
+        MinimapImage::from_map(&map, &tile_registry),
+        Vector2::new(render.config.width as f32, render.config.height as f32),
+    );
+    Minimap::register(minimap, &mut world, &mut update_schedule);
+    let cost_grid = CostGrid::from_map(&map, &tile_registry);
+    PortalGraph::build(&cost_grid).register(&mut world, &mut update_schedule);
+    Pathfinder::default().register(cost_grid, &mut world, &mut update_schedule);
+    FlowFields::default().register(&mut world, &mut update_schedule);
+    let unit_registry = UnitRegistry::default();
+    // a few units walking off from the starting area
+    let start = center;
+    for (index, kind) in ["villager", "villager", "soldier", "sheep"].iter().enumerate() {
+        let offset = Vector2::new(index as f32 * 0.6, 1.0);
+        world.spawn((
+            UnitBundle::new(unit_registry.id(kind).unwrap(), Vector2::new(start.x as f32 + 0.5, start.y as f32 + 0.5) + offset),
+            PathFollower::new(start + Vector2::new(8 - index as i32 * 4, 6)),
+        ));
+    }
+    unit_registry.register(&mut world, &mut update_schedule);
+    BuildingRegistry::default().register(map.size, &mut world, &mut update_schedule);
+    History::default().register(&mut world, &mut update_schedule);
+    ui_schedule.add_system(placement_window::placement_window);
+    world.insert_resource(cs_io::UserStore(cs_io::get_user_store()));
+    MapEditor::default().register(&mut world, &mut update_schedule);
+    ui_schedule.add_system(editor_window::editor_window);
+    map::insert_map(map, &mut world, &mut update_schedule);
+    world.insert_resource(tile_registry);
+    update_schedule.add_system(world_render_pipline::upload_changed_tiles.after(map::publish_tile_changes));
+    update_schedule.add_system(world_render_pipline::upload_tile_preview.after(world_render_pipline::upload_changed_tiles));
     world.insert_resource(<Input<VirtualKeyCode>>::default());
+    world.insert_resource(<Input<MouseButton>>::default());
+    world.insert_resource(Cursor::default());
+    UiLayer::register(&render.device, render.config.format, state.window(), &mut world);
+    GpuProfiler::register(&render.device, &render.queue, &mut world);
     world.insert_resource(render);
+    world.insert_resource(assets);
+    #[cfg(not(target_arch = "wasm32"))]
+    world.insert_resource(WorldPipelineSources::new(
+        shader,
+        diffuse_texture,
+        [texture_bind_group_layout, camera_bind_group, instance_buffer_bind_group_layout, tile_animation_bind_group_layout],
+        [compute_params_bind_group, compute_buffer_bind_group_layout, compute_visible_buffer_bind_group_layout],
+    ));
+    #[cfg(not(target_arch = "wasm32"))]
+    world.insert_resource(RendererShaders {
+        overlay: ShaderSource::new(overlay_shader),
+        text: ShaderSource::new(text_shader),
+        minimap: ShaderSource::new(minimap_shader),
+        sprite: ShaderSource::new(sprite_shader),
+    });
+    #[cfg(not(target_arch = "wasm32"))]
+    world.insert_resource(WorldDataSources::new(tile_definitions, start_map));
+    loading_state::set_loading(None);
     loading_state::set_loading_finish();
-    let mut fps_counter = FPSCounter::new();
-    let mut _t: f64 = 0.0;
+    world.insert_resource(PerformanceStats::default());
+    world.insert_resource(PerformanceOverlay::default());
+    update_schedule.add_system(performance::toggle_performance_overlay);
+    ui_schedule.add_system(performance_overlay::performance_window);
     let dt: f64 = 0.01;
     let mut current_time = Instant::now();
     let mut accumulator = 0.0;
@@ -167,30 +314,46 @@ pub async fn main_loop(width: u32, height: u32) {
             }
 
             winit::event::Event::RedrawRequested(window_id) if window_id == state.window().id() => {
+                #[cfg(not(target_arch = "wasm32"))]
+                hot_reload::reload_changed_assets(&mut world);
+
                 //https://gafferongames.com/post/fix_your_timestep/
                 let new_time = Instant::now();
-                let mut frame_time = new_time - current_time;
+                let measured_frame_time = new_time - current_time;
+                let mut frame_time = measured_frame_time;
                 if frame_time.as_secs_f64() > 0.25 {
                     frame_time = Duration::from_secs_f64(0.25);
                 }
                 current_time = new_time;
 
                 accumulator += frame_time.as_secs_f64();
-                fps_counter.tick(frame_time);
                 while accumulator >= dt {
-                    _t += dt;
+                    let mut time = world.get_resource_mut::<GameTime>().unwrap();
+                    time.elapsed += dt;
+                    time.delta = dt;
 
+                    let tick_start = Instant::now();
                     update_schedule.run(&mut world);
+                    world.get_resource_mut::<PerformanceStats>().unwrap().record_tick(tick_start.elapsed());
 
                     let mut key_input = world.get_resource_mut::<Input<VirtualKeyCode>>().unwrap();
                     key_input.bypass_change_detection();
                     key_input.clear();
-
+                    let mut mouse_input = world.get_resource_mut::<Input<MouseButton>>().unwrap();
+                    mouse_input.bypass_change_detection();
+                    mouse_input.clear();
+                    // removed components are reported for two updates, so systems running before the removal see it too
+                    world.clear_trackers();
 
                     accumulator -= dt;
                 }
 
                 let _alpha = accumulator / dt;
+                world.get_resource_mut::<PerformanceStats>().unwrap().record_frame(measured_frame_time);
+
+                UiLayer::begin_frame(&mut world, state.window());
+                ui_schedule.run(&mut world);
+                UiLayer::end_frame(&mut world, state.window());
 
                 render_game_world(&mut world, &mut state, control_flow);
             }
@@ -0,0 +1,93 @@
+mod common;
+
+use std::fs;
+
+use castle_sim::components::cs_io::asset_pack::{Compression, PackError, PackIndex, PackWriter, HEADER_LEN};
+use castle_sim::components::cs_io::{AssetIoError, PackAssetIo};
+use common::{load, path};
+
+fn pack() -> Vec<u8> {
+    let mut writer = PackWriter::new();
+    writer.add(path("assets/shaders/shader.wgsl"), "fn main() {}\n".repeat(64).into_bytes());
+    writer.add(path("assets/tiles.png"), vec![7, 1, 200]);
+    writer.to_bytes()
+}
+
+#[test]
+fn index_stores_compression_per_entry() {
+    let index = PackIndex::parse(&pack()).unwrap();
+    assert_eq!(index.len(), 2);
+    assert_eq!(index.get(&path("assets/shaders/shader.wgsl")).unwrap().compression, Compression::Deflate);
+    assert_eq!(index.get(&path("assets/tiles.png")).unwrap().compression, Compression::None);
+}
+
+#[test]
+fn reads_entries_from_memory_and_file() {
+    let bytes = pack();
+    let file = std::env::temp_dir().join(format!("castle_sim_pack_{}.pack", std::process::id()));
+    fs::write(&file, &bytes).unwrap();
+
+    for asset_io in [PackAssetIo::from_bytes(bytes).unwrap(), PackAssetIo::open(&file).unwrap()] {
+        assert_eq!(load(&asset_io, "assets/tiles.png").unwrap(), vec![7, 1, 200]);
+        assert_eq!(load(&asset_io, "assets/shaders/shader.wgsl").unwrap(), "fn main() {}\n".repeat(64).into_bytes());
+        assert!(matches!(load(&asset_io, "assets/missing.png"), Err(AssetIoError::NotFound(_))));
+    }
+    fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn damaged_entry_fails_the_checksum() {
+    let mut bytes = pack();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    let asset_io = PackAssetIo::from_bytes(bytes).unwrap();
+    assert!(matches!(load(&asset_io, "assets/tiles.png"), Err(AssetIoError::Decode(_))));
+}
+
+#[test]
+fn truncated_packs_are_rejected() {
+    let bytes = pack();
+    let index_end = HEADER_LEN + u32::from_le_bytes(bytes[10..14].try_into().unwrap()) as usize;
+    for len in [0, 3, HEADER_LEN - 1, HEADER_LEN, index_end - 1] {
+        assert!(matches!(PackIndex::parse(&bytes[..len]), Err(PackError::UnexpectedEnd)), "cut at {len}");
+    }
+    let file = std::env::temp_dir().join(format!("castle_sim_truncated_{}.pack", std::process::id()));
+    fs::write(&file, &bytes[..index_end - 1]).unwrap();
+    assert!(matches!(PackAssetIo::open(&file), Err(PackError::UnexpectedEnd)));
+
+    // a pack cut in its data still opens, only the entries past the end fail
+    fs::write(&file, &bytes[..bytes.len() - 1]).unwrap();
+    for asset_io in [PackAssetIo::from_bytes(bytes[..bytes.len() - 1].to_vec()).unwrap(), PackAssetIo::open(&file).unwrap()] {
+        assert!(matches!(load(&asset_io, "assets/tiles.png"), Err(AssetIoError::Decode(_))));
+    }
+    fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn garbage_headers_are_rejected_before_allocating() {
+    assert!(matches!(PackIndex::parse(b"PNG\x00garbage garbage"), Err(PackError::InvalidMagic)));
+    let mut bytes = pack();
+    bytes[4..6].copy_from_slice(&7u16.to_le_bytes());
+    assert!(matches!(PackIndex::parse(&bytes), Err(PackError::UnsupportedVersion(7))));
+
+    // counts and lengths near u32::MAX must not be trusted
+    let mut bytes = pack();
+    bytes[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(PackIndex::parse(&bytes), Err(PackError::UnexpectedEnd)));
+    let mut bytes = pack();
+    bytes[10..14].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(PackIndex::parse(&bytes), Err(PackError::UnexpectedEnd)));
+    let file = std::env::temp_dir().join(format!("castle_sim_garbage_{}.pack", std::process::id()));
+    fs::write(&file, &bytes).unwrap();
+    assert!(matches!(PackAssetIo::open(&file), Err(PackError::UnexpectedEnd)));
+
+    // an entry pointing past the end of the pack
+    let mut bytes = pack();
+    let offset = HEADER_LEN + 2 + "assets/shaders/shader.wgsl".len();
+    bytes[offset..offset + 8].copy_from_slice(&(u64::MAX - 2).to_le_bytes());
+    fs::write(&file, &bytes).unwrap();
+    for asset_io in [PackAssetIo::from_bytes(bytes).unwrap(), PackAssetIo::open(&file).unwrap()] {
+        assert!(matches!(load(&asset_io, "assets/shaders/shader.wgsl"), Err(AssetIoError::Decode(_))));
+    }
+    fs::remove_file(file).unwrap();
+}
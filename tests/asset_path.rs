@@ -0,0 +1,45 @@
+use castle_sim::components::cs_io::asset_path::{AssetPath, AssetPathError};
+
+#[test]
+fn paths_are_normalized() {
+    let path = AssetPath::new(r"assets\shaders//./old/../text.wgsl").unwrap();
+    assert_eq!(path.as_str(), "assets/shaders/text.wgsl");
+    assert_eq!(path.file_name(), "text.wgsl");
+    assert_eq!(path.extension(), Some("wgsl"));
+    assert_eq!(path.parent(), Some(AssetPath::new("assets/shaders").unwrap()));
+    assert_eq!(path.sibling("../fonts/a.fnt").unwrap().as_str(), "assets/fonts/a.fnt");
+    assert_eq!(AssetPath::new(".hidden").unwrap().extension(), None);
+    assert_eq!(AssetPath::new("top.png").unwrap().parent(), None);
+}
+
+#[test]
+fn empty_paths_are_rejected() {
+    assert_eq!(AssetPath::new(""), Err(AssetPathError::Empty));
+    assert_eq!(AssetPath::new("./"), Err(AssetPathError::Empty));
+    assert_eq!(AssetPath::new("assets/.."), Err(AssetPathError::Empty));
+}
+
+#[test]
+fn absolute_paths_are_rejected() {
+    for path in ["/etc/passwd", r"\assets\tiles.png", "C:/assets/tiles.png", r"c:\tiles.png"] {
+        assert_eq!(AssetPath::new(path), Err(AssetPathError::Absolute(path.to_string())));
+    }
+}
+
+#[test]
+fn paths_leaving_the_root_are_rejected() {
+    assert_eq!(AssetPath::new("../secret"), Err(AssetPathError::EscapesRoot("../secret".to_string())));
+    assert_eq!(AssetPath::new("assets/../../secret"), Err(AssetPathError::EscapesRoot("assets/../../secret".to_string())));
+    let saves = AssetPath::new("saves").unwrap();
+    assert!(matches!(saves.join("../../x"), Err(AssetPathError::EscapesRoot(_))));
+    assert!(matches!(saves.sibling("../x"), Err(AssetPathError::EscapesRoot(_))));
+}
+
+#[test]
+fn invalid_characters_are_rejected() {
+    for (path, character) in [("maps/a:b.map", ':'), ("what?.png", '?'), ("tiles#1.png", '#'), ("new\nline", '\n')] {
+        assert_eq!(AssetPath::new(path), Err(AssetPathError::InvalidCharacter { path: path.to_string(), character }));
+    }
+    let error = AssetPath::new("a|b").unwrap_err();
+    assert_eq!(error.to_string(), "asset path \"a|b\" contains the invalid character '|'");
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use std::sync::Arc;
+
+use castle_sim::components::cs_io::InMemoryAssetIo;
+use castle_sim::components::cs_io::asset_path::AssetPath;
+use castle_sim::components::cs_io::assets::{Asset, AssetError, Assets, LoadContext, LoadState};
+
+#[derive(Debug)]
+struct Text(String);
+
+impl Asset for Text {
+    fn from_bytes(bytes: Vec<u8>, path: &AssetPath, _context: &LoadContext) -> Result<Self, AssetError> {
+        String::from_utf8(bytes).map(Text).map_err(|error| AssetError::decode(path, error))
+    }
+}
+
+#[derive(Debug)]
+struct Length(usize);
+
+impl Asset for Length {
+    fn from_bytes(bytes: Vec<u8>, _path: &AssetPath, _context: &LoadContext) -> Result<Self, AssetError> {
+        Ok(Length(bytes.len()))
+    }
+}
+
+fn assets() -> Assets {
+    let asset_io = InMemoryAssetIo::new()
+        .with_file(AssetPath::new("a.txt").unwrap(), b"first".to_vec())
+        .with_file(AssetPath::new("broken.txt").unwrap(), vec![0xff, 0xfe]);
+    Assets::new(Box::new(asset_io))
+}
+
+#[test]
+fn paths_are_loaded_once_per_type() {
+    let (device, queue) = common::gpu();
+    let mut assets = assets();
+    let first = assets.load::<Text>("a.txt");
+    let second = assets.load::<Text>("./a.txt");
+    let length = assets.load::<Length>("a.txt");
+    assert_eq!(assets.pending_count(), 2);
+    pollster::block_on(assets.load_pending(&device, &queue));
+
+    assert!(Arc::ptr_eq(&first.expect_loaded(), &second.expect_loaded()));
+    assert_eq!(first.expect_loaded().0, "first");
+    assert_eq!(length.expect_loaded().0, 5);
+    assert_eq!(first.version(), 1);
+    // a live asset is not read again
+    assert!(Arc::ptr_eq(&assets.load::<Text>("a.txt").expect_loaded(), &first.expect_loaded()));
+    assert_eq!(assets.pending_count(), 0);
+}
+
+#[test]
+fn dropped_handles_free_their_assets() {
+    let (device, queue) = common::gpu();
+    let mut assets = assets();
+    drop(assets.load::<Text>("a.txt"));
+    // nobody waits for the load any more, so it is skipped
+    assert_eq!(assets.pending_count(), 0);
+    pollster::block_on(assets.load_pending(&device, &queue));
+
+    let handle = assets.load::<Text>("a.txt");
+    assert_eq!(handle.load_state(), LoadState::Loading);
+    pollster::block_on(assets.load_pending(&device, &queue));
+    let value = Arc::downgrade(&handle.expect_loaded());
+    drop(handle);
+    assert!(value.upgrade().is_none());
+
+    assets.remove_unused();
+    let again = assets.load::<Text>("a.txt");
+    assert_eq!(again.load_state(), LoadState::Loading);
+    assert_eq!(assets.pending_count(), 1);
+}
+
+#[test]
+fn failures_are_reported_through_the_load_state() {
+    let (device, queue) = common::gpu();
+    let mut assets = assets();
+    let missing = assets.load::<Text>("missing.txt");
+    let broken = assets.load::<Text>("broken.txt");
+    let invalid = assets.load::<Text>("/etc/passwd");
+    assert!(matches!(invalid.load_state(), LoadState::Failed(_)));
+    assert_eq!(assets.pending_count(), 2);
+    pollster::block_on(assets.load_pending(&device, &queue));
+
+    assert!(matches!(missing.load_state(), LoadState::Failed(_)));
+    match broken.load_state() {
+        LoadState::Failed(message) => assert!(message.starts_with("could not decode broken.txt"), "{message}"),
+        state => panic!("unexpected {state:?}"),
+    }
+    assert!(broken.get().is_none());
+    assert_eq!(broken.version(), 0);
+}
+
+#[test]
+fn changed_files_reload_live_handles() {
+    let (device, queue) = common::gpu();
+    let mut assets = assets();
+    // nothing is loaded yet, so the files put in before are no reloads
+    assert_eq!(assets.queue_changed(), 0);
+    let text = assets.load::<Text>("a.txt");
+    let length = assets.load::<Length>("a.txt");
+    drop(assets.load::<Text>("broken.txt"));
+    pollster::block_on(assets.load_pending(&device, &queue));
+    assert_eq!(assets.queue_changed(), 0);
+
+    memory(&assets).insert(AssetPath::new("a.txt").unwrap(), b"second".to_vec());
+    // freed and never loaded assets are not read again
+    memory(&assets).insert(AssetPath::new("broken.txt").unwrap(), b"fixed".to_vec());
+    memory(&assets).insert(AssetPath::new("other.txt").unwrap(), b"other".to_vec());
+    assert_eq!(assets.queue_changed(), 2);
+    pollster::block_on(assets.load_pending(&device, &queue));
+    assert_eq!(text.expect_loaded().0, "second");
+    assert_eq!(text.version(), 2);
+    assert_eq!(length.expect_loaded().0, 6);
+
+    // a reload that fails keeps the previous value
+    memory(&assets).insert(AssetPath::new("a.txt").unwrap(), vec![0xff]);
+    assert_eq!(assets.queue_changed(), 2);
+    pollster::block_on(assets.load_pending(&device, &queue));
+    assert!(matches!(text.load_state(), LoadState::Failed(_)));
+    assert_eq!(text.expect_loaded().0, "second");
+    assert_eq!(text.version(), 2);
+    assert_eq!(length.version(), 3);
+}
+
+fn memory(assets: &Assets) -> &InMemoryAssetIo {
+    assets.asset_io().downcast_ref::<InMemoryAssetIo>().unwrap()
+}
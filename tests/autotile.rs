@@ -0,0 +1,103 @@
+use castle_sim::components::cs_world::autotile::{self, AutotileMode, EAST, FULL_VARIANT_COUNT, NORTH, NORTH_EAST, SOUTH, SOUTH_WEST, WEST};
+use castle_sim::components::cs_world::map::Map;
+use castle_sim::components::cs_world::tile_registry::{TileRegistry, TileRegistryError};
+use cgmath::Vector2;
+
+#[test]
+fn masks_only_keep_corners_between_connected_sides() {
+    let connected = [Vector2::new(0, -1), Vector2::new(1, 0), Vector2::new(1, -1), Vector2::new(-1, 1)];
+    let connects = |offset: Vector2<i32>| connected.contains(&offset);
+    assert_eq!(autotile::neighbour_mask(AutotileMode::Cardinal, connects), NORTH | EAST);
+    // the south west corner has neither side connected and is dropped
+    assert_eq!(autotile::neighbour_mask(AutotileMode::Full, connects), NORTH | EAST | NORTH_EAST);
+    assert_eq!(autotile::reduce_corners(SOUTH_WEST | SOUTH), SOUTH);
+    assert_eq!(autotile::neighbour_mask(AutotileMode::Full, |_| true), 255);
+}
+
+#[test]
+fn variants_cover_the_atlas_strip_without_gaps() {
+    for mask in 0..16 {
+        assert_eq!(autotile::variant_index(AutotileMode::Cardinal, mask), mask);
+    }
+    // corners never change a cardinal variant
+    assert_eq!(autotile::variant_index(AutotileMode::Cardinal, NORTH | NORTH_EAST), NORTH);
+
+    let mut variants: Vec<u8> = (0..=255).map(|mask| autotile::variant_index(AutotileMode::Full, mask)).collect();
+    variants.sort();
+    variants.dedup();
+    assert_eq!(variants, (0..FULL_VARIANT_COUNT as u8).collect::<Vec<_>>());
+    assert_eq!(autotile::variant_index(AutotileMode::Full, 0), 0);
+    assert_eq!(autotile::variant_index(AutotileMode::Full, 255), FULL_VARIANT_COUNT as u8 - 1);
+    assert_eq!(autotile::variant_index(AutotileMode::Full, NORTH | WEST), autotile::variant_index(AutotileMode::Full, NORTH | WEST | SOUTH_WEST));
+}
+
+#[test]
+fn setting_a_tile_updates_its_neighbours() {
+    let registry = TileRegistry::default();
+    let road = registry.id("road").unwrap();
+    let mut map = Map::new(Vector2::new(4, 4), registry.id("grass").unwrap(), &registry);
+    map.set_tile(Vector2::new(1, 1), road, &registry);
+    let rule = registry.get(road).autotile.clone().unwrap();
+    assert_eq!(map.tiles[map.index(Vector2::new(1, 1))].atlas_coordinate.coordinate, rule.atlas_coordinate(0));
+    // grass around it does not join with roads and keeps its look
+    assert_eq!(map.take_changed_tiles(), vec![map.index(Vector2::new(1, 1))]);
+
+    map.set_tile(Vector2::new(2, 1), road, &registry);
+    assert_eq!(map.tiles[map.index(Vector2::new(1, 1))].atlas_coordinate.coordinate, rule.atlas_coordinate(EAST));
+    assert_eq!(map.tiles[map.index(Vector2::new(2, 1))].atlas_coordinate.coordinate, rule.atlas_coordinate(WEST));
+    assert!(map.take_changed_tiles().contains(&map.index(Vector2::new(1, 1))));
+
+    // tiles outside the map and unchanged kinds are ignored
+    map.set_tile(Vector2::new(4, 0), road, &registry);
+    map.set_tile(Vector2::new(2, 1), road, &registry);
+    assert!(map.take_changed_tiles().is_empty());
+}
+
+#[test]
+fn registries_are_read_from_definition_files() {
+    let registry = TileRegistry::from_ron(r#"[
+        (name: "grass", atlas_coordinate: (0, 0), minimap_color: (1, 2, 3)),
+        (name: "moat", atlas_coordinate: (0, 4), minimap_color: (4, 5, 6), movement_cost: None,
+            autotile: Some((group: Water, mode: Full, first_variant: (0, 4)))),
+    ]"#).unwrap();
+    assert_eq!(registry.id("moat"), Some(1));
+    let moat = registry.get(1);
+    assert_eq!(moat.movement_cost, None);
+    assert_eq!(moat.minimap_color, [4, 5, 6, 255]);
+    assert_eq!(registry.get(0).movement_cost, Some(1.0));
+    assert!(moat.connects_with(moat));
+
+    let duplicate = TileRegistry::from_ron(r#"[
+        (name: "grass", atlas_coordinate: (0, 0), minimap_color: (1, 2, 3)),
+        (name: "grass", atlas_coordinate: (0, 1), minimap_color: (1, 2, 3)),
+    ]"#);
+    assert!(matches!(duplicate, Err(TileRegistryError::DuplicateName(name)) if name == "grass"));
+    assert!(matches!(TileRegistry::from_ron("[(name: \"grass\")]"), Err(TileRegistryError::Parse(_))));
+
+    let long = TileRegistry::from_ron(&format!(r#"[(name: "{}", atlas_coordinate: (0, 0), minimap_color: (1, 2, 3))]"#, "a".repeat(256)));
+    assert!(matches!(long, Err(TileRegistryError::NameTooLong(name)) if name.len() == 256));
+
+    for cost in ["0.0", "-1.0", "inf", "NaN"] {
+        let free = TileRegistry::from_ron(&format!(r#"[
+            (name: "road", atlas_coordinate: (0, 0), minimap_color: (1, 2, 3), movement_cost: Some({cost})),
+        ]"#));
+        assert!(matches!(free, Err(TileRegistryError::InvalidMovementCost(name)) if name == "road"), "{cost}");
+    }
+}
+
+#[test]
+fn refreshing_applies_new_definitions_to_every_tile() {
+    let registry = TileRegistry::default();
+    let road = registry.id("road").unwrap();
+    let mut map = Map::new(Vector2::new(3, 3), registry.id("grass").unwrap(), &registry);
+    map.set_tile(Vector2::new(1, 1), road, &registry);
+    map.take_changed_tiles();
+
+    let source = include_str!("../assets/data/tiles.ron").replace("first_variant: (0, 2)", "first_variant: (0, 9)");
+    let reloaded = TileRegistry::from_ron(&source).unwrap();
+    map.refresh_tiles(&reloaded);
+    let rule = reloaded.get(road).autotile.clone().unwrap();
+    assert_ne!(rule.atlas_coordinate(0), registry.get(road).autotile.clone().unwrap().atlas_coordinate(0));
+    assert_eq!(map.tiles[map.index(Vector2::new(1, 1))].atlas_coordinate.coordinate, rule.atlas_coordinate(0));
+    assert_eq!(map.take_changed_tiles(), (0..9).collect::<Vec<_>>());
+}
@@ -0,0 +1,109 @@
+use bevy_ecs::schedule::Schedule;
+use bevy_ecs::world::World;
+use castle_sim::components::cs_render::sprite::building_sprite;
+use castle_sim::components::cs_world::building::{self, Building, BuildingCost, BuildingRegistry, Occupancy, PlacementError, Rotation};
+use castle_sim::components::cs_world::map::{self, Map};
+use castle_sim::components::cs_world::pathfinding::{CostGrid, Pathfinder};
+use castle_sim::components::cs_world::position::TilePosition;
+use castle_sim::components::cs_world::tile_registry::TileRegistry;
+use castle_sim::components::cs_world::unit::UnitBundle;
+use cgmath::Vector2;
+
+fn world() -> (World, Schedule) {
+    let tile_registry = TileRegistry::default();
+    let mut map = Map::new(Vector2::new(16, 16), tile_registry.id("grass").unwrap(), &tile_registry);
+    map.set_tile(Vector2::new(10, 10), tile_registry.id("road").unwrap(), &tile_registry);
+    map.set_tile(Vector2::new(4, 12), tile_registry.id("water").unwrap(), &tile_registry);
+
+    let mut world = World::new();
+    let mut schedule = Schedule::default();
+    let cost_grid = CostGrid::from_map(&map, &tile_registry);
+    Pathfinder::default().register(cost_grid, &mut world, &mut schedule);
+    world.insert_resource(Occupancy::new(map.size));
+    world.insert_resource(BuildingRegistry::default());
+    map::insert_map(map, &mut world, &mut schedule);
+    world.insert_resource(tile_registry);
+    schedule.run(&mut world);
+    (world, schedule)
+}
+
+#[test]
+fn rotation_turns_the_footprint() {
+    let registry = BuildingRegistry::default();
+    let granary = registry.get(registry.id("granary").unwrap());
+    assert_eq!(granary.footprint(Rotation::None), Vector2::new(3, 2));
+    assert_eq!(granary.footprint(Rotation::Quarter), Vector2::new(2, 3));
+    assert_eq!(granary.footprint(Rotation::Half), Vector2::new(3, 2));
+    assert_eq!(Rotation::ThreeQuarters.next(), Rotation::None);
+
+    let origin = granary.origin_around(Vector2::new(5, 5), Rotation::Quarter);
+    assert_eq!(origin, Vector2::new(5, 4));
+    let tiles = granary.tiles(origin, Rotation::Quarter);
+    assert_eq!(tiles.len(), 6);
+    assert!(tiles.contains(&Vector2::new(6, 6)));
+    assert!(!tiles.contains(&Vector2::new(7, 5)));
+
+    assert_eq!(granary.cost.to_string(), "30 wood, 5 gold");
+    assert_eq!(BuildingCost::default().to_string(), "free");
+
+    // the sprite spans the footprint, whose top corner is half a tile above the origin, and mirrors when sideways
+    let sheet = Vector2::new(176, 576);
+    let building = Building { kind: registry.id("granary").unwrap(), origin: Vector2::new(0, 0), rotation: Rotation::None };
+    let sprite = building_sprite(granary, &building, sheet);
+    assert_eq!(sprite.position, [-32.0, 32.0 - 60.0]);
+    let turned = building_sprite(granary, &Building { rotation: Rotation::Quarter, ..building }, sheet);
+    assert_eq!(turned.position, [-48.0, 32.0 - 60.0]);
+    assert_eq!(turned.uv_min[0], sprite.uv_max[0]);
+}
+
+#[test]
+fn invalid_placements_give_a_reason() {
+    let (mut world, _) = world();
+    let registry = world.resource::<BuildingRegistry>().clone();
+    let house = registry.id("house").unwrap();
+    let well = registry.id("well").unwrap();
+
+    assert_eq!(building::check_placement(&mut world, house, Vector2::new(15, 3), Rotation::None), Err(PlacementError::OutsideMap));
+    assert_eq!(building::check_placement(&mut world, house, Vector2::new(-1, 3), Rotation::None), Err(PlacementError::OutsideMap));
+    let road = building::check_placement(&mut world, house, Vector2::new(9, 9), Rotation::None);
+    assert_eq!(road, Err(PlacementError::WrongTerrain("road".to_string())));
+    assert_eq!(road.unwrap_err().to_string(), "cannot build on road");
+    assert_eq!(building::check_placement(&mut world, well, Vector2::new(10, 10), Rotation::None), Ok(()));
+    assert_eq!(building::check_placement(&mut world, well, Vector2::new(4, 12), Rotation::None), Err(PlacementError::WrongTerrain("water".to_string())));
+
+    let unit_kind = 0;
+    world.spawn(UnitBundle::new(unit_kind, Vector2::new(2.5, 2.5)));
+    assert_eq!(building::check_placement(&mut world, house, Vector2::new(1, 1), Rotation::None), Err(PlacementError::UnitsInTheWay));
+    assert_eq!(building::check_placement(&mut world, house, Vector2::new(3, 3), Rotation::None), Ok(()));
+
+    building::place_building(&mut world, house, Vector2::new(3, 3), Rotation::None).unwrap();
+    let overlapping = building::place_building(&mut world, house, Vector2::new(4, 4), Rotation::None);
+    assert_eq!(overlapping, Err(PlacementError::Occupied));
+    assert_eq!(overlapping.unwrap_err().to_string(), "blocked by a building");
+}
+
+#[test]
+fn placing_and_demolishing_updates_occupancy_and_costs() {
+    let (mut world, mut schedule) = world();
+    let registry = world.resource::<BuildingRegistry>().clone();
+    let farm = registry.id("farm").unwrap();
+    let origin = Vector2::new(5, 5);
+    let entity = building::place_building(&mut world, farm, origin, Rotation::None).unwrap();
+    assert_eq!(world.get::<TilePosition>(entity).unwrap().0, Vector2::new(6.5, 6.5));
+    schedule.run(&mut world);
+
+    for tile in registry.get(farm).tiles(origin, Rotation::None) {
+        assert_eq!(world.resource::<Occupancy>().get(tile), Some(entity));
+        assert_eq!(world.resource::<CostGrid>().cost(tile), None);
+    }
+    assert_eq!(world.resource::<CostGrid>().cost(Vector2::new(8, 5)), Some(1.0));
+
+    let removed = building::demolish_building(&mut world, entity).unwrap();
+    assert_eq!(removed.origin, origin);
+    assert!(world.get_entity(entity).is_none());
+    schedule.run(&mut world);
+    for tile in registry.get(farm).tiles(origin, Rotation::None) {
+        assert!(!world.resource::<Occupancy>().is_occupied(tile));
+        assert_eq!(world.resource::<CostGrid>().cost(tile), Some(1.0));
+    }
+}
@@ -0,0 +1,88 @@
+//! Fixtures shared by the integration tests and benchmarks, every binary only uses some of them.
+#![allow(dead_code)]
+
+use bevy_ecs::world::World;
+use castle_sim::components::cs_io::asset_path::AssetPath;
+use castle_sim::components::cs_io::{AssetIo, AssetIoError};
+use castle_sim::components::cs_world::history::History;
+use castle_sim::components::cs_world::map::Map;
+use castle_sim::components::cs_world::pathfinding::CostGrid;
+use castle_sim::components::cs_world::tile_registry::TileRegistry;
+use cgmath::Vector2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+pub fn path(path: &str) -> AssetPath {
+    AssetPath::new(path).unwrap()
+}
+
+pub fn load(asset_io: &dyn AssetIo, asset_path: &str) -> Result<Vec<u8>, AssetIoError> {
+    pollster::block_on(asset_io.load_path(&path(asset_path)))
+}
+
+/// Device for tests that go through `Assets`, any adapter will do including a software one.
+pub fn gpu() -> (wgpu::Device, wgpu::Queue) {
+    pollster::block_on(async {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+            .expect("the asset tests need a wgpu adapter");
+        adapter.request_device(&wgpu::DeviceDescriptor::default(), None).await.unwrap()
+    })
+}
+
+/// A grass map of `size` tiles with its registry and an edit history, the base of the editing tests.
+pub fn map_world(size: i32) -> World {
+    let tile_registry = TileRegistry::default();
+    let map = Map::new(Vector2::new(size, size), tile_registry.id("grass").unwrap(), &tile_registry);
+    let mut world = World::new();
+    world.insert_resource(History::default());
+    world.insert_resource(map);
+    world.insert_resource(tile_registry);
+    world
+}
+
+pub fn tile_name(world: &World, pos: Vector2<i32>) -> &str {
+    let kind = world.resource::<Map>().kind(pos).unwrap();
+    &world.resource::<TileRegistry>().get(kind).name
+}
+
+/// A grid drawn row by row, the first row is `y = 0`.
+pub fn cost_grid(rows: &[&str]) -> CostGrid {
+    // `.` grass, `=` road, `~` river, `#` wall
+    let mut grid = CostGrid::new(Vector2::new(rows[0].len() as i32, rows.len() as i32), Some(1.0));
+    for (y, row) in rows.iter().enumerate() {
+        for (x, tile) in row.chars().enumerate() {
+            let cost = match tile {
+                '=' => Some(0.5),
+                '~' => Some(4.0),
+                '#' => None,
+                _ => Some(1.0),
+            };
+            grid.set_cost(Vector2::new(x as i32, y as i32), cost);
+        }
+    }
+    grid
+}
+
+/// Grass with scattered walls and a few rivers, the maps of the pathfinding tests and benchmark.
+pub fn generated_grid(size: i32, seed: u64) -> CostGrid {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut grid = CostGrid::new(Vector2::new(size, size), Some(1.0));
+    for _ in 0..size * size / 40 {
+        let pos = Vector2::new(rng.gen_range(0..size), rng.gen_range(0..size));
+        let length = rng.gen_range(1..8);
+        let horizontal = rng.gen_bool(0.5);
+        for offset in 0..length {
+            let offset = if horizontal { Vector2::new(offset, 0) } else { Vector2::new(0, offset) };
+            grid.set_cost(pos + offset, None);
+        }
+    }
+    for _ in 0..size / 32 {
+        let x = rng.gen_range(0..size);
+        for y in 0..size {
+            grid.set_cost(Vector2::new(x, y), Some(4.0));
+        }
+    }
+    grid
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use castle_sim::components::cs_io::{AssetIo, AssetIoError, FileAssetIo};
+use common::load;
+
+/// Asset root in the temp directory with one file and one sub directory.
+fn asset_root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("castle_sim_{}_{}", name, std::process::id()));
+    fs::create_dir_all(root.join("assets/shaders")).unwrap();
+    fs::write(root.join("assets/tiles.png"), [1, 2, 3]).unwrap();
+    root
+}
+
+#[test]
+fn loads_existing_file() {
+    let asset_io = FileAssetIo::new(asset_root("existing"));
+    assert_eq!(load(&asset_io, "assets/tiles.png").unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn windows_separators_resolve_to_the_same_file() {
+    let asset_io = FileAssetIo::new(asset_root("separators"));
+    assert_eq!(load(&asset_io, "assets\\tiles.png").unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn missing_file_is_not_found() {
+    let root = asset_root("missing");
+    let asset_io = FileAssetIo::new(&root);
+    match load(&asset_io, "assets/missing.png") {
+        Err(AssetIoError::NotFound(path)) => assert_eq!(path, root.join("assets").join("missing.png")),
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn directory_is_not_found() {
+    let asset_io = FileAssetIo::new(asset_root("directory"));
+    assert!(matches!(load(&asset_io, "assets/shaders"), Err(AssetIoError::NotFound(_))));
+}
+
+#[test]
+fn loads_run_side_by_side() {
+    let root = asset_root("side_by_side");
+    fs::write(root.join("assets/shaders/text.wgsl"), b"fn main() {}").unwrap();
+    let asset_io = FileAssetIo::new(&root);
+    let (tiles, shader) = (common::path("assets/tiles.png"), common::path("assets/shaders/text.wgsl"));
+    let (tiles, shader) = pollster::block_on(futures_util::future::join(asset_io.load_path(&tiles), asset_io.load_path(&shader)));
+    assert_eq!(tiles.unwrap(), vec![1, 2, 3]);
+    assert_eq!(shader.unwrap(), b"fn main() {}".to_vec());
+}
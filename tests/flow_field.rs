@@ -0,0 +1,162 @@
+mod common;
+
+use std::collections::HashSet;
+
+use bevy_ecs::schedule::Schedule;
+use bevy_ecs::world::World;
+use castle_sim::components::cs_util::time::GameTime;
+use castle_sim::components::cs_world::flow_field::{FlowField, FlowFields};
+use castle_sim::components::cs_world::pathfinding::{find_path, CostGrid, PathResult};
+use castle_sim::components::cs_world::position::{TilePosition, Velocity};
+use castle_sim::components::cs_world::steering::FlowFollower;
+use cgmath::{InnerSpace, Vector2};
+
+const WALLED: [&str; 6] = [
+    "..........",
+    ".######...",
+    "......#...",
+    "..==..#...",
+    "......#...",
+    "..........",
+];
+
+#[test]
+fn integration_matches_shortest_paths() {
+    let grid = common::cost_grid(&WALLED);
+    let target = Vector2::new(9, 3);
+    let field = FlowField::new(&grid, &[target]);
+    for y in 0..6 {
+        for x in 0..10 {
+            let start = Vector2::new(x, y);
+            if !grid.is_passable(start) {
+                assert_eq!(field.cost(start), None);
+                continue;
+            }
+            match find_path(&grid, start, target) {
+                PathResult::Found(path) => {
+                    let cost = field.cost(start).unwrap();
+                    assert!((cost - path.cost).abs() < 0.001, "{:?}: field {} path {}", start, cost, path.cost);
+                }
+                PathResult::NoPath => assert_eq!(field.cost(start), None),
+                PathResult::Portals(_) => unreachable!("find_path searches tile by tile"),
+            }
+        }
+    }
+
+    // following the directions reaches the target over passable tiles
+    let mut tile = Vector2::new(0, 3);
+    let mut steps = 0;
+    while let Some(next) = field.next_tile(tile) {
+        assert!(grid.is_passable(next));
+        tile = next;
+        steps += 1;
+        assert!(steps < 60);
+    }
+    assert!(field.is_target(tile));
+}
+
+#[test]
+fn requests_for_the_same_targets_share_a_field() {
+    let mut grid = common::cost_grid(&WALLED);
+    let mut flow_fields = FlowFields::default();
+    let gate = flow_fields.request(&grid, &[Vector2::new(9, 0), Vector2::new(9, 5)]);
+    let same = flow_fields.request(&grid, &[Vector2::new(9, 5), Vector2::new(9, 0)]);
+    let keep = flow_fields.request(&grid, &[Vector2::new(9, 0)]);
+    assert_eq!(gate, same);
+    assert_eq!(flow_fields.len(), 2);
+    assert_eq!(flow_fields.rebuild_stale(&grid, 2), 2);
+
+    flow_fields.release(gate);
+    assert!(flow_fields.get(gate).is_some());
+    flow_fields.release(same);
+    assert!(flow_fields.get(gate).is_none());
+
+    // walling in the tiles under the wall cuts them off from the target
+    let enclosed = Vector2::new(3, 3);
+    assert!(flow_fields.get(keep).unwrap().cost(enclosed).is_some());
+    for i in 0..6 {
+        grid.set_cost(Vector2::new(0, i), None);
+        grid.set_cost(Vector2::new(i, 5), None);
+    }
+    grid.set_cost(Vector2::new(6, 5), None);
+    assert!(flow_fields.get(keep).unwrap().is_stale(&grid));
+    assert_eq!(flow_fields.rebuild_stale(&grid, 1), 1);
+    assert_eq!(flow_fields.get(keep).unwrap().cost(enclosed), None);
+}
+
+#[test]
+fn requested_fields_are_computed_by_the_rebuild() {
+    let grid = common::cost_grid(&WALLED);
+    let mut flow_fields = FlowFields::default();
+    let id = flow_fields.request(&grid, &[Vector2::new(9, 0)]);
+    let field = flow_fields.get(id).unwrap();
+    assert!(field.is_stale(&grid));
+    assert_eq!(field.cost(Vector2::new(0, 0)), None);
+    assert_eq!(field.steer(Vector2::new(0.5, 0.5)), None);
+
+    assert_eq!(flow_fields.rebuild_stale(&grid, 1), 1);
+    assert_eq!(flow_fields.get(id).unwrap().cost(Vector2::new(0, 0)), Some(9.0));
+    assert_eq!(flow_fields.rebuild_stale(&grid, 1), 0);
+}
+
+#[test]
+fn stale_fields_take_turns() {
+    let mut grid = common::cost_grid(&WALLED);
+    let mut flow_fields = FlowFields::default();
+    let ids: Vec<_> = (0..3).map(|y| flow_fields.request(&grid, &[Vector2::new(9, y)])).collect();
+    assert_eq!(flow_fields.rebuild_stale(&grid, 3), 3);
+
+    // the grid changes every update, yet every field is rebuilt once within three updates
+    let mut rebuilt = HashSet::new();
+    for update in 0..3 {
+        grid.set_cost(Vector2::new(8, 5), Some(2.0 + update as f32));
+        assert_eq!(flow_fields.rebuild_stale(&grid, 1), 1);
+        rebuilt.extend(ids.iter().copied().filter(|id| !flow_fields.get(*id).unwrap().is_stale(&grid)));
+    }
+    assert_eq!(rebuilt.len(), ids.len());
+}
+
+#[test]
+fn groups_reach_the_target_without_stacking() {
+    let grid = common::cost_grid(&[
+        "....................",
+        "....................",
+        "..........#.........",
+        "..........#.........",
+        "..........#.........",
+        "..........#.........",
+        "....................",
+        "....................",
+    ]);
+    let target = Vector2::new(17, 4);
+
+    let mut world = World::new();
+    let mut schedule = Schedule::default();
+    world.insert_resource(GameTime { elapsed: 0.0, delta: 0.02 });
+    let mut flow_fields = FlowFields::default();
+    let field = flow_fields.request(&grid, &[target]);
+    world.insert_resource(grid);
+    flow_fields.register(&mut world, &mut schedule);
+
+    for index in 0..8 {
+        world.spawn((
+            TilePosition(Vector2::new(1.5 + (index % 2) as f32 * 0.3, 2.5 + (index / 2) as f32 * 0.4)),
+            Velocity(Vector2::new(0.0, 0.0)),
+            FlowFollower { field, max_speed: 3.0, radius: 0.3 },
+        ));
+    }
+    for _ in 0..1000 {
+        schedule.run(&mut world);
+    }
+
+    let grid = world.resource::<CostGrid>().clone();
+    let positions: Vec<Vector2<f32>> = world.query::<&TilePosition>().iter(&world).map(|position| position.0).collect();
+    let centre = Vector2::new(target.x as f32 + 0.5, target.y as f32 + 0.5);
+    for (index, position) in positions.iter().enumerate() {
+        assert!(grid.is_passable(Vector2::new(position.x.floor() as i32, position.y.floor() as i32)));
+        assert!((position - centre).magnitude() < 2.5, "{:?} did not arrive", position);
+        for other in positions[index + 1..].iter() {
+            assert!((position - other).magnitude() > 0.3, "{:?} and {:?} overlap", position, other);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use castle_sim::components::cs_world::fog_of_war::{FogOfWar, TileVisibility};
+use cgmath::Vector2;
+
+#[test]
+fn vision_turns_into_explored_tiles() {
+    let mut fog = FogOfWar::new(Vector2::new(16, 16), 2);
+    fog.begin_update();
+    fog.reveal(0, Vector2::new(4.5, 4.5), 2.0);
+    assert_eq!(fog.visibility(0, Vector2::new(4, 4)), Some(TileVisibility::Visible));
+    assert_eq!(fog.visibility(0, Vector2::new(6, 4)), Some(TileVisibility::Visible));
+    assert_eq!(fog.visibility(0, Vector2::new(6, 6)), Some(TileVisibility::Unexplored));
+    // players do not share their view
+    assert_eq!(fog.visibility(1, Vector2::new(4, 4)), Some(TileVisibility::Unexplored));
+
+    fog.begin_update();
+    fog.reveal(0, Vector2::new(10.5, 4.5), 1.0);
+    assert_eq!(fog.visibility(0, Vector2::new(4, 4)), Some(TileVisibility::Explored));
+    assert_eq!(fog.visibility(0, Vector2::new(10, 4)), Some(TileVisibility::Visible));
+    assert_eq!(fog.visibility(0, Vector2::new(-1, 4)), Some(TileVisibility::Unexplored));
+}
+
+#[test]
+fn unknown_players_are_ignored() {
+    let mut fog = FogOfWar::new(Vector2::new(8, 8), 1);
+    assert_eq!(fog.visibility(3, Vector2::new(1, 1)), None);
+    fog.reveal(3, Vector2::new(1.5, 1.5), 2.0);
+    fog.set_active_player(3);
+    assert_eq!(fog.active_player, 0);
+    assert_eq!(fog.visibility(0, Vector2::new(1, 1)), Some(TileVisibility::Unexplored));
+}
+
+#[test]
+fn only_changed_tiles_are_uploaded() {
+    let mut fog = FogOfWar::new(Vector2::new(16, 16), 2);
+    assert_eq!(fog.take_changed().map(|(offset, tiles)| (offset, tiles.len())), Some((0, 256)));
+    assert_eq!(fog.take_changed(), None);
+
+    fog.begin_update();
+    fog.reveal(0, Vector2::new(5.5, 2.5), 0.5);
+    let (offset, tiles) = fog.take_changed().unwrap();
+    // the single tile 37 is widened to its u32 word
+    assert_eq!(offset, 36);
+    assert_eq!(tiles, &[0, TileVisibility::Visible as u8, 0, 0]);
+
+    // seeing the same tiles again changes nothing on the gpu
+    fog.begin_update();
+    fog.reveal(0, Vector2::new(5.5, 2.5), 0.5);
+    assert_eq!(fog.take_changed(), None);
+
+    // other players neither dirty the view nor get uploaded until they are shown
+    fog.begin_update();
+    fog.reveal(0, Vector2::new(5.5, 2.5), 0.5);
+    fog.reveal(1, Vector2::new(12.5, 12.5), 1.0);
+    assert_eq!(fog.take_changed(), None);
+    fog.set_active_player(1);
+    let (offset, tiles) = fog.take_changed().unwrap();
+    assert_eq!((offset, tiles.len()), (0, 256));
+    assert_eq!(tiles[12 * 16 + 12], TileVisibility::Visible as u8);
+}
@@ -0,0 +1,93 @@
+use std::io::Cursor;
+
+use castle_sim::components::cs_io::InMemoryAssetIo;
+use castle_sim::components::cs_io::asset_path::AssetPath;
+use castle_sim::components::cs_render::font::{FontAtlas, FontError};
+use cgmath::Vector2;
+use image::{DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
+
+const DESCRIPTION: &str = r#"info face="Test" size=16
+common lineHeight=20 base=16 scaleW=64 scaleH=32 pages=1
+page id=0 file="test_0.png"
+chars count=4
+char id=65 x=0 y=0 width=8 height=10 xoffset=1 yoffset=2 xadvance=10 page=0
+char id=66 x=8 y=0 width=8 height=10 xoffset=0 yoffset=2 xadvance=9 page=0
+char id=63 x=16 y=0 width=6 height=10 xoffset=0 yoffset=2 xadvance=7 page=0
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=5 page=0
+kernings count=1
+kerning first=65 second=66 amount=-2
+"#;
+
+fn page() -> Vec<u8> {
+    let mut image = RgbaImage::new(64, 32);
+    image.put_pixel(1, 0, Rgba([255, 255, 255, 200]));
+    let mut bytes = Cursor::new(Vec::new());
+    DynamicImage::ImageRgba8(image).write_to(&mut bytes, ImageOutputFormat::Png).unwrap();
+    bytes.into_inner()
+}
+
+fn atlas() -> FontAtlas {
+    FontAtlas::from_bmfont(DESCRIPTION, &page()).unwrap()
+}
+
+#[test]
+fn bmfont_descriptions_are_read() {
+    let atlas = atlas();
+    assert_eq!(atlas.size, Vector2::new(64, 32));
+    assert_eq!(atlas.line_height, 20.0);
+    assert_eq!(atlas.glyphs.len(), 4);
+    let glyph = atlas.glyphs[&'B'];
+    assert_eq!(glyph.uv_min, [0.125, 0.0]);
+    assert_eq!(glyph.uv_max, [0.25, 10.0 / 32.0]);
+    assert_eq!(glyph.advance, 9.0);
+    assert_eq!(atlas.kerning.get(&('A', 'B')), Some(&-2.0));
+    // pages with alpha keep the alpha as coverage
+    assert_eq!(&atlas.pixels[..3], &[0, 200, 0]);
+
+    let asset_io = InMemoryAssetIo::new()
+        .with_file(AssetPath::new("fonts/test.fnt").unwrap(), DESCRIPTION.as_bytes().to_vec())
+        .with_file(AssetPath::new("fonts/test_0.png").unwrap(), page());
+    let loaded = pollster::block_on(FontAtlas::load(&asset_io, &AssetPath::new("fonts/test.fnt").unwrap(), 32.0)).unwrap();
+    assert_eq!(loaded.glyphs.len(), 4);
+}
+
+#[test]
+fn malformed_bmfont_files_name_the_line() {
+    let multi_page = DESCRIPTION.replace("pages=1", "pages=2");
+    assert!(matches!(FontAtlas::from_bmfont(&multi_page, &page()), Err(FontError::Parse { line: 2, .. })));
+    let missing_width = DESCRIPTION.replace("char id=66 x=8 y=0 width=8", "char id=66 x=8 y=0");
+    let error = FontAtlas::from_bmfont(&missing_width, &page()).unwrap_err();
+    assert_eq!(error.to_string(), "bmfont line 6: missing or invalid width");
+    assert!(matches!(FontAtlas::from_bmfont(DESCRIPTION, &[1, 2, 3]), Err(FontError::Image(_))));
+
+    let asset_io = InMemoryAssetIo::new()
+        .with_file(AssetPath::new("test.fnt").unwrap(), DESCRIPTION.replace("page id=0", "page id=1").into_bytes());
+    let error = pollster::block_on(FontAtlas::load(&asset_io, &AssetPath::new("test.fnt").unwrap(), 32.0)).unwrap_err();
+    assert!(matches!(error, FontError::Parse { line: 0, .. }));
+}
+
+#[test]
+fn layout_applies_offsets_kerning_and_lines() {
+    let atlas = atlas();
+    let glyphs = atlas.layout("AB\nA", 1.0);
+    let positions: Vec<_> = glyphs.iter().map(|glyph| glyph.position).collect();
+    // B moves left by the kerning of the pair
+    assert_eq!(positions, vec![Vector2::new(1.0, 2.0), Vector2::new(8.0, 2.0), Vector2::new(1.0, 22.0)]);
+    assert_eq!(glyphs[0].size, Vector2::new(8.0, 10.0));
+
+    // spaces only advance and break the kerning pair, unknown characters fall back to '?'
+    let glyphs = atlas.layout("A BZ", 2.0);
+    assert_eq!(glyphs.len(), 3);
+    assert_eq!(glyphs[1].position, Vector2::new(30.0, 4.0));
+    assert_eq!(glyphs[2].position, Vector2::new(48.0, 4.0));
+    assert_eq!(glyphs[2].uv_min, atlas.glyphs[&'?'].uv_min);
+}
+
+#[test]
+fn measure_matches_the_layout() {
+    let atlas = atlas();
+    assert_eq!(atlas.measure("AB\nA", 1.0), Vector2::new(17.0, 40.0));
+    assert_eq!(atlas.measure("AB\nA", 2.0), Vector2::new(34.0, 80.0));
+    assert_eq!(atlas.measure("A B", 1.0), Vector2::new(24.0, 20.0));
+    assert_eq!(atlas.measure("", 1.0), Vector2::new(0.0, 20.0));
+}
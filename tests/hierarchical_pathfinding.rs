@@ -0,0 +1,129 @@
+mod common;
+
+use castle_sim::components::cs_world::hierarchical_pathfinding::{PortalGraph, Refinement};
+use castle_sim::components::cs_world::pathfinding::{find_path, CostGrid, PathResult, Pathfinder};
+use cgmath::Vector2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn assert_walkable(grid: &CostGrid, waypoints: &[Vector2<i32>]) {
+    for segment in waypoints.windows(2) {
+        assert!(grid.line_of_sight(segment[0], segment[1], f32::INFINITY), "{:?} is blocked", segment);
+    }
+}
+
+#[test]
+fn paths_are_close_to_optimal() {
+    let grid = common::generated_grid(128, 7);
+    let graph = PortalGraph::build(&grid);
+    let mut rng = StdRng::seed_from_u64(11);
+    let mut compared = 0;
+    while compared < 40 {
+        let start = Vector2::new(rng.gen_range(0..128), rng.gen_range(0..128));
+        let goal = Vector2::new(rng.gen_range(0..128), rng.gen_range(0..128));
+        if !grid.is_passable(start) {
+            continue;
+        }
+        let PathResult::Found(optimal) = find_path(&grid, start, goal) else {
+            assert!(graph.find_path(&grid, start, goal).is_none());
+            continue;
+        };
+        let path = graph.find_path(&grid, start, goal).expect("hierarchical search missed a path");
+        assert!(path.cost() <= optimal.cost * 1.2, "{} vs {}", path.cost(), optimal.cost);
+        let waypoints = path.refine_all(&grid).unwrap();
+        assert_eq!(waypoints.first(), Some(&start));
+        assert_eq!(waypoints.last(), Some(&goal));
+        assert_walkable(&grid, &waypoints);
+        compared += 1;
+    }
+}
+
+#[test]
+fn repair_follows_new_walls() {
+    let mut grid = CostGrid::new(Vector2::new(48, 48), Some(1.0));
+    let mut graph = PortalGraph::build(&grid);
+    let start = Vector2::new(2, 20);
+    let goal = Vector2::new(45, 20);
+    assert!(graph.find_path(&grid, start, goal).is_some());
+
+    // a wall across the whole map with one gap at the bottom
+    for y in 0..47 {
+        grid.set_cost(Vector2::new(24, y), None);
+        graph.mark_changed(Vector2::new(24, y));
+    }
+    graph.repair(&grid);
+    let path = graph.find_path(&grid, start, goal).unwrap();
+    // down to the gap and back up again
+    assert!(path.cost() > 60.0);
+    assert_walkable(&grid, &path.refine_all(&grid).unwrap());
+
+    grid.set_cost(Vector2::new(24, 47), None);
+    graph.mark_changed(Vector2::new(24, 47));
+    graph.repair(&grid);
+    assert!(graph.find_path(&grid, start, goal).is_none());
+}
+
+#[test]
+fn refinement_reports_blocked_hops() {
+    let mut grid = CostGrid::new(Vector2::new(64, 16), Some(1.0));
+    let graph = PortalGraph::build(&grid);
+    let mut path = graph.find_path(&grid, Vector2::new(1, 8), Vector2::new(62, 8)).unwrap();
+    assert!(matches!(path.refine_next(&grid), Refinement::Segment(_)));
+
+    // the unit walks on while the map changes, the next hops were planned before
+    for y in 0..16 {
+        for x in 16..64 {
+            grid.set_cost(Vector2::new(x, y), None);
+        }
+    }
+    let mut blocked = false;
+    loop {
+        match path.refine_next(&grid) {
+            Refinement::Segment(_) => {}
+            Refinement::Blocked => {
+                blocked = true;
+                break;
+            }
+            Refinement::Finished => break,
+        }
+    }
+    assert!(blocked);
+}
+
+#[test]
+fn long_requests_are_planned_over_portals() {
+    let grid = common::generated_grid(128, 3);
+    let graph = PortalGraph::build(&grid);
+    let mut pathfinder = Pathfinder::default();
+    let (start, goal) = (Vector2::new(2, 2), Vector2::new(120, 110));
+    let long = pathfinder.request(&grid, start, goal);
+    let short = pathfinder.request(&grid, start, Vector2::new(10, 6));
+    pathfinder.run(&grid, Some(&graph));
+
+    let Some(PathResult::Portals(path)) = pathfinder.take_result(long) else {
+        panic!("the long request was not planned over the portals");
+    };
+    assert_eq!(path, graph.find_path(&grid, start, goal).unwrap());
+    let waypoints = path.refine_all(&grid).unwrap();
+    assert_eq!(waypoints.last(), Some(&goal));
+    assert_walkable(&grid, &waypoints);
+    assert!(matches!(pathfinder.take_result(short), Some(PathResult::Found(_))));
+
+    // a small budget spreads the plan over several updates
+    let mut pathfinder = Pathfinder::with_budget(100);
+    let slow = pathfinder.request(&grid, start, goal);
+    let mut updates = 0;
+    while pathfinder.is_pending(slow) {
+        pathfinder.run(&grid, Some(&graph));
+        updates += 1;
+    }
+    assert!(updates > 5);
+    assert!(matches!(pathfinder.take_result(slow), Some(PathResult::Portals(planned)) if Some(&planned) == graph.find_path(&grid, start, goal).as_ref()));
+
+    // without a graph every request is searched tile by tile
+    let flat = pathfinder.request(&grid, start, goal);
+    while pathfinder.is_pending(flat) {
+        pathfinder.run(&grid, None);
+    }
+    assert!(matches!(pathfinder.take_result(flat), Some(PathResult::Found(_))));
+}
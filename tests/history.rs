@@ -0,0 +1,97 @@
+mod common;
+
+use bevy_ecs::world::World;
+use castle_sim::components::cs_world::building::{Building, BuildingRegistry, Occupancy, Rotation};
+use castle_sim::components::cs_world::history::{self, DemolishBuilding, History, PlaceBuilding};
+use castle_sim::components::cs_world::map::{Map, TileEdit};
+use castle_sim::components::cs_world::tile_registry::TileRegistry;
+use cgmath::Vector2;
+
+fn world(limit: usize) -> World {
+    let mut world = common::map_world(12);
+    world.insert_resource(Occupancy::new(Vector2::new(12, 12)));
+    world.insert_resource(BuildingRegistry::default());
+    world.insert_resource(History::new(limit));
+    world
+}
+
+fn paint(world: &mut World, tiles: &[Vector2<i32>], name: &str) {
+    let kind = world.resource::<TileRegistry>().id(name).unwrap();
+    let tiles: Vec<_> = tiles.iter().map(|tile| (*tile, kind)).collect();
+    let edit = TileEdit::new(world.resource::<Map>(), &tiles);
+    history::execute(world, format!("paint {}", name), edit);
+}
+
+fn kinds(world: &World) -> Vec<u16> {
+    world.resource::<Map>().kinds.clone()
+}
+
+#[test]
+fn tile_edits_revert_exactly() {
+    let mut world = world(10);
+    let original = kinds(&world);
+    paint(&mut world, &[Vector2::new(1, 1), Vector2::new(2, 1)], "road");
+    let roads = kinds(&world);
+    // painting over the road again has to restore the road, not the grass under it
+    paint(&mut world, &[Vector2::new(2, 1), Vector2::new(3, 1), Vector2::new(3, 1)], "water");
+    let water = kinds(&world);
+    assert_eq!(world.resource::<History>().undo_label(), Some("paint water"));
+
+    assert!(history::undo(&mut world));
+    assert_eq!(kinds(&world), roads);
+    assert!(history::undo(&mut world));
+    assert_eq!(kinds(&world), original);
+    assert!(!history::undo(&mut world));
+
+    assert!(history::redo(&mut world));
+    assert!(history::redo(&mut world));
+    assert_eq!(kinds(&world), water);
+    assert!(!history::redo(&mut world));
+
+    // a new edit drops what could be redone
+    history::undo(&mut world);
+    paint(&mut world, &[Vector2::new(5, 5)], "wall");
+    assert_eq!(world.resource::<History>().redo_len(), 0);
+    assert_eq!(world.resource::<History>().redo_label(), None);
+}
+
+#[test]
+fn the_oldest_edits_fall_off() {
+    let mut world = world(3);
+    for x in 0..5 {
+        paint(&mut world, &[Vector2::new(x, 0)], "road");
+    }
+    assert_eq!(world.resource::<History>().undo_len(), 3);
+    while history::undo(&mut world) {}
+    let road = world.resource::<TileRegistry>().id("road").unwrap();
+    let map = world.resource::<Map>();
+    assert_eq!(map.kind(Vector2::new(1, 0)), Some(road));
+    assert_ne!(map.kind(Vector2::new(2, 0)), Some(road));
+}
+
+#[test]
+fn buildings_come_back_where_they_stood() {
+    let mut world = world(10);
+    let registry = world.resource::<BuildingRegistry>().clone();
+    let house = Building { kind: registry.id("house").unwrap(), origin: Vector2::new(4, 4), rotation: Rotation::Quarter };
+    let footprint = registry.get(house.kind).tiles(house.origin, house.rotation);
+    let buildings = |world: &mut World| world.query::<&Building>().iter(world).copied().collect::<Vec<_>>();
+
+    history::execute(&mut world, "place house", PlaceBuilding(house));
+    history::execute(&mut world, "demolish house", DemolishBuilding(house));
+    assert!(buildings(&mut world).is_empty());
+
+    // the restored house is a new entity, undoing its placement still finds it
+    history::undo(&mut world);
+    assert_eq!(buildings(&mut world), vec![house]);
+    assert!(footprint.iter().all(|tile| world.resource::<Occupancy>().is_occupied(*tile)));
+    history::undo(&mut world);
+    assert!(buildings(&mut world).is_empty());
+    assert!(footprint.iter().all(|tile| !world.resource::<Occupancy>().is_occupied(*tile)));
+
+    history::redo(&mut world);
+    history::redo(&mut world);
+    assert!(buildings(&mut world).is_empty());
+    history::undo(&mut world);
+    assert_eq!(buildings(&mut world), vec![house]);
+}
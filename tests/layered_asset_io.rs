@@ -0,0 +1,29 @@
+mod common;
+
+use castle_sim::components::cs_io::{AssetIo, AssetIoError, InMemoryAssetIo, LayeredAssetIo};
+use common::{load, path};
+
+#[test]
+fn first_layer_wins_and_missing_files_fall_through() {
+    let overrides = InMemoryAssetIo::new().with_file(path("assets/tiles.png"), vec![1]);
+    let base = InMemoryAssetIo::new()
+        .with_file(path("assets/tiles.png"), vec![2])
+        .with_file(path("assets/shaders/shader.wgsl"), vec![3]);
+    let layered = LayeredAssetIo::new(vec![Box::new(overrides), Box::new(base)]);
+
+    assert_eq!(load(&layered, "assets/tiles.png").unwrap(), vec![1]);
+    assert_eq!(load(&layered, "assets/shaders/shader.wgsl").unwrap(), vec![3]);
+    assert!(matches!(load(&layered, "assets/missing.png"), Err(AssetIoError::NotFound(_))));
+}
+
+#[test]
+fn inserted_files_are_reported_as_changed() {
+    let memory = InMemoryAssetIo::new();
+    memory.insert(path("assets/tiles.png"), vec![1]);
+    memory.insert(path("assets/tiles.png"), vec![2]);
+
+    assert_eq!(load(&memory, "assets/tiles.png").unwrap(), vec![2]);
+    let layered = LayeredAssetIo::new(vec![Box::new(memory)]);
+    assert_eq!(layered.changed_paths(), vec![path("assets/tiles.png")]);
+    assert!(layered.changed_paths().is_empty());
+}
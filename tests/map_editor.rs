@@ -0,0 +1,207 @@
+mod common;
+
+use bevy_ecs::world::World;
+use castle_sim::components::cs_io::InMemoryAssetIo;
+use castle_sim::components::cs_render::overlay::{self, TileOverlay};
+use castle_sim::components::cs_world::editor::{self, Brush, EditorError, EditorLayer, HeightMode, MapEditor};
+use castle_sim::components::cs_world::history::{self, History};
+use castle_sim::components::cs_world::map::{self, Map, TileChange, TileEdit, HEIGHT_STEP, MAX_HEIGHT};
+use castle_sim::components::cs_world::map_data::{MapData, MapDataError, MAX_MAP_SIZE};
+use castle_sim::components::cs_world::tile_registry::TileRegistry;
+use cgmath::Vector2;
+use common::tile_name;
+
+fn editor_world(size: i32) -> World {
+    let mut world = common::map_world(size);
+    world.insert_resource(MapEditor::default());
+    world
+}
+
+#[test]
+fn brushes_cover_their_shape_inside_the_map() {
+    let registry = TileRegistry::default();
+    let map = Map::new(Vector2::new(16, 16), registry.id("grass").unwrap(), &registry);
+    let center = Vector2::new(8, 8);
+    assert_eq!(editor::brush_tiles(&map, Brush::Single, EditorLayer::Terrain, center, 3), vec![center]);
+    assert_eq!(editor::brush_tiles(&map, Brush::Square, EditorLayer::Terrain, center, 1).len(), 9);
+    // only the far corners of the 5x5 square are cut off
+    let circle = editor::brush_tiles(&map, Brush::Circle, EditorLayer::Terrain, center, 2);
+    assert_eq!(circle.len(), 21);
+    assert!(!circle.contains(&Vector2::new(10, 10)));
+    assert!(circle.contains(&Vector2::new(10, 9)));
+
+    assert_eq!(editor::brush_tiles(&map, Brush::Square, EditorLayer::Terrain, Vector2::new(0, 0), 1).len(), 4);
+    assert!(editor::brush_tiles(&map, Brush::Square, EditorLayer::Terrain, Vector2::new(-1, 0), 1).is_empty());
+}
+
+#[test]
+fn fill_stops_at_other_kinds_and_heights() {
+    let registry = TileRegistry::default();
+    let water = registry.id("water").unwrap();
+    let mut map = Map::new(Vector2::new(8, 8), registry.id("grass").unwrap(), &registry);
+    // a lake in the corner, touching the rest of the water only diagonally
+    for tile in [Vector2::new(0, 0), Vector2::new(1, 0), Vector2::new(0, 1), Vector2::new(2, 1)] {
+        map.set_tile(tile, water, &registry);
+    }
+    let mut lake = editor::brush_tiles(&map, Brush::Fill, EditorLayer::Terrain, Vector2::new(1, 0), 0);
+    lake.sort_by_key(|tile| (tile.y, tile.x));
+    assert_eq!(lake, vec![Vector2::new(0, 0), Vector2::new(1, 0), Vector2::new(0, 1)]);
+    assert_eq!(editor::brush_tiles(&map, Brush::Fill, EditorLayer::Terrain, Vector2::new(5, 5), 0).len(), 60);
+
+    map.set_height(Vector2::new(4, 4), 2);
+    map.set_height(Vector2::new(4, 5), 2);
+    assert_eq!(editor::brush_tiles(&map, Brush::Fill, EditorLayer::Height, Vector2::new(4, 5), 0).len(), 2);
+}
+
+#[test]
+fn strokes_touch_every_tile_once_and_undo_together() {
+    let mut world = editor_world(16);
+    {
+        let mut map_editor = world.resource_mut::<MapEditor>();
+        map_editor.layer = EditorLayer::Height;
+        map_editor.brush = Brush::Square;
+        map_editor.height_mode = HeightMode::Raise;
+    }
+    let ground = world.resource::<Map>().tiles[world.resource::<Map>().index(Vector2::new(5, 5))].position[1];
+    editor::paint(&mut world, Vector2::new(5, 5));
+    editor::paint(&mut world, Vector2::new(6, 5));
+    assert!(world.resource::<MapEditor>().is_painting());
+    editor::finish_stroke(&mut world);
+    let map = world.resource::<Map>();
+    assert_eq!(map.height(Vector2::new(5, 5)), Some(1));
+    assert_eq!(map.height(Vector2::new(7, 6)), Some(1));
+    assert_eq!(map.height(Vector2::new(8, 5)), Some(0));
+    assert!(map.tiles[map.index(Vector2::new(5, 5))].position[1] < ground);
+    assert_eq!(world.resource::<History>().undo_label(), Some("raise terrain"));
+
+    let water = world.resource::<TileRegistry>().id("water").unwrap();
+    {
+        let mut map_editor = world.resource_mut::<MapEditor>();
+        map_editor.layer = EditorLayer::Terrain;
+        map_editor.brush = Brush::Single;
+        map_editor.tile = water;
+    }
+    editor::paint(&mut world, Vector2::new(1, 1));
+    editor::paint(&mut world, Vector2::new(2, 1));
+    editor::finish_stroke(&mut world);
+    assert_eq!(world.resource::<History>().undo_len(), 2);
+    assert_eq!(tile_name(&world, Vector2::new(2, 1)), "water");
+
+    assert!(history::undo(&mut world));
+    assert_eq!(tile_name(&world, Vector2::new(1, 1)), "grass");
+    assert_eq!(tile_name(&world, Vector2::new(2, 1)), "grass");
+    assert!(history::undo(&mut world));
+    let map = world.resource::<Map>();
+    assert_eq!(map.height(Vector2::new(5, 5)), Some(0));
+    assert_eq!(map.tiles[map.index(Vector2::new(5, 5))].position[1], ground);
+
+    // a cancelled stroke leaves neither changes nor an undo step behind
+    editor::paint(&mut world, Vector2::new(3, 3));
+    editor::cancel_stroke(&mut world);
+    editor::finish_stroke(&mut world);
+    assert_eq!(tile_name(&world, Vector2::new(3, 3)), "grass");
+    assert_eq!(world.resource::<History>().undo_len(), 0);
+}
+
+#[test]
+fn map_files_keep_heights_and_still_read_version_one() {
+    let registry = TileRegistry::default();
+    let mut map = Map::new(Vector2::new(4, 3), registry.id("grass").unwrap(), &registry);
+    map.set_tile(Vector2::new(1, 2), registry.id("water").unwrap(), &registry);
+    map.set_height(Vector2::new(3, 0), MAX_HEIGHT + 3);
+    let data = MapData::from_map(&map, &registry);
+    let read = MapData::from_slice(&data.to_bytes()).unwrap();
+    assert_eq!(read, data);
+    let loaded = read.to_map(&registry).unwrap();
+    assert_eq!(loaded.kinds, map.kinds);
+    assert_eq!(loaded.heights, map.heights);
+    assert_eq!(loaded.height(Vector2::new(3, 0)), Some(MAX_HEIGHT));
+    assert_eq!(loaded.tiles[3].position, map.tiles[3].position);
+
+    // version 1 is the same file without the heights at the end
+    let mut bytes = data.to_bytes();
+    bytes.truncate(bytes.len() - data.heights.len());
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+    let old = MapData::from_slice(&bytes).unwrap();
+    assert_eq!(old.tiles, data.tiles);
+    assert!(old.heights.iter().all(|height| *height == 0));
+    bytes[4..6].copy_from_slice(&3u16.to_le_bytes());
+    assert_eq!(MapData::from_slice(&bytes), Err(MapDataError::UnsupportedVersion(3)));
+}
+
+#[test]
+fn corrupt_map_sizes_are_rejected() {
+    let registry = TileRegistry::default();
+    let map = Map::new(Vector2::new(4, 3), registry.id("grass").unwrap(), &registry);
+    let bytes = MapData::from_map(&map, &registry).to_bytes();
+    let with_size = |width: u32, height: u32| {
+        let mut bytes = bytes.clone();
+        bytes[6..10].copy_from_slice(&width.to_le_bytes());
+        bytes[10..14].copy_from_slice(&height.to_le_bytes());
+        MapData::from_slice(&bytes)
+    };
+    assert_eq!(with_size(0, 3), Err(MapDataError::InvalidSize(0, 3)));
+    // sizes that turn negative as i32 or would need gigabytes of tiles
+    assert_eq!(with_size(u32::MAX, 3), Err(MapDataError::InvalidSize(u32::MAX, 3)));
+    assert_eq!(with_size(4, 0x8000_0000), Err(MapDataError::InvalidSize(4, 0x8000_0000)));
+    assert_eq!(with_size(MAX_MAP_SIZE + 1, 1), Err(MapDataError::InvalidSize(MAX_MAP_SIZE + 1, 1)));
+    // a valid size the file is too short for
+    assert_eq!(with_size(MAX_MAP_SIZE, MAX_MAP_SIZE), Err(MapDataError::UnexpectedEnd));
+    assert!(with_size(4, 3).is_ok());
+}
+
+#[test]
+fn raised_tiles_are_picked_and_outlined_where_they_are_drawn() {
+    let registry = TileRegistry::default();
+    let grass = registry.id("grass").unwrap();
+    let mut map = Map::new(Vector2::new(16, 16), grass, &registry);
+    let raised = Vector2::new(5, 5);
+    map.set_height(raised, 4);
+    let flat = map::map_to_screen_pos_centered(Vector2::new(5.0, 5.0));
+    let drawn = flat - Vector2::new(0.0, 4.0 * HEIGHT_STEP);
+    // the raised tile hides the flat tile drawn behind it
+    assert_eq!(map::screen_to_map_tile(drawn), Vector2::new(4, 4));
+    assert_eq!(map.pick_tile(drawn), raised);
+    assert_eq!(map.pick_tile(map::map_to_screen_pos_centered(Vector2::new(2.0, 3.0))), Vector2::new(2, 3));
+
+    let mut overlay = TileOverlay::default();
+    overlay.set_layer("test", vec![raised, Vector2::new(2, 3)], [1.0; 4]);
+    let (instances, fill_count) = overlay::build_overlay_instances(&overlay, &map, [Vector2::new(0.0, 0.0); 4]);
+    assert_eq!(fill_count, 2);
+    assert_eq!(instances[0].position, [drawn.x, drawn.y]);
+    let ground = map::map_to_screen_pos_centered(Vector2::new(2.0, 3.0));
+    assert_eq!(instances[1].position, [ground.x, ground.y]);
+
+    // an edit of the same tile twice keeps the first kind before and the last kind after
+    let water = registry.id("water").unwrap();
+    let edit = TileEdit::new(&map, &[(raised, water), (Vector2::new(1, 1), water), (raised, grass), (Vector2::new(1, 1), grass), (Vector2::new(1, 1), water)]);
+    assert_eq!(edit.changes, vec![TileChange { pos: Vector2::new(1, 1), before: grass, after: water }]);
+}
+
+#[test]
+fn saved_maps_load_back_as_one_edit() {
+    let store = InMemoryAssetIo::new();
+    let mut world = editor_world(8);
+    let water = world.resource::<TileRegistry>().id("water").unwrap();
+    world.resource_mut::<MapEditor>().tile = water;
+    editor::paint(&mut world, Vector2::new(2, 2));
+    editor::finish_stroke(&mut world);
+    world.resource_mut::<Map>().set_height(Vector2::new(6, 6), 4);
+    editor::save_map(&world, &store, "lake").unwrap();
+    assert_eq!(editor::saved_maps(&store).unwrap(), vec!["lake".to_string()]);
+    assert!(matches!(editor::save_map(&world, &store, "../lake"), Err(EditorError::InvalidName)));
+
+    let mut other = editor_world(8);
+    editor::load_map(&mut other, &store, "lake").unwrap();
+    assert_eq!(tile_name(&other, Vector2::new(2, 2)), "water");
+    assert_eq!(other.resource::<Map>().height(Vector2::new(6, 6)), Some(4));
+    assert_eq!(other.resource::<History>().undo_label(), Some("load lake"));
+    assert!(history::undo(&mut other));
+    assert_eq!(tile_name(&other, Vector2::new(2, 2)), "grass");
+    assert_eq!(other.resource::<Map>().height(Vector2::new(6, 6)), Some(0));
+
+    let mut larger = editor_world(16);
+    let error = editor::load_map(&mut larger, &store, "lake").unwrap_err();
+    assert_eq!(error.to_string(), "saved map is 8x8 tiles but this one is 16x16");
+    assert!(matches!(editor::load_map(&mut larger, &store, "missing"), Err(EditorError::Storage(_))));
+}
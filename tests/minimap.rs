@@ -0,0 +1,37 @@
+use castle_sim::components::cs_render::minimap::MinimapLayout;
+use cgmath::{InnerSpace, Vector2};
+
+fn layout() -> MinimapLayout {
+    MinimapLayout {
+        center: Vector2::new(400.0, 300.0),
+        half_extent: Vector2::new(128.0, 64.0),
+        map_size: Vector2::new(64.0, 32.0),
+    }
+}
+
+#[test]
+fn map_positions_round_trip_through_the_screen() {
+    let layout = layout();
+    for map_pos in [Vector2::new(0.0, 0.0), Vector2::new(63.0, 31.0), Vector2::new(10.25, 20.5), Vector2::new(-0.5, 31.5)] {
+        let back = layout.screen_to_map(layout.map_to_screen(map_pos)).unwrap();
+        assert!((back - map_pos).magnitude() < 1e-3, "{map_pos:?} came back as {back:?}");
+    }
+    // the (0, 0) corner of the map is the top of the diamond
+    assert_eq!(layout.map_to_screen(Vector2::new(-0.5, -0.5)), Vector2::new(400.0, 236.0));
+    assert_eq!(layout.map_to_screen(Vector2::new(63.5, 31.5)), Vector2::new(400.0, 364.0));
+}
+
+#[test]
+fn positions_outside_the_diamond_are_rejected() {
+    let layout = layout();
+    // the corners of the bounding box are outside of the diamond
+    assert_eq!(layout.screen_to_map(Vector2::new(273.0, 237.0)), None);
+    assert_eq!(layout.screen_to_map(Vector2::new(527.0, 363.0)), None);
+    assert_eq!(layout.screen_to_map(Vector2::new(400.0, 230.0)), None);
+    assert_eq!(layout.screen_to_map(Vector2::new(0.0, 0.0)), None);
+    assert!(layout.screen_to_map(Vector2::new(400.0, 300.0)).is_some());
+
+    let placed = MinimapLayout::bottom_right(Vector2::new(800.0, 600.0), Vector2::new(64, 64));
+    assert!(placed.screen_to_map(Vector2::new(790.0 - 128.0, 590.0 - 64.0)).is_some());
+    assert_eq!(placed.screen_to_map(Vector2::new(795.0, 595.0)), None);
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use castle_sim::components::cs_world::pathfinding::{find_path, CostGrid, Path, PathResult, Pathfinder};
+use cgmath::Vector2;
+
+fn found(result: PathResult) -> Path {
+    match result {
+        PathResult::Found(path) => path,
+        PathResult::Portals(_) | PathResult::NoPath => panic!("no tile path found"),
+    }
+}
+
+#[test]
+fn open_ground_is_one_straight_segment() {
+    let grid = common::cost_grid(&["......", "......", "......", "......"]);
+    let path = found(find_path(&grid, Vector2::new(0, 0), Vector2::new(5, 3)));
+    assert_eq!(path.waypoints, vec![Vector2::new(0, 0), Vector2::new(5, 3)]);
+    assert!((path.cost - (2.0 + 3.0 * std::f32::consts::SQRT_2)).abs() < 0.001);
+}
+
+#[test]
+fn walks_around_walls_without_cutting_corners() {
+    let grid = common::cost_grid(&[
+        "..#..",
+        "..#..",
+        ".....",
+    ]);
+    let path = found(find_path(&grid, Vector2::new(0, 0), Vector2::new(4, 0)));
+    assert!(path.waypoints.iter().all(|tile| grid.is_passable(*tile)));
+    assert!(!grid.line_of_sight(Vector2::new(1, 1), Vector2::new(3, 1), f32::INFINITY));
+    // a shallow line past the end of the wall still crosses (2, 1)
+    assert!(!grid.line_of_sight(Vector2::new(1, 1), Vector2::new(3, 2), f32::INFINITY));
+    for segment in path.waypoints.windows(2) {
+        assert!(grid.line_of_sight(segment[0], segment[1], f32::INFINITY));
+    }
+}
+
+#[test]
+fn diagonal_between_two_walls_is_blocked() {
+    let grid = common::cost_grid(&[
+        ".#",
+        "#.",
+    ]);
+    assert_eq!(find_path(&grid, Vector2::new(0, 0), Vector2::new(1, 1)), PathResult::NoPath);
+}
+
+#[test]
+fn prefers_roads_and_avoids_rivers() {
+    let grid = common::cost_grid(&[
+        ".......",
+        "~~~~~~.",
+        "=======",
+    ]);
+    let path = found(find_path(&grid, Vector2::new(0, 0), Vector2::new(0, 2)));
+    // wading through the river costs 4, walking around it is cheaper
+    assert!(!path.waypoints.iter().any(|tile| grid.cost(*tile) == Some(4.0)));
+    let road = found(find_path(&grid, Vector2::new(0, 2), Vector2::new(6, 2)));
+    assert!((road.cost - 3.0).abs() < 0.001);
+}
+
+#[test]
+fn blocked_goal_has_no_path() {
+    let grid = common::cost_grid(&["..#", "..."]);
+    assert_eq!(find_path(&grid, Vector2::new(0, 0), Vector2::new(2, 0)), PathResult::NoPath);
+}
+
+#[test]
+fn jobs_share_the_budget_across_updates() {
+    let grid = CostGrid::new(Vector2::new(64, 64), Some(1.0));
+    let mut pathfinder = Pathfinder::with_budget(50);
+    let ids: Vec<_> = (0..10).map(|i| pathfinder.request(&grid, Vector2::new(0, i), Vector2::new(63, 63 - i))).collect();
+
+    pathfinder.run(&grid, None);
+    assert!(ids.iter().all(|id| pathfinder.is_pending(*id)));
+
+    let mut updates = 1;
+    while pathfinder.pending_count() > 0 {
+        pathfinder.run(&grid, None);
+        updates += 1;
+        assert!(updates < 1000);
+    }
+    for id in ids {
+        assert!(matches!(pathfinder.take_result(id), Some(PathResult::Found(_))));
+        assert_eq!(pathfinder.take_result(id), None);
+    }
+}
+
+#[test]
+fn jobs_restart_when_the_grid_changes() {
+    let mut grid = CostGrid::new(Vector2::new(32, 3), Some(1.0));
+    let mut pathfinder = Pathfinder::with_budget(5);
+    let id = pathfinder.request(&grid, Vector2::new(0, 1), Vector2::new(31, 1));
+    pathfinder.run(&grid, None);
+    for y in 0..3 {
+        grid.set_cost(Vector2::new(16, y), None);
+    }
+    while pathfinder.is_pending(id) {
+        pathfinder.run(&grid, None);
+    }
+    assert_eq!(pathfinder.take_result(id), Some(PathResult::NoPath));
+}
+
+#[test]
+fn jobs_keep_their_progress_when_far_tiles_change() {
+    let mut grid = CostGrid::new(Vector2::new(64, 16), Some(1.0));
+    let mut pathfinder = Pathfinder::with_budget(20);
+    let id = pathfinder.request(&grid, Vector2::new(0, 1), Vector2::new(63, 1));
+    let mut updates = 0;
+    while pathfinder.is_pending(id) {
+        // a change every update far from the search would restart it forever
+        grid.set_cost(Vector2::new(32, 15), if updates % 2 == 0 { Some(2.0) } else { Some(1.0) });
+        pathfinder.run(&grid, None);
+        updates += 1;
+        assert!(updates < 1000);
+    }
+    let path = found(pathfinder.take_result(id).unwrap());
+    assert_eq!(path.waypoints, vec![Vector2::new(0, 1), Vector2::new(63, 1)]);
+}
+
+#[test]
+fn smoothing_is_charged_to_the_budget() {
+    let grid = CostGrid::new(Vector2::new(200, 1), Some(1.0));
+    let mut pathfinder = Pathfinder::with_budget(1000);
+    let id = pathfinder.request(&grid, Vector2::new(0, 0), Vector2::new(199, 0));
+    // the search itself fits in one update, checking the long straight line does not
+    pathfinder.run(&grid, None);
+    assert!(pathfinder.is_pending(id));
+    while pathfinder.is_pending(id) {
+        pathfinder.run(&grid, None);
+    }
+    let path = found(pathfinder.take_result(id).unwrap());
+    assert_eq!(path.waypoints, vec![Vector2::new(0, 0), Vector2::new(199, 0)]);
+}
@@ -0,0 +1,49 @@
+use castle_sim::components::cs_render::shader_types::tile_animation::pack_frame;
+use castle_sim::components::cs_world::tile_registry::{AnimationMode, TileAnimation, TileRegistry, TileRegistryError, MIN_FRAME_DURATION};
+
+#[test]
+fn loops_wrap_and_ping_pongs_turn_at_both_ends() {
+    let frames = |mode: AnimationMode, count: u32| (0..10).map(|step| mode.frame(step, count)).collect::<Vec<_>>();
+    assert_eq!(frames(AnimationMode::Loop, 3), vec![0, 1, 2, 0, 1, 2, 0, 1, 2, 0]);
+    assert_eq!(frames(AnimationMode::PingPong, 3), vec![0, 1, 2, 1, 0, 1, 2, 1, 0, 1]);
+    assert_eq!(frames(AnimationMode::PingPong, 2), vec![0, 1, 0, 1, 0, 1, 0, 1, 0, 1]);
+    // single frames and empty animations stay on the first frame
+    assert_eq!(frames(AnimationMode::PingPong, 1), vec![0; 10]);
+    assert_eq!(frames(AnimationMode::Loop, 0), vec![0; 10]);
+
+    let animation = TileAnimation { frames: vec![[0, 0], [1, 0], [2, 0]], frame_duration: 0.5, mode: AnimationMode::PingPong };
+    assert_eq!(animation.frame_at(0.4), 0);
+    assert_eq!(animation.frame_at(1.2), 2);
+    assert_eq!(animation.frame_at(1.6), 1);
+    let still = TileAnimation { frame_duration: 0.0, ..animation };
+    assert!(still.frame_at(10.0) < 3);
+}
+
+#[test]
+fn animation_tables_list_every_animated_kind_in_id_order() {
+    let registry = TileRegistry::default();
+    let (frames, animations) = registry.animation_table();
+    let animated: Vec<&TileAnimation> = registry.iter().filter_map(|(_, definition)| definition.animation.as_ref()).collect();
+    assert_eq!(animations.len(), animated.len());
+    assert_eq!(frames.len(), animated.iter().map(|animation| animation.frames.len()).sum::<usize>());
+
+    let water = registry.id("water").unwrap();
+    let entry = animations[registry.animation_id(water) as usize - 1];
+    let animation = registry.get(water).animation.as_ref().unwrap();
+    assert_eq!(entry.frame_count, animation.frames.len() as u32);
+    assert_eq!(entry.mode, AnimationMode::PingPong as u32);
+    let first = entry.first_frame as usize;
+    assert_eq!(&frames[first..first + animation.frames.len()], animation.frames.iter().map(|frame| pack_frame(*frame)).collect::<Vec<_>>());
+    assert_eq!(pack_frame([3, 1]), 0x0103);
+    assert_eq!(registry.animation_id(registry.id("grass").unwrap()), 0);
+    assert!(animations.iter().all(|animation| animation.frame_duration >= MIN_FRAME_DURATION));
+}
+
+#[test]
+fn animations_without_duration_are_rejected() {
+    let registry = TileRegistry::from_ron(r#"[
+        (name: "flag", atlas_coordinate: (0, 10), minimap_color: (1, 2, 3),
+            animation: Some((frames: [(0, 0), (1, 0)], frame_duration: 0.0, mode: Loop))),
+    ]"#);
+    assert!(matches!(registry, Err(TileRegistryError::InvalidAnimation(name)) if name == "flag"));
+}
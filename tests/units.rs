@@ -0,0 +1,145 @@
+use bevy_ecs::schedule::Schedule;
+use bevy_ecs::world::World;
+use castle_sim::components::cs_render::sprite::unit_sprite;
+use castle_sim::components::cs_util::time::GameTime;
+use castle_sim::components::cs_world::hierarchical_pathfinding::PortalGraph;
+use castle_sim::components::cs_world::pathfinding::{self, CostGrid, Pathfinder};
+use castle_sim::components::cs_world::position::TilePosition;
+use castle_sim::components::cs_world::tile_registry::AnimationMode;
+use castle_sim::components::cs_world::unit::{
+    AnimationPlayer, Facing, PathFollower, UnitAnimation, UnitAnimationState, UnitBundle, UnitRegistry, SPRITE_SIZE,
+};
+use cgmath::Vector2;
+
+#[test]
+fn facing_follows_the_isometric_screen() {
+    // moving along +x goes down and to the right on screen
+    assert_eq!(Facing::from_direction(Vector2::new(1.0, 0.0)), Some(Facing::SouthEast));
+    assert_eq!(Facing::from_direction(Vector2::new(0.0, 1.0)), Some(Facing::SouthWest));
+    assert_eq!(Facing::from_direction(Vector2::new(1.0, 1.0)), Some(Facing::South));
+    assert_eq!(Facing::from_direction(Vector2::new(-1.0, -1.0)), Some(Facing::North));
+    assert_eq!(Facing::from_direction(Vector2::new(1.0, -1.0)), Some(Facing::East));
+    assert_eq!(Facing::from_direction(Vector2::new(-1.0, 1.0)), Some(Facing::West));
+    assert_eq!(Facing::from_direction(Vector2::new(0.0, 0.0)), None);
+}
+
+#[test]
+fn animations_pick_their_frames() {
+    let walk = UnitAnimation::new(1, 4, 0.1, AnimationMode::Loop);
+    let columns: Vec<u32> = (0..6).map(|step| walk.column(step as f32 * 0.1 + 0.05)).collect();
+    assert_eq!(columns, vec![1, 2, 3, 4, 1, 2]);
+    let bounce = UnitAnimation::new(0, 3, 0.1, AnimationMode::PingPong);
+    let columns: Vec<u32> = (0..6).map(|step| bounce.column(step as f32 * 0.1 + 0.05)).collect();
+    assert_eq!(columns, vec![0, 1, 2, 1, 0, 1]);
+    // a zero frame duration stays on the frames instead of dividing by zero
+    let instant = UnitAnimation::new(2, 3, 0.0, AnimationMode::PingPong);
+    assert!((0..4).map(|step| instant.column(step as f32 * 0.5)).all(|column| (2..5).contains(&column)));
+    assert_eq!(UnitAnimation::new(1, 0, 0.1, AnimationMode::Loop).column(1.0), 1);
+
+    let registry = UnitRegistry::default();
+    let soldier = registry.get(registry.id("soldier").unwrap());
+    let animation = AnimationPlayer { state: UnitAnimationState::Idle, elapsed: 3.0 };
+    let sheet = Vector2::new(SPRITE_SIZE.x * 5, SPRITE_SIZE.y * 24);
+    let sprite = unit_sprite(soldier, Vector2::new(0.0, 0.0), Facing::West, &animation, sheet);
+    assert_eq!(sprite.uv_min, [0.0, 12.0 / 24.0]);
+    assert_eq!(sprite.uv_max, [0.2, 13.0 / 24.0]);
+    // the feet stand on the position
+    assert_eq!(sprite.position[0] + sprite.size[0] / 2.0, 0.0);
+}
+
+#[test]
+fn units_walk_their_path_and_stop() {
+    let mut grid = CostGrid::new(Vector2::new(12, 8), Some(1.0));
+    for y in 0..6 {
+        grid.set_cost(Vector2::new(5, y), None);
+    }
+
+    let mut world = World::new();
+    let mut schedule = Schedule::default();
+    world.insert_resource(GameTime { elapsed: 0.0, delta: 0.01 });
+    // without a map to keep the grid in sync only the path jobs run
+    world.insert_resource(Pathfinder::default());
+    world.insert_resource(grid);
+    schedule.add_system(pathfinding::run_path_jobs);
+    let registry = UnitRegistry::default();
+    let villager = world.spawn((
+        UnitBundle::new(registry.id("villager").unwrap(), Vector2::new(1.5, 1.5)),
+        PathFollower::new(Vector2::new(9, 1)),
+    )).id();
+    registry.register(&mut world, &mut schedule);
+
+    let mut walked = false;
+    for _ in 0..2000 {
+        schedule.run(&mut world);
+        let animation = world.get::<AnimationPlayer>(villager).unwrap();
+        walked |= animation.state == UnitAnimationState::Walk;
+        let position = world.get::<TilePosition>(villager).unwrap();
+        assert!(world.resource::<CostGrid>().is_passable(position.tile()));
+    }
+
+    assert!(walked);
+    assert!(world.get::<PathFollower>(villager).is_none());
+    assert_eq!(world.get::<TilePosition>(villager).unwrap().0, Vector2::new(9.5, 1.5));
+    assert_eq!(world.get::<AnimationPlayer>(villager).unwrap().state, UnitAnimationState::Idle);
+    // the last leg climbs back up along the wall, which is to the right on screen
+    assert_eq!(*world.get::<Facing>(villager).unwrap(), Facing::East);
+}
+
+#[test]
+fn long_paths_are_refined_while_walking() {
+    let mut grid = CostGrid::new(Vector2::new(64, 16), Some(1.0));
+    for y in 2..16 {
+        grid.set_cost(Vector2::new(30, y), None);
+    }
+
+    let mut world = World::new();
+    let mut schedule = Schedule::default();
+    world.insert_resource(GameTime { elapsed: 0.0, delta: 0.05 });
+    world.insert_resource(PortalGraph::build(&grid));
+    world.insert_resource(Pathfinder::default());
+    world.insert_resource(grid);
+    schedule.add_system(pathfinding::run_path_jobs);
+    let registry = UnitRegistry::default();
+    let villager = world.spawn((
+        UnitBundle::new(registry.id("villager").unwrap(), Vector2::new(1.5, 12.5)),
+        PathFollower::new(Vector2::new(60, 12)),
+    )).id();
+    registry.register(&mut world, &mut schedule);
+
+    for _ in 0..3000 {
+        schedule.run(&mut world);
+        let position = world.get::<TilePosition>(villager).unwrap();
+        assert!(world.resource::<CostGrid>().is_passable(position.tile()));
+        if world.get::<PathFollower>(villager).is_none() {
+            break;
+        }
+    }
+    assert!(world.get::<PathFollower>(villager).is_none());
+    assert_eq!(world.get::<TilePosition>(villager).unwrap().0, Vector2::new(60.5, 12.5));
+}
+
+#[test]
+fn requests_of_removed_followers_are_cancelled() {
+    let mut world = World::new();
+    let mut schedule = Schedule::default();
+    world.insert_resource(GameTime { elapsed: 0.0, delta: 0.01 });
+    // a tiny budget keeps the requests waiting
+    world.insert_resource(Pathfinder::with_budget(1));
+    world.insert_resource(CostGrid::new(Vector2::new(32, 32), Some(1.0)));
+    schedule.add_system(pathfinding::run_path_jobs);
+    let registry = UnitRegistry::default();
+    let kind = registry.id("soldier").unwrap();
+    let followers: Vec<_> = (0..4)
+        .map(|i| world.spawn((UnitBundle::new(kind, Vector2::new(0.5, i as f32 + 0.5)), PathFollower::new(Vector2::new(30, 30)))).id())
+        .collect();
+    registry.register(&mut world, &mut schedule);
+    schedule.run(&mut world);
+    assert_eq!(world.resource::<Pathfinder>().pending_count(), 4);
+
+    world.entity_mut(followers[0]).remove::<PathFollower>();
+    world.despawn(followers[1]);
+    world.entity_mut(followers[2]).insert(PathFollower::new(Vector2::new(20, 20)));
+    schedule.run(&mut world);
+    // the replaced follower waits for its new path, the untouched one for its old path
+    assert_eq!(world.resource::<Pathfinder>().pending_count(), 2);
+}
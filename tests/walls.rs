@@ -0,0 +1,161 @@
+mod common;
+
+use bevy_ecs::world::{Mut, World};
+use castle_sim::components::cs_render::overlay::TileOverlay;
+use castle_sim::components::cs_ui::ui_layer::UiFocus;
+use castle_sim::components::cs_util::input::{Cursor, Input};
+use castle_sim::components::cs_world::building::{BuildingCost, Occupancy, PlacementError};
+use castle_sim::components::cs_world::history::{self, History};
+use castle_sim::components::cs_world::map::{Map, TilePreview};
+use castle_sim::components::cs_world::placement::{self, WallMode, WallTool};
+use castle_sim::components::cs_world::tile_registry::TileRegistry;
+use castle_sim::components::cs_world::unit::UnitBundle;
+use castle_sim::components::cs_world::wall::{self, WallPiece, TOWER_SPACING};
+use cgmath::Vector2;
+use common::tile_name;
+use winit::event::{MouseButton, VirtualKeyCode};
+
+fn world() -> World {
+    let mut world = common::map_world(16);
+    world.resource_scope(|world, mut map: Mut<Map>| {
+        let tile_registry = world.resource::<TileRegistry>();
+        map.set_tile(Vector2::new(12, 2), tile_registry.id("water").unwrap(), tile_registry);
+    });
+    world.insert_resource(Occupancy::new(Vector2::new(16, 16)));
+    world
+}
+
+#[test]
+fn drags_follow_the_map_axes() {
+    let straight = wall::drag_path(Vector2::new(2, 3), Vector2::new(2, 0));
+    assert_eq!(straight, vec![Vector2::new(2, 3), Vector2::new(2, 2), Vector2::new(2, 1), Vector2::new(2, 0)]);
+    assert_eq!(wall::drag_path(Vector2::new(4, 4), Vector2::new(4, 4)), vec![Vector2::new(4, 4)]);
+
+    // the longer side comes first and the corner is only visited once
+    let bent = wall::drag_path(Vector2::new(0, 0), Vector2::new(3, 1));
+    assert_eq!(bent, vec![Vector2::new(0, 0), Vector2::new(1, 0), Vector2::new(2, 0), Vector2::new(3, 0), Vector2::new(3, 1)]);
+    let bent = wall::drag_path(Vector2::new(0, 0), Vector2::new(-1, -3));
+    assert_eq!(bent.len(), 5);
+    assert_eq!(bent[3], Vector2::new(0, -3));
+}
+
+#[test]
+fn plans_skip_blocked_tiles_and_add_up_costs() {
+    let mut world = world();
+    world.spawn(UnitBundle::new(0, Vector2::new(5.5, 2.5)));
+    let path = wall::drag_path(Vector2::new(2, 2), Vector2::new(13, 2));
+    let plan = wall::plan_walls(&mut world, &path, false);
+    assert_eq!(plan.changes.len(), 10);
+    assert_eq!(plan.cost, BuildingCost { stone: 20, ..Default::default() });
+    assert_eq!(plan.blocked, vec![(Vector2::new(5, 2), PlacementError::UnitsInTheWay), (Vector2::new(12, 2), PlacementError::WrongTerrain("water".to_string()))]);
+    assert_eq!(plan.blocked_reason().unwrap(), "units are in the way");
+
+    // towers go on the ends, the corner and every few segments in between
+    let path = wall::drag_path(Vector2::new(0, 8), Vector2::new(TOWER_SPACING as i32 + 2, 10));
+    let plan = wall::plan_walls(&mut world, &path, true);
+    let tower = world.resource::<TileRegistry>().id(WallPiece::Tower.tile_name()).unwrap();
+    let towers: Vec<Vector2<i32>> = plan.changes.iter().filter(|(_, kind)| *kind == tower).map(|(tile, _)| *tile).collect();
+    assert_eq!(towers, vec![Vector2::new(0, 8), Vector2::new(TOWER_SPACING as i32, 8), Vector2::new(TOWER_SPACING as i32 + 2, 8), Vector2::new(TOWER_SPACING as i32 + 2, 10)]);
+}
+
+#[test]
+fn a_drag_is_built_and_taken_back_as_one_edit() {
+    let mut world = world();
+    let path = wall::drag_path(Vector2::new(1, 1), Vector2::new(4, 3));
+    let plan = wall::plan_walls(&mut world, &path, false);
+    wall::build_walls(&mut world, &plan, "build walls");
+    assert_eq!(world.resource::<History>().undo_len(), 1);
+    for tile in path.iter() {
+        assert_eq!(tile_name(&world, *tile), "wall");
+    }
+
+    // dragging over existing walls only upgrades them to towers
+    let plan = wall::plan_walls(&mut world, &path, true);
+    assert_eq!(plan.changes.len(), 3);
+    wall::build_walls(&mut world, &plan, "build walls");
+    assert_eq!(tile_name(&world, Vector2::new(4, 1)), "wall_tower");
+    assert!(history::undo(&mut world));
+    assert_eq!(tile_name(&world, Vector2::new(4, 1)), "wall");
+
+    // nothing to build is not worth an undo step
+    let plan = wall::plan_walls(&mut world, &path, false);
+    wall::build_walls(&mut world, &plan, "build walls");
+    assert_eq!(world.resource::<History>().undo_len(), 1);
+    assert!(history::undo(&mut world));
+    for tile in path.iter() {
+        assert_eq!(tile_name(&world, *tile), "grass");
+    }
+}
+
+#[test]
+fn releasing_off_the_map_drops_the_drag() {
+    let mut world = world();
+    world.init_resource::<Input<VirtualKeyCode>>();
+    world.init_resource::<Input<MouseButton>>();
+    world.init_resource::<Cursor>();
+    world.init_resource::<UiFocus>();
+    world.init_resource::<TileOverlay>();
+    world.init_resource::<TilePreview>();
+    world.init_resource::<WallTool>();
+    world.resource_mut::<WallTool>().select(Some(WallMode::Walls));
+
+    world.resource_mut::<TileOverlay>().hovered_tile = Some(Vector2::new(2, 2));
+    world.resource_mut::<Input<MouseButton>>().press(MouseButton::Left);
+    placement::update_wall_tool(&mut world);
+    assert!(world.resource::<WallTool>().is_dragging());
+
+    world.resource_mut::<TileOverlay>().hovered_tile = None;
+    world.resource_mut::<Input<MouseButton>>().clear();
+    world.resource_mut::<Input<MouseButton>>().release(MouseButton::Left);
+    placement::update_wall_tool(&mut world);
+    assert!(!world.resource::<WallTool>().is_dragging());
+
+    world.resource_mut::<TileOverlay>().hovered_tile = Some(Vector2::new(5, 2));
+    world.resource_mut::<Input<MouseButton>>().clear();
+    placement::update_wall_tool(&mut world);
+    assert_eq!(world.resource::<TilePreview>().tiles.len(), 1);
+    assert_eq!(world.resource::<History>().undo_len(), 0);
+}
+
+#[test]
+fn gatehouses_replace_straight_segments() {
+    let mut world = world();
+    let plan = wall::plan_walls(&mut world, &wall::drag_path(Vector2::new(2, 5), Vector2::new(8, 7)), false);
+    wall::build_walls(&mut world, &plan, "build walls");
+
+    assert_eq!(wall::plan_gatehouse(&world, Vector2::new(5, 4)), Err(PlacementError::NotOnWall));
+    assert_eq!(wall::plan_gatehouse(&world, Vector2::new(2, 5)), Err(PlacementError::NotStraight));
+    assert_eq!(wall::plan_gatehouse(&world, Vector2::new(8, 5)), Err(PlacementError::NotStraight));
+    assert_eq!(wall::plan_gatehouse(&world, Vector2::new(-1, 5)), Err(PlacementError::OutsideMap));
+
+    let plan = wall::plan_gatehouse(&world, Vector2::new(5, 5)).unwrap();
+    assert_eq!(plan.cost, WallPiece::Gatehouse.cost() + WallPiece::Tower.cost() + WallPiece::Tower.cost());
+    wall::build_walls(&mut world, &plan, "build gatehouse");
+    assert_eq!(tile_name(&world, Vector2::new(4, 5)), "wall_tower");
+    assert_eq!(tile_name(&world, Vector2::new(5, 5)), "gatehouse");
+    assert_eq!(tile_name(&world, Vector2::new(6, 5)), "wall_tower");
+    // the gate sits in the wall line and is no wall to put another gate into
+    assert_eq!(wall::plan_gatehouse(&world, Vector2::new(5, 5)), Err(PlacementError::NotOnWall));
+    // the short leg between the corner and the end still fits one
+    assert!(wall::plan_gatehouse(&world, Vector2::new(8, 6)).is_ok());
+}
+
+#[test]
+fn previews_join_with_the_walls_around_them() {
+    let registry = TileRegistry::default();
+    let wall_kind = registry.id("wall").unwrap();
+    let mut map = Map::new(Vector2::new(8, 8), registry.id("grass").unwrap(), &registry);
+    map.set_tile(Vector2::new(3, 3), wall_kind, &registry);
+    let before: Vec<[u8; 2]> = map.tiles.iter().map(|tile| tile.atlas_coordinate.coordinate).collect();
+
+    let preview = map.preview_tiles(&[(Vector2::new(4, 3), wall_kind)], &registry);
+    assert_eq!(preview.len(), 9);
+    assert!(map.tiles.iter().map(|tile| tile.atlas_coordinate.coordinate).eq(before.iter().copied()));
+    let existing = preview.iter().find(|(index, _)| *index == map.index(Vector2::new(3, 3))).unwrap().1;
+    let planned = preview.iter().find(|(index, _)| *index == map.index(Vector2::new(4, 3))).unwrap().1;
+
+    map.set_tile(Vector2::new(4, 3), wall_kind, &registry);
+    assert_eq!(existing.atlas_coordinate.coordinate, map.tiles[map.index(Vector2::new(3, 3))].atlas_coordinate.coordinate);
+    assert_eq!(planned.atlas_coordinate.coordinate, map.tiles[map.index(Vector2::new(4, 3))].atlas_coordinate.coordinate);
+    assert_ne!(existing.atlas_coordinate.coordinate, before[map.index(Vector2::new(3, 3))]);
+}
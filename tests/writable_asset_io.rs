@@ -0,0 +1,40 @@
+mod common;
+
+use std::fs;
+
+use castle_sim::components::cs_io::{AssetIoError, InMemoryAssetIo, UserDataAssetIo, WritableAssetIo};
+use common::path;
+
+/// Save, overwrite, list and delete behave the same for every backend.
+fn save_list_delete(storage: &dyn WritableAssetIo) {
+    pollster::block_on(async {
+        let saves = path("saves");
+        assert!(storage.list(&saves).await.unwrap().is_empty());
+
+        storage.save(&path("saves/castle.csmp"), &[1, 2]).await.unwrap();
+        storage.save(&path("saves/castle.csmp"), &[3]).await.unwrap();
+        storage.save(&path("saves/autosave.csmp"), &[4]).await.unwrap();
+        storage.save(&path("saves/replays/first.replay"), &[5]).await.unwrap();
+        storage.save(&path("settings.ron"), &[6]).await.unwrap();
+
+        assert_eq!(storage.load_path(&path("saves/castle.csmp")).await.unwrap(), vec![3]);
+        assert_eq!(storage.list(&saves).await.unwrap(), vec![path("saves/autosave.csmp"), path("saves/castle.csmp")]);
+
+        storage.delete(&path("saves/castle.csmp")).await.unwrap();
+        assert!(matches!(storage.load_path(&path("saves/castle.csmp")).await, Err(AssetIoError::NotFound(_))));
+        assert!(matches!(storage.delete(&path("saves/castle.csmp")).await, Err(AssetIoError::NotFound(_))));
+        assert_eq!(storage.list(&saves).await.unwrap(), vec![path("saves/autosave.csmp")]);
+    });
+}
+
+#[test]
+fn in_memory_storage() {
+    save_list_delete(&InMemoryAssetIo::new());
+}
+
+#[test]
+fn user_data_directory() {
+    let root = std::env::temp_dir().join(format!("castle_sim_user_data_{}", std::process::id()));
+    save_list_delete(&UserDataAssetIo::new(&root));
+    fs::remove_dir_all(root).unwrap();
+}